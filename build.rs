@@ -1,17 +1,45 @@
 use std::io::Result;
 
+// The service generator is shared with the `codegen` feature of the library
+#[allow(dead_code)]
+#[path = "src/codegen.rs"]
+mod codegen;
+
 fn main() -> Result<()> {
     // Tell Cargo to re-run this script if the proto files change
     println!("cargo:rerun-if-changed=protos/");
+    println!("cargo:rerun-if-changed=src/codegen.rs");
     
     // Define output path for the generated code
-    let _out_dir = std::env::var("OUT_DIR").unwrap();
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    
+    // Generate the messages of every proto file, keeping their descriptors for the reflection service
+    let mut config = prost_build::Config::new();
+    config.file_descriptor_set_path(out_dir.join("quicserve_descriptor.bin"));
+    config.type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]");
     
-    // Generate code from proto files
-    prost_build::compile_protos(
-        &["src/protos/service.proto"],
+    // The built-in services use their generated messages directly, with payloads as
+    // `Bytes` and every JSON field optional like in proto3
+    config.bytes([".quicserve.reflection", ".quicserve.pubsub"]);
+    config.message_attribute(".quicserve.reflection", "#[serde(default)]");
+    config.message_attribute(".quicserve.health", "#[serde(default)]");
+    config.message_attribute(".quicserve.pubsub", "#[serde(default)]");
+    
+    config.compile_protos(
+        &["src/protos/service.proto", "src/protos/reflection.proto", "src/protos/health.proto", "src/protos/pubsub.proto"],
         &["src/protos/"]
     )?;
     
+    // Generate typed servers and clients for the sample service only, referring to this crate.
+    // The built-in services are implemented by hand and registered under their own names,
+    // so generated stubs for them would call methods that don't exist. This pass writes the
+    // final `quicserve.rs`, so datagram payloads are made `Bytes` here
+    let mut config = prost_build::Config::new();
+    config.bytes([".quicserve.DatagramProto"]);
+    codegen::ServiceGenerator::new()
+        .with_crate_path("crate")
+        .configure(&mut config);
+    config.compile_protos(&["src/protos/service.proto"], &["src/protos/"])?;
+    
     Ok(())
-}
\ No newline at end of file
+}
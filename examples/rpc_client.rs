@@ -7,7 +7,8 @@ use log::{debug, info, warn};
 use prost::Message;
 use serde::{Deserialize, Serialize};
 
-use quicserve::{Client, Config};
+use futures_util::{SinkExt, StreamExt};
+use quicserve::{CallOptions, Client, Config};
 
 // Include the generated protobuf code
 include!(concat!(env!("OUT_DIR"), "/quicserve.rs"));
@@ -42,6 +43,41 @@ async fn main() -> Result<()> {
     let response: EchoResponse = client.call("echo.echo", &request).await?;
     info!("Echo response: {}", response.message);
     
+    // Call echo service with metadata
+    let options = CallOptions::new()
+        .with_metadata("x-trace-id", "example-trace")?;
+    let response = client.call_with_options::<_, EchoResponse>("echo.echo", &request, options).await?;
+    info!(
+        "Echo response: {} (trace id: {:?})",
+        response.message.message,
+        response.headers.get_str("x-trace-id"),
+    );
+    
+    // Call streaming method
+    info!("Calling echo stream...");
+    let stream_request = StreamRequest {
+        count: 5,
+        interval_ms: 100,
+    };
+    
+    let mut stream = client.call_stream::<_, StreamResponse>("echo.stream", &stream_request).await?;
+    while let Some(response) = stream.next().await {
+        let response = response?;
+        info!("Stream response {}: {}", response.sequence, response.payload);
+    }
+    
+    // Open bidirectional streaming call
+    info!("Opening echo chat...");
+    let (mut sink, mut replies) = client.open_stream::<EchoRequest, EchoResponse>("echo.chat").await?;
+    for i in 0..3 {
+        sink.send(EchoRequest { message: format!("Chat message {}", i) }).await?;
+    }
+    sink.close().await?;
+    
+    while let Some(reply) = replies.next().await {
+        info!("Chat reply: {}", reply?.message);
+    }
+    
     // Call compute service
     info!("Calling compute service for matrix multiplication...");
     #[derive(Serialize, Deserialize)]
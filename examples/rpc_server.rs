@@ -10,7 +10,8 @@ use prost::Message;
 use serde::{Deserialize, Serialize};
 use tokio::time;
 
-use quicserve::{Config, Error, Server, Service};
+use futures_util::StreamExt;
+use quicserve::{CallContext, Config, Error, InterceptedRequest, Interceptor, RequestStream, ResponseStream, Server, Service};
 
 // Include the generated protobuf code
 include!(concat!(env!("OUT_DIR"), "/quicserve.rs"));
@@ -27,6 +28,13 @@ impl Service for EchoService {
                 let request = EchoRequest::decode(payload)
                     .map_err(|e| Error::Decoding(e))?;
                 
+                // Echo the caller's trace ID back as a response header
+                if let Some(context) = CallContext::current() {
+                    if let Some(trace_id) = context.metadata().get("x-trace-id") {
+                        context.set_header("x-trace-id", trace_id.clone())?;
+                    }
+                }
+                
                 // Create response
                 let response = EchoResponse {
                     message: format!("Echo: {}", request.message),
@@ -39,35 +47,92 @@ impl Service for EchoService {
                 
                 Ok(buf.freeze())
             }
+            _ => Err(Error::MethodNotFound(method.to_string())),
+        }
+    }
+    
+    async fn call_stream(&self, method: &str, payload: Bytes) -> Result<ResponseStream, Error> {
+        match method {
             "stream" => {
-                // This is just an example implementation since bidirectional streaming
-                // would be implemented differently - here we just return the first response
+                // Deserialize request
                 let request = StreamRequest::decode(payload)
                     .map_err(|e| Error::Decoding(e))?;
                 
-                // Create first response in the stream
-                let response = StreamResponse {
-                    sequence: 1,
-                    payload: format!("First response of {}", request.count),
-                    timestamp: std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs() as i64,
-                };
+                let interval = Duration::from_millis(request.interval_ms.max(0) as u64);
                 
-                // Serialize response
-                let mut buf = BytesMut::with_capacity(response.encoded_len());
-                response.encode(&mut buf)
-                    .map_err(|e| Error::Encoding(e))?;
+                // Produce one response per interval
+                let stream = futures_util::stream::iter(1..=request.count.max(0))
+                    .then(move |sequence| async move {
+                        time::sleep(interval).await;
+                        
+                        // Create response
+                        let response = StreamResponse {
+                            sequence,
+                            payload: format!("Response {}", sequence),
+                            timestamp: std::time::SystemTime::now()
+                                .duration_since(std::time::UNIX_EPOCH)
+                                .unwrap()
+                                .as_secs() as i64,
+                        };
+                        
+                        // Serialize response
+                        let mut buf = BytesMut::with_capacity(response.encoded_len());
+                        response.encode(&mut buf)
+                            .map_err(|e| Error::Encoding(e))?;
+                        
+                        Ok(buf.freeze())
+                    });
                 
-                Ok(buf.freeze())
+                Ok(Box::pin(stream))
+            }
+            _ => Err(Error::MethodNotFound(method.to_string())),
+        }
+    }
+    
+    async fn call_bidi(&self, method: &str, requests: RequestStream) -> Result<ResponseStream, Error> {
+        match method {
+            "chat" => {
+                // Echo every message the client sends until it half-closes the call
+                let stream = requests.map(|payload| {
+                    let request = EchoRequest::decode(payload?)
+                        .map_err(|e| Error::Decoding(e))?;
+                    
+                    let response = EchoResponse {
+                        message: format!("Echo: {}", request.message),
+                    };
+                    
+                    let mut buf = BytesMut::with_capacity(response.encoded_len());
+                    response.encode(&mut buf)
+                        .map_err(|e| Error::Encoding(e))?;
+                    
+                    Ok(buf.freeze())
+                });
+                
+                Ok(Box::pin(stream))
             }
             _ => Err(Error::MethodNotFound(method.to_string())),
         }
     }
     
     fn methods(&self) -> Vec<String> {
-        vec!["echo".into(), "stream".into()]
+        vec!["echo".into(), "stream".into(), "chat".into()]
+    }
+}
+
+// Interceptor logging every call
+struct LoggingInterceptor;
+
+#[async_trait]
+impl Interceptor for LoggingInterceptor {
+    async fn on_request(&self, request: &mut InterceptedRequest) -> Result<(), Error> {
+        info!("Call to {} from {}", request.method, request.peer_addr);
+        Ok(())
+    }
+    
+    async fn on_response(&self, request: &InterceptedRequest, response: &mut Result<Bytes, Error>) {
+        if let Err(e) = response {
+            warn!("Call to {} failed: {}", request.method, e);
+        }
     }
 }
 
@@ -199,10 +264,18 @@ async fn main() -> Result<()> {
     server.register_service("echo", EchoService).await?;
     server.register_service("compute", ComputeService).await?;
     
+    // Log every call
+    server.add_interceptor(LoggingInterceptor).await?;
+    
     info!("Server started. Press Ctrl+C to quit.");
     
     // Start the server
-    server.serve().await?;
+    let handle = server.serve().await?;
+    
+    // Shut down gracefully on Ctrl+C
+    tokio::signal::ctrl_c().await?;
+    info!("Shutting down...");
+    handle.shutdown_with_deadline(Duration::from_secs(10)).await;
     
     Ok(())
 }
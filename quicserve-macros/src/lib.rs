@@ -0,0 +1,481 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Error, Expr, ExprLit, FnArg, GenericArgument, Ident, ItemTrait, Lit,
+    Meta, PathArguments, ReturnType, Token, TraitItem, TraitItemFn, Type,
+};
+
+/// Defines a QuicServe service from a Rust trait
+///
+/// Every method of the trait must be `async`, take `&self`, optionally a
+/// `&CallContext`, then a single request, and return a `Result`. A request of type
+/// `TypedStream<T>` makes the method client-streaming, and a response of type
+/// `TypedStream<T>` makes it server-streaming; with both it is bidirectional.
+///
+/// Next to the trait, the macro generates:
+///
+/// - a `<TRAIT>_NAME` constant holding the name to register the service under,
+/// - a `<Trait>Server` adapter serving an implementation of the trait as a `Service`,
+///   (de)serializing payloads in the session's serialization format,
+/// - a `<Trait>Client` stub with one typed method per trait method.
+///
+/// Methods are called by their Rust names. The service is named after the trait in
+/// snake_case unless `name = "..."` is given, and `crate = "..."` sets the path the
+/// generated code refers to QuicServe by.
+///
+/// ```ignore
+/// #[quicserve::service(name = "greeter")]
+/// pub trait Greeter {
+///     async fn greet(&self, request: GreetRequest) -> Result<GreetReply, Error>;
+///     async fn countdown(&self, context: &CallContext, request: CountRequest) -> Result<TypedStream<Tick>, Error>;
+/// }
+///
+/// server.register_service(GREETER_NAME, GreeterServer::new(MyGreeter)).await?;
+/// let reply = GreeterClient::new(client).greet(&request).await?;
+/// ```
+#[proc_macro_attribute]
+pub fn service(args: TokenStream, input: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(input as ItemTrait);
+    match expand_service(args.into(), item) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Arguments of the `service` attribute
+struct ServiceArgs {
+    /// Name the service is registered under
+    name: Option<String>,
+    /// Path the generated code refers to QuicServe by
+    crate_path: syn::Path,
+}
+
+impl ServiceArgs {
+    /// Parses `name = "..."` and `crate = "..."` arguments
+    fn parse(args: TokenStream2) -> syn::Result<Self> {
+        let mut parsed = Self {
+            name: None,
+            crate_path: parse_quote!(::quicserve),
+        };
+        
+        let metas = Punctuated::<Meta, Token![,]>::parse_terminated.parse2(args)?;
+        for meta in metas {
+            let name_value = match &meta {
+                Meta::NameValue(name_value) => name_value,
+                _ => return Err(Error::new(meta.span(), "expected `name = \"...\"` or `crate = \"...\"`")),
+            };
+            let value = match &name_value.value {
+                Expr::Lit(ExprLit { lit: Lit::Str(value), .. }) => value,
+                other => return Err(Error::new(other.span(), "expected a string literal")),
+            };
+            
+            if name_value.path.is_ident("name") {
+                parsed.name = Some(value.value());
+            } else if name_value.path.is_ident("crate") {
+                parsed.crate_path = value.parse()?;
+            } else {
+                return Err(Error::new(name_value.path.span(), "unknown argument, expected `name` or `crate`"));
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// A method of a service trait
+struct ServiceMethod {
+    /// Name of the method, also its name on the wire
+    name: Ident,
+    /// Doc comments of the method, repeated on the client
+    docs: Vec<Attribute>,
+    /// Whether the method takes the call context
+    takes_context: bool,
+    /// Type of the request message
+    request: Type,
+    /// Type of the response message
+    response: Type,
+    /// Whether the client sends a stream of requests
+    client_streaming: bool,
+    /// Whether the server replies with a stream of responses
+    server_streaming: bool,
+}
+
+impl ServiceMethod {
+    /// Checks the signature of a trait method and extracts its message types
+    fn parse(method: &TraitItemFn) -> syn::Result<Self> {
+        let sig = &method.sig;
+        if sig.asyncness.is_none() {
+            return Err(Error::new(sig.fn_token.span(), "service methods must be `async`"));
+        }
+        if !sig.generics.params.is_empty() {
+            return Err(Error::new(sig.generics.span(), "service methods can't be generic"));
+        }
+        
+        // Step 1: `&self`, then an optional context, then the request
+        let mut inputs = sig.inputs.iter();
+        match inputs.next() {
+            Some(FnArg::Receiver(receiver)) if receiver.reference.is_some() && receiver.mutability.is_none() => {}
+            _ => return Err(Error::new(sig.inputs.span(), "service methods must take `&self`")),
+        }
+        let arguments: Vec<&Type> = inputs
+            .map(|input| match input {
+                FnArg::Typed(pat_type) => Ok(&*pat_type.ty),
+                FnArg::Receiver(receiver) => Err(Error::new(receiver.span(), "unexpected receiver")),
+            })
+            .collect::<syn::Result<_>>()?;
+        let (takes_context, request) = match arguments.as_slice() {
+            [request] => (false, *request),
+            [Type::Reference(context), request] if context.mutability.is_none() && is_call_context(&context.elem) => {
+                (true, *request)
+            }
+            [context, _] => return Err(Error::new(context.span(), "the call context must be taken as `&CallContext`")),
+            _ => return Err(Error::new(
+                sig.inputs.span(),
+                "service methods take a single request, optionally preceded by `&CallContext`",
+            )),
+        };
+        
+        // Step 2: a `Result` whose success type is the response
+        let response = match &sig.output {
+            ReturnType::Type(_, ty) => result_ok_type(ty)
+                .ok_or_else(|| Error::new(ty.span(), "service methods must return a `Result`"))?,
+            ReturnType::Default => return Err(Error::new(sig.span(), "service methods must return a `Result`")),
+        };
+        
+        // Step 3: `TypedStream` requests and responses make the method streaming
+        let (request, client_streaming) = match typed_stream_item(request) {
+            Some(item) => (item.clone(), true),
+            None => (request.clone(), false),
+        };
+        let (response, server_streaming) = match typed_stream_item(response) {
+            Some(item) => (item.clone(), true),
+            None => (response.clone(), false),
+        };
+        
+        Ok(Self {
+            name: sig.ident.clone(),
+            docs: method.attrs.iter().filter(|attr| attr.path().is_ident("doc")).cloned().collect(),
+            takes_context,
+            request,
+            response,
+            client_streaming,
+            server_streaming,
+        })
+    }
+    
+    /// Returns the arguments the adapter passes to the trait method
+    fn call_arguments(&self, request: TokenStream2) -> TokenStream2 {
+        match self.takes_context {
+            true => quote!(&context, #request),
+            false => quote!(#request),
+        }
+    }
+}
+
+/// Returns the first type argument of a type whose last path segment is `name`
+fn generic_argument<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+    let path = match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => &type_path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last().filter(|segment| segment.ident == name)?;
+    match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) => arguments.args.iter().find_map(|argument| match argument {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+/// Returns whether a type names `CallContext`, by its last path segment
+fn is_call_context(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => type_path.path.segments.last()
+            .is_some_and(|segment| segment.ident == "CallContext" && segment.arguments.is_empty()),
+        _ => false,
+    }
+}
+
+/// Returns the success type of a `Result`
+fn result_ok_type(ty: &Type) -> Option<&Type> {
+    generic_argument(ty, "Result")
+}
+
+/// Returns the message type of a `TypedStream`
+fn typed_stream_item(ty: &Type) -> Option<&Type> {
+    generic_argument(ty, "TypedStream")
+}
+
+/// Converts a trait name such as `ChatRoom` to `chat_room`
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_uppercase() && previous_lower {
+            snake.push('_');
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+/// Generates the trait, its name constant, server adapter and client stub
+fn expand_service(args: TokenStream2, mut item: ItemTrait) -> syn::Result<TokenStream2> {
+    let args = ServiceArgs::parse(args)?;
+    let krate = &args.crate_path;
+    let trait_name = &item.ident;
+    let vis = &item.vis;
+    let service_name = args.name.unwrap_or_else(|| snake_case(&trait_name.to_string()));
+    let name_const = format_ident!("{}_NAME", snake_case(&trait_name.to_string()).to_uppercase());
+    let server = format_ident!("{}Server", trait_name);
+    let client = format_ident!("{}Client", trait_name);
+    
+    if !item.generics.params.is_empty() {
+        return Err(Error::new(item.generics.span(), "service traits can't be generic"));
+    }
+    
+    // Collect the methods, rejecting anything that can't be served
+    let mut methods = Vec::new();
+    for trait_item in &item.items {
+        match trait_item {
+            TraitItem::Fn(method) => methods.push(ServiceMethod::parse(method)?),
+            other => return Err(Error::new(other.span(), "service traits may only contain methods")),
+        }
+    }
+    
+    // Handlers are shared across calls and tasks
+    item.supertraits.push(parse_quote!(::core::marker::Send));
+    item.supertraits.push(parse_quote!(::core::marker::Sync));
+    item.supertraits.push(parse_quote!('static));
+    if item.colon_token.is_none() {
+        item.colon_token = Some(Default::default());
+    }
+    
+    let context = quote! {
+        let context = #krate::CallContext::current()
+            .ok_or_else(|| #krate::Error::Internal(concat!(#service_name, " called outside of a call").to_string()))?;
+        let format = context.format();
+    };
+    let not_found = quote!(_ => Err(#krate::Error::MethodNotFound(method.to_string())),);
+    
+    // Unary methods
+    let unary: Vec<TokenStream2> = methods.iter()
+        .filter(|method| !method.client_streaming && !method.server_streaming)
+        .map(|method| {
+            let name = &method.name;
+            let wire_name = name.to_string();
+            let arguments = method.call_arguments(quote!(#krate::deserialize(&payload, format)?));
+            quote! {
+                #wire_name => {
+                    let response = self.inner.#name(#arguments).await?;
+                    #krate::serialize(&response, format)
+                }
+            }
+        })
+        .collect();
+    let call = match unary.is_empty() {
+        true => quote! {
+            let _ = payload;
+            Err(#krate::Error::MethodNotFound(method.to_string()))
+        },
+        false => quote! {
+            #context
+            match method {
+                #(#unary)*
+                #not_found
+            }
+        },
+    };
+    
+    // Server-streaming methods
+    let server_streaming: Vec<TokenStream2> = methods.iter()
+        .filter(|method| !method.client_streaming && method.server_streaming)
+        .map(|method| {
+            let name = &method.name;
+            let wire_name = name.to_string();
+            let arguments = method.call_arguments(quote!(#krate::deserialize(&payload, format)?));
+            quote! {
+                #wire_name => {
+                    let responses = self.inner.#name(#arguments).await?;
+                    Ok(#krate::streaming::encode_stream(responses, format))
+                }
+            }
+        })
+        .collect();
+    let call_stream = match server_streaming.is_empty() {
+        true => quote!(),
+        false => quote! {
+            async fn call_stream(&self, method: &str, payload: #krate::Bytes) -> Result<#krate::ResponseStream, #krate::Error> {
+                #context
+                match method {
+                    #(#server_streaming)*
+                    #not_found
+                }
+            }
+        },
+    };
+    
+    // Client-streaming and bidirectional streaming methods
+    let client_streaming: Vec<TokenStream2> = methods.iter()
+        .filter(|method| method.client_streaming)
+        .map(|method| {
+            let name = &method.name;
+            let wire_name = name.to_string();
+            let arguments = method.call_arguments(quote!(#krate::streaming::decode_stream(requests, format)));
+            let reply = match method.server_streaming {
+                true => quote!(Ok(#krate::streaming::encode_stream(response, format))),
+                false => quote!(#krate::streaming::encode_single(&response, format)),
+            };
+            quote! {
+                #wire_name => {
+                    let response = self.inner.#name(#arguments).await?;
+                    #reply
+                }
+            }
+        })
+        .collect();
+    let call_bidi = match client_streaming.is_empty() {
+        true => quote!(),
+        false => quote! {
+            async fn call_bidi(&self, method: &str, requests: #krate::RequestStream) -> Result<#krate::ResponseStream, #krate::Error> {
+                #context
+                match method {
+                    #(#client_streaming)*
+                    #not_found
+                }
+            }
+        },
+    };
+    
+    let method_names: Vec<String> = methods.iter().map(|method| method.name.to_string()).collect();
+    
+    // Typed client methods, calling `service.method`
+    let client_methods: Vec<TokenStream2> = methods.iter()
+        .map(|method| {
+            let name = &method.name;
+            let with_options = format_ident!("{}_with_options", name);
+            let full_name = format!("{}.{}", service_name, name);
+            let (request, response) = (&method.request, &method.response);
+            let call_doc = match method.docs.is_empty() {
+                true => {
+                    let doc = format!("Calls `{}`", full_name);
+                    quote!(#[doc = #doc])
+                }
+                false => {
+                    let docs = &method.docs;
+                    quote!(#(#docs)*)
+                }
+            };
+            let options_doc = format!("Calls `{}` with per-call options", full_name);
+            
+            match (method.client_streaming, method.server_streaming) {
+                (false, false) => quote! {
+                    #call_doc
+                    pub async fn #name(&self, request: &#request) -> Result<#response, #krate::Error> {
+                        self.client.call(#full_name, request).await
+                    }
+                    
+                    #[doc = #options_doc]
+                    pub async fn #with_options(&self, request: &#request, options: #krate::CallOptions) -> Result<#krate::CallResponse<#response>, #krate::Error> {
+                        self.client.call_with_options(#full_name, request, options).await
+                    }
+                },
+                (false, true) => quote! {
+                    #call_doc
+                    pub async fn #name(&self, request: &#request) -> Result<#krate::CallStream<#response>, #krate::Error> {
+                        self.client.call_stream(#full_name, request).await
+                    }
+                    
+                    #[doc = #options_doc]
+                    pub async fn #with_options(&self, request: &#request, options: #krate::CallOptions) -> Result<#krate::CallStream<#response>, #krate::Error> {
+                        self.client.call_stream_with_options(#full_name, request, options).await
+                    }
+                },
+                (true, _) => quote! {
+                    #call_doc
+                    pub async fn #name(&self) -> Result<(#krate::CallSink<#request>, #krate::CallStream<#response>), #krate::Error> {
+                        self.client.open_stream(#full_name).await
+                    }
+                    
+                    #[doc = #options_doc]
+                    pub async fn #with_options(&self, options: #krate::CallOptions) -> Result<(#krate::CallSink<#request>, #krate::CallStream<#response>), #krate::Error> {
+                        self.client.open_stream_with_options(#full_name, options).await
+                    }
+                },
+            }
+        })
+        .collect();
+    
+    let name_doc = format!("Name the [`{}`] service is registered under", trait_name);
+    let server_doc = format!("Adapter serving a [`{}`] implementation as a QuicServe service", trait_name);
+    let client_doc = format!("Typed client of the [`{}`] service", trait_name);
+    let server_new_doc = format!("Creates a new {}", server);
+    let client_new_doc = format!("Creates a new {} making calls through a client", client);
+    
+    Ok(quote! {
+        #[#krate::async_trait]
+        #item
+        
+        #[doc = #name_doc]
+        #vis const #name_const: &str = #service_name;
+        
+        #[doc = #server_doc]
+        #vis struct #server<T> {
+            /// Wrapped implementation
+            inner: T,
+        }
+        
+        impl<T: #trait_name> #server<T> {
+            #[doc = #server_new_doc]
+            pub fn new(inner: T) -> Self {
+                Self { inner }
+            }
+            
+            /// Returns the wrapped implementation
+            pub fn into_inner(self) -> T {
+                self.inner
+            }
+        }
+        
+        #[#krate::async_trait]
+        impl<T: #trait_name> #krate::Service for #server<T> {
+            async fn call(&self, method: &str, payload: #krate::Bytes) -> Result<#krate::Bytes, #krate::Error> {
+                #call
+            }
+            
+            #call_stream
+            
+            #call_bidi
+            
+            fn methods(&self) -> Vec<String> {
+                vec![#(#method_names.to_string()),*]
+            }
+        }
+        
+        #[doc = #client_doc]
+        #[derive(Clone)]
+        #vis struct #client {
+            /// Client the calls are made through
+            client: #krate::Client,
+        }
+        
+        impl #client {
+            #[doc = #client_new_doc]
+            pub fn new(client: #krate::Client) -> Self {
+                Self { client }
+            }
+            
+            /// Returns the client the calls are made through
+            pub fn inner(&self) -> &#krate::Client {
+                &self.client
+            }
+            
+            #(#client_methods)*
+        }
+    })
+}
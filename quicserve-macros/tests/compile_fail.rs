@@ -0,0 +1,6 @@
+/// Checks the errors reported for traits the `service` macro can't serve
+#[test]
+fn service_macro_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,6 @@
+/// Checks that the code generated for traits the `service` macro accepts compiles
+#[test]
+fn service_macro_expansions() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/pass/*.rs");
+}
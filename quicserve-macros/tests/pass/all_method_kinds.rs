@@ -0,0 +1,54 @@
+use quicserve::{CallContext, CallOptions, CallSink, CallStream, Client, Error, Service, TypedStream};
+
+#[quicserve::service(name = "greeter")]
+pub trait Greeter {
+    /// Greets one person
+    async fn greet(&self, request: String) -> Result<String, Error>;
+    
+    async fn countdown(&self, context: &CallContext, request: u32) -> Result<TypedStream<u32>, Error>;
+    
+    async fn tally(&self, requests: TypedStream<String>) -> Result<u32, Error>;
+    
+    async fn chat(&self, context: &CallContext, requests: TypedStream<String>) -> Result<TypedStream<String>, Error>;
+}
+
+struct Greetings;
+
+#[quicserve::async_trait]
+impl Greeter for Greetings {
+    async fn greet(&self, request: String) -> Result<String, Error> {
+        Ok(request)
+    }
+    
+    async fn countdown(&self, _context: &CallContext, request: u32) -> Result<TypedStream<u32>, Error> {
+        let _ = request;
+        Err(Error::Cancelled)
+    }
+    
+    async fn tally(&self, _requests: TypedStream<String>) -> Result<u32, Error> {
+        Ok(0)
+    }
+    
+    async fn chat(&self, _context: &CallContext, requests: TypedStream<String>) -> Result<TypedStream<String>, Error> {
+        Ok(requests)
+    }
+}
+
+fn served<S: Service>(service: S) -> Vec<String> {
+    service.methods()
+}
+
+async fn calls(client: Client) -> Result<(), Error> {
+    let greeter = GreeterClient::new(client);
+    let _reply: String = greeter.greet(&"world".to_string()).await?;
+    let _ticks: CallStream<u32> = greeter.countdown(&3).await?;
+    let _tally: (CallSink<String>, CallStream<u32>) = greeter.tally().await?;
+    let _chat: (CallSink<String>, CallStream<String>) = greeter.chat_with_options(CallOptions::new()).await?;
+    Ok(())
+}
+
+fn main() {
+    assert_eq!(GREETER_NAME, "greeter");
+    assert_eq!(served(GreeterServer::new(Greetings)), ["greet", "countdown", "tally", "chat"]);
+    let _ = calls;
+}
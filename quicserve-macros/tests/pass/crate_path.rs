@@ -0,0 +1,11 @@
+use quicserve as rpc;
+
+#[rpc::service(crate = "rpc")]
+pub trait ChatRoom {
+    async fn post(&self, request: String) -> Result<(), rpc::Error>;
+}
+
+fn main() {
+    assert_eq!(CHAT_ROOM_NAME, "chat_room");
+    let _ = ChatRoomClient::new;
+}
@@ -0,0 +1,8 @@
+use quicserve_macros::service;
+
+#[service]
+pub trait Greeter<T> {
+    async fn greet(&self, request: T) -> Result<T, String>;
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use quicserve_macros::service;
+
+#[service]
+pub trait Greeter {
+    async fn greet(&self, request: String) -> String;
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use quicserve_macros::service;
+
+#[service]
+pub trait Greeter {
+    const GREETING: &'static str;
+    
+    async fn greet(&self, request: String) -> Result<String, String>;
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use quicserve_macros::service;
+
+#[service]
+pub trait Greeter {
+    async fn greet(&self, context: &CallContext, first: String, second: String) -> Result<String, String>;
+}
+
+fn main() {}
@@ -0,0 +1,291 @@
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use base64::engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD};
+use base64::Engine;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::auth::{Authenticator, ConnectInfo, Identity};
+use crate::error::Error;
+
+/// Header carrying the API key of a session
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Attribute holding the scopes granted to an API key
+pub const SCOPE_ATTRIBUTE: &str = "scope";
+
+/// Stored API key, as written in a key file
+///
+/// Only a salted SHA-256 hash of the key is stored, never the key itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyEntry {
+    /// Subject of the identity the key authenticates as
+    pub subject: String,
+    /// Salt mixed into the hash (base64)
+    pub salt: String,
+    /// SHA-256 hash of the salt followed by the key (base64)
+    pub hash: String,
+    /// Scopes granted to the key, available as the `scope` attribute of the identity
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl ApiKeyEntry {
+    /// Creates an entry for a key, hashed with a fresh random salt
+    pub fn new(subject: impl Into<String>, key: &str) -> Self {
+        let salt: [u8; 16] = rand::random();
+        Self {
+            subject: subject.into(),
+            salt: BASE64.encode(salt),
+            hash: BASE64.encode(hash_key(&salt, key)),
+            scopes: Vec::new(),
+        }
+    }
+    
+    /// Grants a scope to the key
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scopes.push(scope.into());
+        self
+    }
+}
+
+/// File of stored API keys
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApiKeyFile {
+    /// Stored keys
+    pub keys: Vec<ApiKeyEntry>,
+}
+
+/// Generates a new random API key
+pub fn generate_api_key() -> String {
+    let key: [u8; 32] = rand::random();
+    URL_SAFE_NO_PAD.encode(key)
+}
+
+/// Returns the salted hash of a key
+fn hash_key(salt: &[u8], key: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    hasher.update(key.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Stored key, decoded and ready to be matched
+struct LoadedKey {
+    /// Salt mixed into the hash
+    salt: Vec<u8>,
+    /// Salted hash of the key
+    hash: Vec<u8>,
+    /// Identity the key authenticates as
+    identity: Identity,
+}
+
+impl LoadedKey {
+    /// Returns whether a key matches this one, in time independent of where they differ
+    fn matches(&self, key: &str) -> bool {
+        let hash = hash_key(&self.salt, key);
+        hash.len() == self.hash.len()
+            && hash.iter().zip(&self.hash).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+}
+
+/// Keys loaded from the key file
+struct KeyStore {
+    /// Loaded keys
+    keys: Vec<LoadedKey>,
+    /// Modification time and length of the file when it was last read
+    stamp: Option<(SystemTime, u64)>,
+}
+
+/// Authenticator checking API keys against a file of salted hashes
+///
+/// Clients send their key in the `x-api-key` header of the CONNECT request. The key
+/// file is JSON in the form of [`ApiKeyFile`]. It is checked for changes whenever a
+/// session is established and reloaded when it has changed, so keys can be added,
+/// rotated and revoked without a restart. Revoking a key rejects new sessions; sessions
+/// already established with it are not closed. If a changed file can't be loaded, the
+/// previous keys stay in use.
+pub struct ApiKeyAuthenticator {
+    /// Path of the key file
+    path: PathBuf,
+    /// Keys currently in use
+    store: RwLock<KeyStore>,
+}
+
+impl ApiKeyAuthenticator {
+    /// Creates a new ApiKeyAuthenticator, loading keys from a file
+    pub async fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let stamp = file_stamp(&path).await?;
+        let keys = load_keys(&path).await?;
+        info!("Loaded {} API keys from {}", keys.len(), path.display());
+        
+        Ok(Self {
+            path,
+            store: RwLock::new(KeyStore {
+                keys,
+                stamp: Some(stamp),
+            }),
+        })
+    }
+    
+    /// Reloads the key file if it changed since it was last read
+    async fn reload_if_changed(&self) {
+        let stamp = match file_stamp(&self.path).await {
+            Ok(stamp) => stamp,
+            Err(e) => {
+                warn!("{}", e);
+                return;
+            }
+        };
+        if self.store.read().unwrap().stamp == Some(stamp) {
+            return;
+        }
+        
+        // Record the new stamp even if loading fails, so a broken file is only reported once
+        match load_keys(&self.path).await {
+            Ok(keys) => {
+                info!("Reloaded {} API keys from {}", keys.len(), self.path.display());
+                let mut store = self.store.write().unwrap();
+                store.keys = keys;
+                store.stamp = Some(stamp);
+            }
+            Err(e) => {
+                warn!("Keeping previous API keys: {}", e);
+                self.store.write().unwrap().stamp = Some(stamp);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Authenticator for ApiKeyAuthenticator {
+    async fn authenticate(&self, info: &ConnectInfo) -> Result<Identity, Error> {
+        let key = info.header(API_KEY_HEADER)
+            .ok_or_else(|| Error::AuthenticationFailed("Missing API key".to_string()))?;
+        
+        self.reload_if_changed().await;
+        
+        let store = self.store.read().unwrap();
+        store.keys.iter()
+            .find(|loaded| loaded.matches(key))
+            .map(|loaded| loaded.identity.clone())
+            .ok_or_else(|| Error::AuthenticationFailed("Invalid API key".to_string()))
+    }
+}
+
+/// Returns the modification time and length of a file
+async fn file_stamp(path: &Path) -> Result<(SystemTime, u64), Error> {
+    let metadata = tokio::fs::metadata(path).await
+        .map_err(|e| Error::InvalidConfig(format!("Failed to read API key file {}: {}", path.display(), e)))?;
+    let modified = metadata.modified()
+        .map_err(|e| Error::InvalidConfig(format!("Failed to read API key file {}: {}", path.display(), e)))?;
+    Ok((modified, metadata.len()))
+}
+
+/// Reads and decodes a key file
+async fn load_keys(path: &Path) -> Result<Vec<LoadedKey>, Error> {
+    let json = tokio::fs::read(path).await
+        .map_err(|e| Error::InvalidConfig(format!("Failed to read API key file {}: {}", path.display(), e)))?;
+    let file: ApiKeyFile = serde_json::from_slice(&json)
+        .map_err(|e| Error::InvalidConfig(format!("Invalid API key file {}: {}", path.display(), e)))?;
+    
+    file.keys.into_iter()
+        .map(|entry| {
+            let decode = |value: &str| BASE64.decode(value)
+                .map_err(|e| Error::InvalidConfig(format!("Invalid API key entry for {}: {}", entry.subject, e)));
+            let salt = decode(&entry.salt)?;
+            let hash = decode(&entry.hash)?;
+            
+            let mut identity = Identity::new(entry.subject.clone());
+            for scope in &entry.scopes {
+                identity = identity.with_attribute(SCOPE_ATTRIBUTE, scope.clone());
+            }
+            Ok(LoadedKey { salt, hash, identity })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderMap;
+    
+    fn connect_info(key: &str) -> ConnectInfo {
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, key.parse().unwrap());
+        ConnectInfo {
+            peer_addr: "127.0.0.1:4433".parse().unwrap(),
+            authority: None,
+            path: "/".to_string(),
+            headers,
+            peer_certificates: Vec::new(),
+        }
+    }
+    
+    fn loaded(entry: &ApiKeyEntry) -> LoadedKey {
+        LoadedKey {
+            salt: BASE64.decode(&entry.salt).unwrap(),
+            hash: BASE64.decode(&entry.hash).unwrap(),
+            identity: Identity::new(entry.subject.clone()),
+        }
+    }
+    
+    fn write_keys(path: &Path, keys: Vec<ApiKeyEntry>) {
+        std::fs::write(path, serde_json::to_vec(&ApiKeyFile { keys }).unwrap()).unwrap();
+    }
+    
+    #[test]
+    fn entry_matches_only_its_key() {
+        let key = generate_api_key();
+        let entry = ApiKeyEntry::new("alice", &key);
+        assert!(!entry.hash.contains(&key));
+        
+        let loaded = loaded(&entry);
+        assert!(loaded.matches(&key));
+        assert!(!loaded.matches(&generate_api_key()));
+        assert!(!loaded.matches(""));
+    }
+    
+    #[test]
+    fn entries_are_salted() {
+        let key = generate_api_key();
+        let first = ApiKeyEntry::new("alice", &key);
+        let second = ApiKeyEntry::new("alice", &key);
+        assert_ne!(first.salt, second.salt);
+        assert_ne!(first.hash, second.hash);
+        assert!(loaded(&first).matches(&key) && loaded(&second).matches(&key));
+    }
+    
+    #[tokio::test]
+    async fn reloads_changed_key_file() {
+        let path = std::env::temp_dir().join(format!("quicserve-apikeys-{}.json", uuid::Uuid::new_v4()));
+        let old_key = generate_api_key();
+        write_keys(&path, vec![ApiKeyEntry::new("alice", &old_key).with_scope("read")]);
+        
+        let authenticator = ApiKeyAuthenticator::from_file(&path).await.unwrap();
+        let identity = authenticator.authenticate(&connect_info(&old_key)).await.unwrap();
+        assert_eq!(identity.subject, "alice");
+        assert_eq!(identity.attribute_values(SCOPE_ATTRIBUTE), ["read"]);
+        
+        // Rotate alice's key and add bob; the file grows, so the change is seen even
+        // if the modification time doesn't move
+        let new_key = generate_api_key();
+        let bob_key = generate_api_key();
+        write_keys(&path, vec![ApiKeyEntry::new("alice", &new_key), ApiKeyEntry::new("bob", &bob_key)]);
+        
+        assert!(authenticator.authenticate(&connect_info(&old_key)).await.is_err());
+        assert_eq!(authenticator.authenticate(&connect_info(&new_key)).await.unwrap().subject, "alice");
+        assert_eq!(authenticator.authenticate(&connect_info(&bob_key)).await.unwrap().subject, "bob");
+        
+        // A broken file keeps the previous keys in use
+        std::fs::write(&path, b"not json").unwrap();
+        assert_eq!(authenticator.authenticate(&connect_info(&bob_key)).await.unwrap().subject, "bob");
+        
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{HeaderMap, StatusCode};
+
+use crate::error::Error;
+
+/// Information about a WebTransport session being established
+#[derive(Debug, Clone)]
+pub struct ConnectInfo {
+    /// Address of the client
+    pub peer_addr: SocketAddr,
+    /// Authority the client connected to, if sent
+    pub authority: Option<String>,
+    /// Path of the CONNECT request
+    pub path: String,
+    /// Headers of the CONNECT request
+    pub headers: HeaderMap,
+    /// Verified certificate chain presented by the client, leaf first (DER)
+    ///
+    /// Empty unless the server requests client certificates and the client presented one.
+    pub peer_certificates: Vec<Bytes>,
+}
+
+impl ConnectInfo {
+    /// Returns the value of a header, if it is present and valid text
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+    
+    /// Returns the bearer token of the `authorization` header, if any
+    pub fn bearer_token(&self) -> Option<&str> {
+        parse_bearer_token(self.header(http::header::AUTHORIZATION.as_str())?)
+    }
+}
+
+/// Authenticated identity attached to a session
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Identity {
+    /// Name of the authenticated principal
+    pub subject: String,
+    /// Attributes of the principal, such as roles or groups
+    pub attributes: HashMap<String, Vec<String>>,
+}
+
+impl Identity {
+    /// Creates a new Identity
+    pub fn new(subject: impl Into<String>) -> Self {
+        Self {
+            subject: subject.into(),
+            attributes: HashMap::new(),
+        }
+    }
+    
+    /// Adds a value to an attribute
+    pub fn with_attribute(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.entry(key.into()).or_default().push(value.into());
+        self
+    }
+    
+    /// Returns the first value of an attribute
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key)?.first().map(String::as_str)
+    }
+    
+    /// Returns every value of an attribute
+    pub fn attribute_values(&self, key: &str) -> &[String] {
+        self.attributes.get(key).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Authenticates WebTransport sessions as they are established
+///
+/// The authenticator runs once per session, before it is accepted. Returning an
+/// identity accepts the session and makes the identity available to every call
+/// made on it through [`CallContext::identity`](crate::CallContext::identity).
+/// Returning an error rejects the session with the matching HTTP status.
+#[async_trait]
+pub trait Authenticator: Send + Sync + 'static {
+    /// Authenticates a session from its CONNECT request
+    async fn authenticate(&self, info: &ConnectInfo) -> Result<Identity, Error>;
+}
+
+/// Authenticator accepting a fixed set of bearer tokens
+#[derive(Debug, Clone, Default)]
+pub struct StaticTokenAuthenticator {
+    /// Identities by bearer token
+    tokens: HashMap<String, Identity>,
+}
+
+impl StaticTokenAuthenticator {
+    /// Creates a new StaticTokenAuthenticator with no tokens
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Accepts a bearer token, authenticating its sessions as `identity`
+    pub fn with_token(mut self, token: impl Into<String>, identity: Identity) -> Self {
+        self.tokens.insert(token.into(), identity);
+        self
+    }
+}
+
+#[async_trait]
+impl Authenticator for StaticTokenAuthenticator {
+    async fn authenticate(&self, info: &ConnectInfo) -> Result<Identity, Error> {
+        let token = info.bearer_token()
+            .ok_or_else(|| Error::AuthenticationFailed("Missing bearer token".to_string()))?;
+        
+        self.tokens.get(token)
+            .cloned()
+            .ok_or_else(|| Error::AuthenticationFailed("Invalid bearer token".to_string()))
+    }
+}
+
+/// Returns the token of a `Bearer <token>` authorization value
+pub(crate) fn parse_bearer_token(value: &str) -> Option<&str> {
+    let (scheme, token) = value.split_once(' ')?;
+    scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+}
+
+/// Returns the HTTP status a session is rejected with when authentication fails
+pub(crate) fn rejection_status(err: &Error) -> StatusCode {
+    match err {
+        Error::AuthenticationFailed(_) => StatusCode::UNAUTHORIZED,
+        Error::PermissionDenied(_) => StatusCode::FORBIDDEN,
+        Error::ResourceExhausted(_) => StatusCode::TOO_MANY_REQUESTS,
+        Error::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn authenticator_errors_map_to_http_statuses() {
+        let status = |err: Error| rejection_status(&err);
+        assert_eq!(status(Error::AuthenticationFailed("bad token".to_string())), StatusCode::UNAUTHORIZED);
+        assert_eq!(status(Error::PermissionDenied("banned".to_string())), StatusCode::FORBIDDEN);
+        assert_eq!(status(Error::ResourceExhausted("too many sessions".to_string())), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(status(Error::Unavailable("directory down".to_string())), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status(Error::Internal("bug".to_string())), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    
+    #[test]
+    fn parses_bearer_tokens() {
+        assert_eq!(parse_bearer_token("Bearer abc"), Some("abc"));
+        assert_eq!(parse_bearer_token("bearer  abc "), Some("abc"));
+        assert_eq!(parse_bearer_token("Basic abc"), None);
+        assert_eq!(parse_bearer_token("abc"), None);
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::auth::Identity;
+use crate::context::CallContext;
+use crate::error::Error;
+use crate::utils::glob_match;
+
+/// Attribute holding the subject of the client's certificate
+pub const CERT_SUBJECT_ATTRIBUTE: &str = "cert.subject";
+
+/// Attribute holding the subject alternative names of the client's certificate
+pub const CERT_SAN_ATTRIBUTE: &str = "cert.san";
+
+/// Effect of an authorization rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Effect {
+    /// The call is allowed
+    Allow,
+    /// The call is denied
+    Deny,
+}
+
+impl Default for Effect {
+    fn default() -> Self {
+        Effect::Deny
+    }
+}
+
+/// Rule allowing or denying calls to a set of methods
+///
+/// A rule matches a call when every one of its conditions holds. Empty conditions
+/// always hold.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Rule {
+    /// Effect of the rule when it matches
+    pub effect: Effect,
+    /// Glob patterns over `service.method` names
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// Glob patterns over the caller's subject
+    #[serde(default)]
+    pub subjects: Vec<String>,
+    /// Glob patterns over attributes of the caller, all of which must match
+    #[serde(default)]
+    pub attributes: HashMap<String, Vec<String>>,
+    /// Whether the rule only matches authenticated callers
+    #[serde(default)]
+    pub authenticated: bool,
+}
+
+impl Rule {
+    /// Creates a rule allowing calls to methods matching `pattern`
+    pub fn allow(pattern: impl Into<String>) -> Self {
+        Self {
+            effect: Effect::Allow,
+            methods: vec![pattern.into()],
+            ..Default::default()
+        }
+    }
+    
+    /// Creates a rule denying calls to methods matching `pattern`
+    pub fn deny(pattern: impl Into<String>) -> Self {
+        Self {
+            effect: Effect::Deny,
+            methods: vec![pattern.into()],
+            ..Default::default()
+        }
+    }
+    
+    /// Restricts the rule to callers whose subject matches `pattern`
+    pub fn for_subject(mut self, pattern: impl Into<String>) -> Self {
+        self.subjects.push(pattern.into());
+        self
+    }
+    
+    /// Restricts the rule to callers with a value of `key` matching `pattern`
+    pub fn with_attribute(mut self, key: impl Into<String>, pattern: impl Into<String>) -> Self {
+        self.attributes.entry(key.into()).or_default().push(pattern.into());
+        self
+    }
+    
+    /// Restricts the rule to authenticated callers
+    pub fn authenticated(mut self) -> Self {
+        self.authenticated = true;
+        self
+    }
+    
+    /// Returns whether the rule matches a call
+    fn matches(&self, method: &str, caller: &Caller<'_>) -> bool {
+        if !self.methods.is_empty() && !self.methods.iter().any(|pattern| glob_match(pattern, method)) {
+            return false;
+        }
+        
+        if self.authenticated && !caller.is_authenticated() {
+            return false;
+        }
+        
+        if !self.subjects.is_empty() {
+            let subject = match caller.subject() {
+                Some(subject) => subject,
+                None => return false,
+            };
+            if !self.subjects.iter().any(|pattern| glob_match(pattern, subject)) {
+                return false;
+            }
+        }
+        
+        self.attributes.iter().all(|(key, patterns)| {
+            caller.attribute_values(key)
+                .iter()
+                .any(|value| patterns.iter().any(|pattern| glob_match(pattern, value)))
+        })
+    }
+}
+
+/// Ordered set of authorization rules
+///
+/// Rules are evaluated in order and the first rule matching a call decides its fate.
+/// Calls matching no rule get the default effect, which is to deny.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Policy {
+    /// Effect applied to calls matching no rule
+    #[serde(default, rename = "default")]
+    pub default_effect: Effect,
+    /// Rules in evaluation order
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+impl Policy {
+    /// Creates an empty policy denying every call
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Loads a policy from a JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let json = std::fs::read(path)
+            .map_err(|e| Error::InvalidConfig(format!("Failed to read policy file {}: {}", path.display(), e)))?;
+        serde_json::from_slice(&json)
+            .map_err(|e| Error::InvalidConfig(format!("Invalid policy file {}: {}", path.display(), e)))
+    }
+    
+    /// Appends a rule to the policy
+    pub fn with_rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+    
+    /// Sets the effect applied to calls matching no rule
+    pub fn with_default(mut self, effect: Effect) -> Self {
+        self.default_effect = effect;
+        self
+    }
+    
+    /// Authorizes a call, failing with [`Error::PermissionDenied`] if it is denied
+    pub fn authorize(&self, context: &CallContext) -> Result<(), Error> {
+        let method = context.method();
+        let caller = Caller {
+            identity: context.identity(),
+            peer_subject: context.peer_subject(),
+            peer_sans: context.peer_sans(),
+        };
+        
+        match self.effect(method, &caller) {
+            Effect::Allow => Ok(()),
+            Effect::Deny => Err(Error::PermissionDenied(match caller.subject() {
+                Some(subject) => format!("{} may not call {}", subject, method),
+                None => format!("Anonymous callers may not call {}", method),
+            })),
+        }
+    }
+    
+    /// Returns the effect of the first rule matching a call, or the default effect
+    fn effect(&self, method: &str, caller: &Caller<'_>) -> Effect {
+        self.rules.iter()
+            .find(|rule| rule.matches(method, caller))
+            .map_or(self.default_effect, |rule| rule.effect)
+    }
+}
+
+/// Caller of a call, as seen by rules
+struct Caller<'a> {
+    /// Identity established by the authenticator or an interceptor, if any
+    identity: Option<&'a Identity>,
+    /// Subject of the client's certificate, if one was presented
+    peer_subject: Option<&'a str>,
+    /// Subject alternative names of the client's certificate
+    peer_sans: &'a [String],
+}
+
+impl Caller<'_> {
+    /// Returns whether the caller authenticated, either with the authenticator or a certificate
+    fn is_authenticated(&self) -> bool {
+        self.identity.is_some() || self.peer_subject.is_some()
+    }
+    
+    /// Returns the subject of the caller's identity, or else of its certificate
+    fn subject(&self) -> Option<&str> {
+        match self.identity {
+            Some(identity) => Some(identity.subject.as_str()),
+            None => self.peer_subject,
+        }
+    }
+    
+    /// Returns the values of an attribute of the caller
+    fn attribute_values(&self, key: &str) -> Vec<&str> {
+        match key {
+            CERT_SUBJECT_ATTRIBUTE => self.peer_subject.into_iter().collect(),
+            CERT_SAN_ATTRIBUTE => self.peer_sans.iter().map(String::as_str).collect(),
+            _ => self.identity
+                .map(|identity| identity.attribute_values(key).iter().map(String::as_str).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Returns a caller authenticated as `identity`
+    fn caller(identity: &Identity) -> Caller<'_> {
+        Caller { identity: Some(identity), peer_subject: None, peer_sans: &[] }
+    }
+    
+    /// Returns an anonymous caller
+    fn anonymous() -> Caller<'static> {
+        Caller { identity: None, peer_subject: None, peer_sans: &[] }
+    }
+    
+    #[test]
+    fn calls_matching_no_rule_are_denied_by_default() {
+        let policy = Policy::new().with_rule(Rule::allow("health.*"));
+        
+        assert_eq!(policy.effect("health.check", &anonymous()), Effect::Allow);
+        assert_eq!(policy.effect("admin.reset", &anonymous()), Effect::Deny);
+    }
+    
+    #[test]
+    fn default_effect_applies_to_unmatched_calls() {
+        let policy = Policy::new()
+            .with_default(Effect::Allow)
+            .with_rule(Rule::deny("admin.*"));
+        
+        assert_eq!(policy.effect("echo.echo", &anonymous()), Effect::Allow);
+        assert_eq!(policy.effect("admin.reset", &anonymous()), Effect::Deny);
+    }
+    
+    #[test]
+    fn first_matching_rule_wins() {
+        let admin = Identity::new("alice").with_attribute("role", "admin");
+        let user = Identity::new("bob").with_attribute("role", "user");
+        let policy = Policy::new()
+            .with_rule(Rule::allow("admin.*").with_attribute("role", "admin"))
+            .with_rule(Rule::deny("admin.*"))
+            .with_rule(Rule::allow("*"));
+        
+        assert_eq!(policy.effect("admin.reset", &caller(&admin)), Effect::Allow);
+        assert_eq!(policy.effect("admin.reset", &caller(&user)), Effect::Deny);
+        assert_eq!(policy.effect("echo.echo", &caller(&user)), Effect::Allow);
+    }
+    
+    #[test]
+    fn subject_and_authentication_conditions() {
+        let alice = Identity::new("alice@example.com");
+        let policy = Policy::new()
+            .with_rule(Rule::allow("billing.*").for_subject("*@example.com"))
+            .with_rule(Rule::allow("profile.*").authenticated());
+        
+        assert_eq!(policy.effect("billing.pay", &caller(&alice)), Effect::Allow);
+        assert_eq!(policy.effect("billing.pay", &anonymous()), Effect::Deny);
+        assert_eq!(policy.effect("profile.get", &caller(&alice)), Effect::Allow);
+        assert_eq!(policy.effect("profile.get", &anonymous()), Effect::Deny);
+    }
+    
+    #[test]
+    fn certificate_attributes() {
+        let sans = vec!["svc.internal".to_string()];
+        let peer = Caller { identity: None, peer_subject: Some("CN=svc"), peer_sans: &sans };
+        let policy = Policy::new()
+            .with_rule(Rule::allow("internal.*").with_attribute(CERT_SAN_ATTRIBUTE, "*.internal"));
+        
+        assert!(peer.is_authenticated());
+        assert_eq!(peer.subject(), Some("CN=svc"));
+        assert_eq!(policy.effect("internal.sync", &peer), Effect::Allow);
+        assert_eq!(policy.effect("internal.sync", &anonymous()), Effect::Deny);
+    }
+    
+    #[test]
+    fn policy_files_use_lowercase_effects() {
+        let policy: Policy = serde_json::from_str(r#"{
+            "default": "allow",
+            "rules": [{ "effect": "deny", "methods": ["admin.*"] }]
+        }"#).unwrap();
+        
+        assert_eq!(policy.default_effect, Effect::Allow);
+        assert_eq!(policy.effect("admin.reset", &anonymous()), Effect::Deny);
+    }
+}
@@ -12,8 +12,9 @@ use quicserve::{
     config::Config,
     error::Error,
     server::Server,
-    utils::{configure_logging, parse_format, parse_socket_addr},
-    SerializationFormat,
+    utils::{configure_logging, parse_client_auth, parse_format, parse_socket_addr, parse_stream_mode},
+    apikey::{generate_api_key, ApiKeyEntry},
+    ApiKeyAuthenticator, ClientAuthMode, Policy, SerializationFormat,
 };
 
 /// QuicServe: A high-performance RPC system using WebTransport over HTTP/3
@@ -48,10 +49,14 @@ enum Commands {
         #[clap(long)]
         ca: Option<PathBuf>,
 
-        /// Whether to verify client certificates
-        #[clap(long)]
+        /// Whether to require client certificates (same as `--client-auth required`)
+        #[clap(long, conflicts_with = "client_auth")]
         verify_client: bool,
 
+        /// Client certificate verification (none, optional or required), against the CA given by --ca
+        #[clap(long, default_value = "none")]
+        client_auth: String,
+
         /// Serialization format (json or protobuf)
         #[clap(short, long, default_value = "protobuf")]
         format: String,
@@ -67,6 +72,26 @@ enum Commands {
         /// Idle timeout in milliseconds
         #[clap(long, default_value = "30000")]
         idle_timeout: u64,
+
+        /// Time in milliseconds given to in-flight calls to finish on shutdown
+        #[clap(long, default_value = "30000")]
+        shutdown_timeout: u64,
+
+        /// Authorization policy file path (JSON)
+        #[clap(long)]
+        policy: Option<PathBuf>,
+
+        /// API key file path (JSON); sessions must then present a listed key
+        #[clap(long)]
+        api_keys: Option<PathBuf>,
+
+        /// Enable the reflection service, letting clients list services and methods
+        #[clap(long)]
+        reflection: bool,
+
+        /// Enable the health service for load balancer and orchestration probes
+        #[clap(long)]
+        health: bool,
     },
 
     /// Connect to a QuicServe server
@@ -83,10 +108,22 @@ enum Commands {
         #[clap(long)]
         ca: Option<PathBuf>,
 
+        /// Client certificate file path presented to the server (PEM format, optional)
+        #[clap(long)]
+        cert: Option<PathBuf>,
+
+        /// Client private key file path (PEM format, optional)
+        #[clap(long)]
+        key: Option<PathBuf>,
+
         /// Serialization format (json or protobuf)
         #[clap(short, long, default_value = "protobuf")]
         format: String,
 
+        /// Stream mode (per-call or shared)
+        #[clap(long, default_value = "per-call")]
+        stream_mode: String,
+
         /// RPC timeout in milliseconds
         #[clap(long, default_value = "30000")]
         timeout: u64,
@@ -106,6 +143,25 @@ enum Commands {
         /// Input data file path (for request payload)
         #[clap(short, long)]
         input: Option<PathBuf>,
+
+        /// API key sent to the server
+        #[clap(long)]
+        api_key: Option<String>,
+
+        /// List the services and methods of the server (requires reflection on the server)
+        #[clap(long)]
+        list: bool,
+    },
+
+    /// Generate a new API key and the entry to add to an API key file
+    Keygen {
+        /// Subject the key authenticates as
+        #[clap(short, long)]
+        subject: String,
+
+        /// Scope granted to the key (repeatable)
+        #[clap(long)]
+        scope: Vec<String>,
     },
 }
 
@@ -133,10 +189,16 @@ async fn main() -> Result<()> {
             key,
             ca,
             verify_client,
+            client_auth,
             format,
             max_streams,
             keep_alive,
             idle_timeout,
+            shutdown_timeout,
+            policy,
+            api_keys,
+            reflection,
+            health,
         } => {
             run_server(
                 addr,
@@ -144,10 +206,16 @@ async fn main() -> Result<()> {
                 key,
                 ca,
                 verify_client,
+                client_auth,
                 format,
                 max_streams,
                 keep_alive,
                 idle_timeout,
+                shutdown_timeout,
+                policy,
+                api_keys,
+                reflection,
+                health,
             )
             .await?;
         }
@@ -155,18 +223,27 @@ async fn main() -> Result<()> {
             addr,
             host,
             ca,
+            cert,
+            key,
             format,
+            stream_mode,
             timeout,
             keep_alive,
             idle_timeout,
             method,
             input,
+            api_key,
+            list,
         } => {
             run_client(
-                addr, host, ca, format, timeout, keep_alive, idle_timeout, method, input,
+                addr, host, ca, cert, key, format, stream_mode, timeout, keep_alive, idle_timeout, method, input, api_key,
+                list,
             )
             .await?;
         }
+        Commands::Keygen { subject, scope } => {
+            run_keygen(subject, scope)?;
+        }
     }
 
     Ok(())
@@ -178,11 +255,17 @@ async fn run_server(
     cert_path: PathBuf,
     key_path: PathBuf,
     ca_path: Option<PathBuf>,
-    verify_peer: bool,
+    verify_client: bool,
+    client_auth_str: String,
     format_str: String,
     max_concurrent_streams: u64,
     keep_alive_ms: u64,
     idle_timeout_ms: u64,
+    shutdown_timeout_ms: u64,
+    policy_path: Option<PathBuf>,
+    api_keys_path: Option<PathBuf>,
+    reflection: bool,
+    health: bool,
 ) -> Result<()> {
     // Parse address
     let addr = parse_socket_addr(&addr, 4433)
@@ -192,12 +275,20 @@ async fn run_server(
     let format = parse_format(&format_str)
         .context("Failed to parse serialization format")?;
 
+    // Parse client authentication mode
+    let client_auth = if verify_client {
+        ClientAuthMode::Required
+    } else {
+        parse_client_auth(&client_auth_str)
+            .context("Failed to parse client auth mode")?
+    };
+
     // Create server configuration
     let mut config = Config::new(addr);
     config.cert_path = Some(cert_path);
     config.key_path = Some(key_path);
     config.ca_path = ca_path;
-    config.verify_peer = verify_peer;
+    config.client_auth = client_auth;
     config.format = format;
     config.max_concurrent_streams = max_concurrent_streams;
     config.keep_alive_ms = Some(keep_alive_ms);
@@ -206,21 +297,67 @@ async fn run_server(
     // Create and start server
     let server = Server::new(config).await?;
     
+    // Load the authorization policy
+    if let Some(policy_path) = policy_path {
+        let policy = Policy::from_file(&policy_path)
+            .context("Failed to load authorization policy")?;
+        server.set_policy(policy).await?;
+    }
+    
+    // Require API keys
+    if let Some(api_keys_path) = api_keys_path {
+        let authenticator = ApiKeyAuthenticator::from_file(&api_keys_path).await
+            .context("Failed to load API keys")?;
+        server.set_authenticator(authenticator).await?;
+    }
+    
+    // Let clients discover the registered services
+    if reflection {
+        server.enable_reflection().await?;
+    }
+    
+    // Answer health checks
+    if health {
+        server.enable_health().await?;
+    }
+    
     // Register demo service (for example purposes)
     // In a real application, you would register your own services here
     // server.register_service("example", ExampleService::new()).await?;
 
-    // Handle termination signals
-    let server_task = tokio::spawn(async move {
-        if let Err(e) = server.serve().await {
-            error!("Server error: {}", e);
-        }
-    });
+    // Start serving in the background
+    let handle = server.serve().await?;
 
-    // Wait for Ctrl+C signal
-    signal::ctrl_c().await?;
+    // Wait for a termination signal, or for the server to stop on its own
+    tokio::select! {
+        result = shutdown_signal() => result?,
+        _ = handle.wait() => return Ok(()),
+    }
     info!("Shutdown signal received, stopping server...");
 
+    // Let in-flight calls finish before exiting
+    handle
+        .shutdown_with_deadline(Duration::from_millis(shutdown_timeout_ms))
+        .await;
+
+    Ok(())
+}
+
+/// Waits for SIGINT or, on Unix, SIGTERM
+async fn shutdown_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())
+            .context("Failed to install SIGTERM handler")?;
+        tokio::select! {
+            result = signal::ctrl_c() => result.context("Failed to listen for Ctrl+C")?,
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    signal::ctrl_c().await.context("Failed to listen for Ctrl+C")?;
+
     Ok(())
 }
 
@@ -229,12 +366,17 @@ async fn run_client(
     addr: String,
     host: String,
     ca_path: Option<PathBuf>,
+    cert_path: Option<PathBuf>,
+    key_path: Option<PathBuf>,
     format_str: String,
+    stream_mode_str: String,
     timeout_ms: u64,
     keep_alive_ms: u64,
     idle_timeout_ms: u64,
     method: Option<String>,
     input: Option<PathBuf>,
+    api_key: Option<String>,
+    list: bool,
 ) -> Result<()> {
     // Parse address
     let addr = parse_socket_addr(&addr, 4433)
@@ -244,14 +386,22 @@ async fn run_client(
     let format = parse_format(&format_str)
         .context("Failed to parse serialization format")?;
 
+    // Parse stream mode
+    let stream_mode = parse_stream_mode(&stream_mode_str)
+        .context("Failed to parse stream mode")?;
+
     // Create client configuration
     let mut config = Config::new(addr);
     config.ca_path = ca_path;
+    config.client_cert_path = cert_path;
+    config.client_key_path = key_path;
     config.server_name = Some(host);
     config.format = format;
+    config.stream_mode = stream_mode;
     config.timeout_ms = timeout_ms;
     config.keep_alive_ms = Some(keep_alive_ms);
     config.idle_timeout_ms = Some(idle_timeout_ms);
+    config.api_key = api_key;
 
     // Create client instance
     let client = Client::new(config).await?;
@@ -260,8 +410,15 @@ async fn run_client(
     client.connect().await?;
     info!("Connected to server at {}", addr);
 
-    // If method specified, make an RPC call
-    if let Some(method_name) = method {
+    // List services if requested, otherwise make an RPC call if a method is specified
+    if list {
+        for service in client.list_services().await? {
+            println!("{}", service.name);
+            for method_name in service.methods {
+                println!("  {}.{}", service.name, method_name);
+            }
+        }
+    } else if let Some(method_name) = method {
         // Parse input data
         let input_data = match input {
             Some(path) => std::fs::read(&path).context("Failed to read input file")?,
@@ -288,4 +445,18 @@ async fn run_client(
     info!("Connection closed");
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Generate an API key and print it along with its key file entry
+fn run_keygen(subject: String, scopes: Vec<String>) -> Result<()> {
+    let key = generate_api_key();
+    let mut entry = ApiKeyEntry::new(subject, &key);
+    entry.scopes = scopes;
+
+    // The key is only shown once; the file only ever holds its hash
+    println!("API key: {}", key);
+    println!("Key file entry:");
+    println!("{}", serde_json::to_string_pretty(&entry).context("Failed to serialize key file entry")?);
+
+    Ok(())
+}
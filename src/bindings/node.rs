@@ -389,7 +389,10 @@ fn create_server(ctx: CallContext) -> Result<JsObject> {
             // Run server
             runtime.block_on(async {
                 match server_clone.serve().await {
-                    Ok(_) => deferred.resolve(|env| env.get_undefined()),
+                    Ok(handle) => {
+                        handle.wait().await;
+                        deferred.resolve(|env| env.get_undefined())
+                    }
                     Err(e) => deferred.reject(Error::new(Status::GenericFailure, format!("Server error: {}", e))),
                 }
             });
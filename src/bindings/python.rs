@@ -321,8 +321,10 @@ impl PyServer {
                 // Store server instance
                 self.server = Some(Arc::new(server.clone()));
                 
-                // Start server
-                server.serve().await.map_err(err_to_py)
+                // Start server and wait until it stops
+                let handle = server.serve().await.map_err(err_to_py)?;
+                handle.wait().await;
+                Ok(())
             })
         })
     }
@@ -352,8 +354,9 @@ impl PyServer {
         // Start server in background thread
         let handle = thread::spawn(move || {
             rt.block_on(async {
-                if let Err(e) = server_arc.serve().await {
-                    eprintln!("Server error: {}", e);
+                match server_arc.serve().await {
+                    Ok(handle) => handle.wait().await,
+                    Err(e) => eprintln!("Server error: {}", e),
                 }
             });
         });
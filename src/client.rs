@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -11,27 +12,110 @@ use h3_webtransport::client;
 use log::{debug, error, info, warn};
 use quinn::{ClientConfig, Endpoint};
 use tokio::sync::{mpsc, Mutex, RwLock, oneshot};
+use tokio_util::sync::CancellationToken;
 
-use crate::{config::Config, error::Error, Request, Response, WEBTRANSPORT_PROTOCOL};
-use crate::transport::MessageStream;
+use crate::{config::Config, error::Error, Request, RequestKind, Response, SerializationFormat, Service, StreamMode, WEBTRANSPORT_PROTOCOL};
+use crate::apikey::API_KEY_HEADER;
+use crate::context::CallContext;
+use crate::datagram::{check_datagram_size, max_datagram_size};
+use crate::metadata::Metadata;
+use crate::proto::quicserve::DatagramProto;
+use crate::pubsub::{Event, SubscribeRequest, Subscription, PUBSUB_SERVICE_NAME};
+use crate::push::serve_reverse_calls;
+use crate::reflection::{
+    DescribeServiceRequest, DescribeServiceResponse, ListServicesRequest, ListServicesResponse, ServiceInfo,
+    REFLECTION_SERVICE_NAME,
+};
+use crate::streaming::{CallSink, CallStream};
+use crate::transport::{MessageReader, MessageStream, MessageWriter, CALL_CANCELLED_CODE};
+use crate::utils::format_method_name;
 
 /// Type definition for RPC response channels
-type ResponseChannel = oneshot::Sender<Result<Bytes, Error>>;
+type ResponseChannel = oneshot::Sender<Result<Response, Error>>;
+
+/// Options applied to a single call
+#[derive(Debug, Clone, Default)]
+pub struct CallOptions {
+    /// Timeout for the call, overriding the configured one
+    pub timeout: Option<Duration>,
+    /// Metadata sent with the call
+    pub metadata: Metadata,
+}
+
+impl CallOptions {
+    /// Creates a new CallOptions with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Sets the timeout for the call
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+    
+    /// Adds a metadata entry to the call
+    ///
+    /// Fails if the value isn't valid UTF-8 and the key doesn't end in `-bin`.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<Bytes>) -> Result<Self, Error> {
+        self.metadata.insert(key, value)?;
+        Ok(self)
+    }
+}
+
+/// Response of a call together with the metadata sent by the server
+#[derive(Debug, Clone)]
+pub struct CallResponse<R> {
+    /// Response message
+    pub message: R,
+    /// Response headers
+    pub headers: Metadata,
+    /// Response trailers
+    pub trailers: Metadata,
+}
+
+impl<R> CallResponse<R> {
+    /// Returns the response message, discarding the metadata
+    pub fn into_inner(self) -> R {
+        self.message
+    }
+}
+
+impl From<Bytes> for CallResponse<Bytes> {
+    fn from(message: Bytes) -> Self {
+        Self {
+            message,
+            headers: Metadata::new(),
+            trailers: Metadata::new(),
+        }
+    }
+}
 
 /// RPC Client implementation
+///
+/// Clones share the same connection, and the endpoint is closed once the last clone is dropped.
+#[derive(Clone)]
 pub struct Client {
     /// Configuration
     config: Config,
     /// QUIC endpoint
     endpoint: Endpoint,
+    /// Closes the endpoint once every clone of the client is gone
+    _endpoint_guard: Arc<EndpointGuard>,
+    /// QUIC connection carrying the session
+    connection: Arc<Mutex<Option<quinn::Connection>>>,
     /// WebTransport session
-    session: Arc<Mutex<Option<client::Session>>>,
-    /// Message stream for communication
-    message_stream: Arc<Mutex<Option<MessageStream>>>,
+    session: Arc<Mutex<Option<Arc<client::Session>>>>,
+    /// Writing half of the shared message stream (shared stream mode only)
+    writer: Arc<Mutex<Option<MessageWriter>>>,
     /// Pending requests waiting for responses
     pending: Arc<Mutex<HashMap<u64, ResponseChannel>>>,
     /// Next request ID
     next_id: Arc<Mutex<u64>>,
+    /// Sequence number of the next datagram
+    next_datagram_sequence: Arc<AtomicU64>,
+    /// Services answering pushes and calls made by the server
+    services: Arc<RwLock<HashMap<String, Arc<dyn Service>>>>,
 }
 
 impl Client {
@@ -49,11 +133,15 @@ impl Client {
         
         Ok(Self {
             config,
+            _endpoint_guard: Arc::new(EndpointGuard { endpoint: endpoint.clone() }),
             endpoint,
+            connection: Arc::new(Mutex::new(None)),
             session: Arc::new(Mutex::new(None)),
-            message_stream: Arc::new(Mutex::new(None)),
+            writer: Arc::new(Mutex::new(None)),
             pending: Arc::new(Mutex::new(HashMap::new())),
             next_id: Arc::new(Mutex::new(0)),
+            next_datagram_sequence: Arc::new(AtomicU64::new(0)),
+            services: Arc::new(RwLock::new(HashMap::new())),
         })
     }
     
@@ -65,8 +153,9 @@ impl Client {
             .map_err(|e| Error::Quic(format!("Failed to connect: {}", e)))?
             .await
             .map_err(|e| Error::Quic(format!("Connection failed: {}", e)))?;
-            
+        
         info!("Connected to {}", self.config.addr);
+        let quic_connection = connection.clone();
         
         // Create HTTP/3 connection
         let h3_conn = h3::client::Connection::new(h3::quic::Connection::new(connection))
@@ -81,59 +170,104 @@ impl Client {
             .await
             .map_err(|e| Error::WebTransport(format!("Failed to create WebTransport client: {}", e)))?;
         
-        // Connect to the RPC endpoint
-        let session = session.connect("/rpc")
+        // Connect to the RPC endpoint, presenting the configured credentials
+        let session = session.connect_request(self.connect_request()?)
             .await
-            .map_err(|e| Error::WebTransport(format!("Failed to connect to RPC endpoint: {}", e)))?;
-            
+            .map_err(|e| match e.status() {
+                Some(status) if status == http::StatusCode::UNAUTHORIZED => {
+                    Error::AuthenticationFailed("Server rejected the session credentials".to_string())
+                }
+                Some(status) if status == http::StatusCode::FORBIDDEN => {
+                    Error::PermissionDenied("Server refused the session".to_string())
+                }
+                Some(status) if status == http::StatusCode::TOO_MANY_REQUESTS => {
+                    Error::ResourceExhausted("Server is refusing further sessions".to_string())
+                }
+                Some(status) if status == http::StatusCode::SERVICE_UNAVAILABLE => {
+                    Error::Unavailable("Server can't accept the session".to_string())
+                }
+                _ => Error::WebTransport(format!("Failed to connect to RPC endpoint: {}", e)),
+            })?;
+        
         debug!("WebTransport session established");
+        let session = Arc::new(session);
         
-        // Open bidirectional stream for RPC communication
-        let stream = session.open_bi()
-            .await
-            .map_err(|e| Error::WebTransport(format!("Failed to open bidirectional stream: {}", e)))?;
-            
-        debug!("Bidirectional stream opened");
+        // Serve pushes and calls made by the server
+        tokio::spawn(serve_reverse_calls(
+            session.clone(),
+            self.services.clone(),
+            quic_connection.remote_address(),
+            self.config.format,
+            self.config.max_in_flight_requests.max(1),
+        ));
         
-        // Create message stream wrapper
-        let message_stream = MessageStream::new(stream);
+        // In shared mode, open the single bidirectional stream used by every call
+        let reader = match self.config.stream_mode {
+            StreamMode::Shared => {
+                let stream = session.open_bi()
+                    .await
+                    .map_err(|e| Error::WebTransport(format!("Failed to open bidirectional stream: {}", e)))?;
+                
+                debug!("Bidirectional stream opened");
+                
+                // Split the stream so responses can be read while requests are written
+                let (reader, writer) = MessageStream::new(stream).split();
+                {
+                    let mut writer_guard = self.writer.lock().await;
+                    *writer_guard = Some(writer);
+                }
+                Some(reader)
+            }
+            StreamMode::PerCall => None,
+        };
         
         // Update client state
         {
+            let mut connection_guard = self.connection.lock().await;
+            *connection_guard = Some(quic_connection);
             let mut session_guard = self.session.lock().await;
             *session_guard = Some(session);
         }
-        {
-            let mut stream_guard = self.message_stream.lock().await;
-            *stream_guard = Some(message_stream);
-        }
         
         // Start response handler
-        self.start_response_handler().await;
+        if let Some(reader) = reader {
+            self.start_response_handler(reader);
+        }
         
         Ok(())
     }
     
-    /// Starts the response handler to process incoming messages
-    async fn start_response_handler(&self) {
-        let message_stream = self.message_stream.clone();
+    /// Builds the CONNECT request establishing the RPC session
+    fn connect_request(&self) -> Result<http::Request<()>, Error> {
+        let authority = self.config.server_name.as_deref().unwrap_or("localhost");
+        let mut builder = http::Request::builder()
+            .method(http::Method::CONNECT)
+            .uri(format!("https://{}/rpc", authority));
+        
+        // Credentials
+        if let Some(token) = &self.config.bearer_token {
+            builder = builder.header(http::header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        if let Some(api_key) = &self.config.api_key {
+            builder = builder.header(API_KEY_HEADER, api_key.as_str());
+        }
+        for (name, value) in &self.config.connect_headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        
+        builder.body(())
+            .map_err(|e| Error::InvalidConfig(format!("Invalid connect headers: {}", e)))
+    }
+    
+    /// Starts the response handler to process incoming messages on the shared stream
+    fn start_response_handler(&self, mut reader: MessageReader) {
+        let writer = self.writer.clone();
         let pending = self.pending.clone();
         let format = self.config.format;
         
         tokio::spawn(async move {
-            let mut stream = {
-                let mut guard = message_stream.lock().await;
-                match guard.take() {
-                    Some(stream) => stream,
-                    None => {
-                        error!("Message stream not initialized");
-                        return;
-                    }
-                }
-            };
-            
             // Process incoming responses
-            while let Some(response_bytes) = match stream.receive().await {
+            while let Some(response_bytes) = match reader.receive().await {
                 Ok(bytes) => bytes,
                 Err(e) => {
                     error!("Error receiving response: {}", e);
@@ -159,12 +293,7 @@ impl Client {
                 
                 // Send response to waiting caller
                 if let Some(sender) = sender {
-                    let result = match response.error {
-                        Some(err) => Err(Error::RpcFailed(err)),
-                        None => Ok(response.payload.unwrap_or_else(|| Bytes::new())),
-                    };
-                    
-                    if sender.send(result).is_err() {
+                    if sender.send(check_response(response)).is_err() {
                         debug!("Failed to send response to caller - caller dropped");
                     }
                 } else {
@@ -172,9 +301,11 @@ impl Client {
                 }
             }
             
-            // Put the stream back for reconnection handling
-            let mut guard = message_stream.lock().await;
-            *guard = Some(stream);
+            // The shared stream is gone, fail every call still waiting on it
+            writer.lock().await.take();
+            for (_, sender) in pending.lock().await.drain() {
+                let _ = sender.send(Err(Error::ConnectionClosed));
+            }
             
             debug!("Response handler exited");
         });
@@ -182,6 +313,22 @@ impl Client {
     
     /// Calls a remote procedure and returns the result
     pub async fn call<T, R>(&self, method: &str, request: &T) -> Result<R, Error>
+    where
+        T: serde::Serialize + prost::Message,
+        R: serde::de::DeserializeOwned + prost::Message + Default,
+    {
+        self.call_with_options(method, request, CallOptions::default())
+            .await
+            .map(CallResponse::into_inner)
+    }
+    
+    /// Calls a remote procedure with per-call options and returns the result with its metadata
+    pub async fn call_with_options<T, R>(
+        &self,
+        method: &str,
+        request: &T,
+        options: CallOptions,
+    ) -> Result<CallResponse<R>, Error>
     where
         T: serde::Serialize + prost::Message,
         R: serde::de::DeserializeOwned + prost::Message + Default,
@@ -189,66 +336,369 @@ impl Client {
         // Serialize request payload
         let payload = crate::serialize(request, self.config.format)?;
         
+        // Call and deserialize response
+        let response = self.call_raw(method, payload, options).await?;
+        Ok(CallResponse {
+            message: crate::deserialize(&response.message, self.config.format)?,
+            headers: response.headers,
+            trailers: response.trailers,
+        })
+    }
+    
+    /// Calls a remote procedure with an already serialized payload
+    pub async fn call_raw(
+        &self,
+        method: &str,
+        payload: Bytes,
+        options: CallOptions,
+    ) -> Result<CallResponse<Bytes>, Error> {
         // Get next request ID
-        let id = {
-            let mut id_guard = self.next_id.lock().await;
-            let id = *id_guard;
-            *id_guard = id.wrapping_add(1);
-            id
-        };
+        let id = self.next_request_id().await;
+        
+        // The server is told how long we are going to wait
+        let timeout = self.call_timeout(options.timeout);
         
         // Create RPC request
         let rpc_request = Request {
             id,
             method: method.to_string(),
             payload,
+            kind: RequestKind::Unary,
+            end_of_stream: false,
+            timeout_ms: Some(timeout.as_millis() as u64),
+            metadata: options.metadata,
+        };
+        
+        // Serialize and send request, then wait for the response
+        let request_bytes = crate::serialize(&rpc_request, self.config.format)?;
+        let response = match self.config.stream_mode {
+            StreamMode::Shared => self.call_shared(id, request_bytes, timeout).await?,
+            StreamMode::PerCall => self.call_per_call(request_bytes, timeout).await?,
+        };
+        
+        Ok(CallResponse {
+            message: response.payload.unwrap_or_default(),
+            headers: response.headers,
+            trailers: response.trailers,
+        })
+    }
+    
+    /// Calls a server-streaming remote procedure and returns a stream of its results
+    ///
+    /// Streaming calls always use their own bidirectional stream, regardless of the
+    /// configured stream mode. Dropping the returned stream cancels the call.
+    pub async fn call_stream<T, R>(&self, method: &str, request: &T) -> Result<CallStream<R>, Error>
+    where
+        T: serde::Serialize + prost::Message,
+        R: serde::de::DeserializeOwned + prost::Message + Default,
+    {
+        self.call_stream_with_options(method, request, CallOptions::default()).await
+    }
+    
+    /// Calls a server-streaming remote procedure with per-call options
+    ///
+    /// Unlike unary calls, streams are only bound by a timeout if one is set in `options`.
+    pub async fn call_stream_with_options<T, R>(
+        &self,
+        method: &str,
+        request: &T,
+        options: CallOptions,
+    ) -> Result<CallStream<R>, Error>
+    where
+        T: serde::Serialize + prost::Message,
+        R: serde::de::DeserializeOwned + prost::Message + Default,
+    {
+        // Serialize request payload
+        let payload = crate::serialize(request, self.config.format)?;
+        
+        // Create RPC request
+        let rpc_request = Request {
+            id: self.next_request_id().await,
+            method: method.to_string(),
+            payload,
+            kind: RequestKind::ServerStreaming,
+            end_of_stream: false,
+            timeout_ms: stream_timeout_ms(options.timeout),
+            metadata: options.metadata,
+        };
+        
+        // Open a dedicated stream and send the request
+        let (reader, mut writer) = self.open_call_stream().await?.split();
+        let request_bytes = crate::serialize(&rpc_request, self.config.format)?;
+        writer.send(request_bytes).await
+            .map_err(|e| Error::WebTransport(format!("Failed to send request: {}", e)))?;
+        
+        // Keep the writing half open until the trailer arrives, so dropping the stream can reset it
+        Ok(CallStream::new(reader, Some(writer), self.config.format))
+    }
+    
+    /// Opens a client-streaming or bidirectional streaming call
+    ///
+    /// Messages written to the returned sink are sent to the server; closing the sink
+    /// half-closes the client's side of the call. The returned stream yields the server's
+    /// replies until its end-of-stream trailer. Dropping the sink without closing it, or
+    /// dropping the stream before it ends, cancels the call.
+    ///
+    /// The server buffers a few messages the handler hasn't consumed yet. Sending further
+    /// messages while that buffer is full fails the call with
+    /// [`StatusCode::ResourceExhausted`](crate::StatusCode::ResourceExhausted).
+    pub async fn open_stream<T, R>(&self, method: &str) -> Result<(CallSink<T>, CallStream<R>), Error>
+    where
+        T: serde::Serialize + prost::Message,
+        R: serde::de::DeserializeOwned + prost::Message + Default,
+    {
+        self.open_stream_with_options(method, CallOptions::default()).await
+    }
+    
+    /// Opens a client-streaming or bidirectional streaming call with per-call options
+    pub async fn open_stream_with_options<T, R>(
+        &self,
+        method: &str,
+        options: CallOptions,
+    ) -> Result<(CallSink<T>, CallStream<R>), Error>
+    where
+        T: serde::Serialize + prost::Message,
+        R: serde::de::DeserializeOwned + prost::Message + Default,
+    {
+        let id = self.next_request_id().await;
+        
+        // Create the opening request, it carries no message of its own
+        let rpc_request = Request {
+            id,
+            method: method.to_string(),
+            payload: Bytes::new(),
+            kind: RequestKind::BidiStreaming,
+            end_of_stream: false,
+            timeout_ms: stream_timeout_ms(options.timeout),
+            metadata: options.metadata,
         };
         
+        // Open a dedicated stream and send the opening request
+        let (reader, mut writer) = self.open_call_stream().await?.split();
+        let request_bytes = crate::serialize(&rpc_request, self.config.format)?;
+        writer.send(request_bytes).await
+            .map_err(|e| Error::WebTransport(format!("Failed to send request: {}", e)))?;
+        
+        Ok((
+            CallSink::new(id, writer, self.config.format),
+            CallStream::new(reader, None, self.config.format),
+        ))
+    }
+    
+    /// Returns how long to wait for a unary call
+    ///
+    /// Calls made while handling another call never outlive that call's deadline.
+    fn call_timeout(&self, requested: Option<Duration>) -> Duration {
+        let timeout = requested.unwrap_or(Duration::from_millis(self.config.timeout_ms));
+        match CallContext::current().and_then(|context| context.remaining()) {
+            Some(remaining) => timeout.min(remaining),
+            None => timeout,
+        }
+    }
+    
+    /// Returns the next request ID
+    async fn next_request_id(&self) -> u64 {
+        let mut id_guard = self.next_id.lock().await;
+        let id = *id_guard;
+        *id_guard = id.wrapping_add(1);
+        id
+    }
+    
+    /// Calls a remote procedure, abandoning the call if `cancellation` is cancelled first
+    ///
+    /// Cancelling the call aborts its handler on the server.
+    pub async fn call_cancellable<T, R>(
+        &self,
+        method: &str,
+        request: &T,
+        cancellation: &CancellationToken,
+    ) -> Result<R, Error>
+    where
+        T: serde::Serialize + prost::Message,
+        R: serde::de::DeserializeOwned + prost::Message + Default,
+    {
+        // Dropping the call future cancels it on the server
+        tokio::select! {
+            result = self.call(method, request) => result,
+            _ = cancellation.cancelled() => Err(Error::Cancelled),
+        }
+    }
+    
+    /// Sends a request over the shared stream and waits for its response
+    async fn call_shared(&self, id: u64, request_bytes: Bytes, timeout: Duration) -> Result<Response, Error> {
         // Create response channel
         let (tx, rx) = oneshot::channel();
         
-        // Register pending request
+        // Register pending request, it is cancelled if abandoned before the response arrives
         {
             let mut pending_guard = self.pending.lock().await;
             pending_guard.insert(id, tx);
         }
+        let mut guard = PendingGuard {
+            id,
+            pending: self.pending.clone(),
+            writer: self.writer.clone(),
+            format: self.config.format,
+            completed: false,
+        };
         
-        // Serialize and send request
-        let request_bytes = crate::serialize(&rpc_request, self.config.format)?;
+        // Send request
         {
-            let mut stream_guard = self.message_stream.lock().await;
-            let stream = stream_guard.as_mut()
+            let mut writer_guard = self.writer.lock().await;
+            let writer = writer_guard.as_mut()
                 .ok_or_else(|| Error::ConnectionClosed)?;
             
-                stream.send(request_bytes).await
+            writer.send(request_bytes).await
                 .map_err(|e| Error::WebTransport(format!("Failed to send request: {}", e)))?;
         }
-
+        
         // Wait for response with timeout
-        let response_bytes = tokio::time::timeout(
-            Duration::from_millis(self.config.timeout_ms),
+        let result = tokio::time::timeout(
+            timeout,
             rx,
         ).await
         .map_err(|_| Error::Timeout)?
-        .map_err(|_| Error::ConnectionClosed)??;
+        .map_err(|_| Error::ConnectionClosed)?;
+        
+        guard.completed = true;
+        result
+    }
+    
+    /// Sends a request over its own bidirectional stream and waits for its response
+    async fn call_per_call(&self, request_bytes: Bytes, timeout: Duration) -> Result<Response, Error> {
+        let stream = self.open_call_stream().await?;
+        
+        // The stream is reset if the call is abandoned before the response arrives
+        let mut guard = StreamGuard {
+            stream,
+            completed: false,
+        };
         
-        // Deserialize response
-        let result = crate::deserialize(&response_bytes, self.config.format)?;
-        Ok(result)
+        // Send request, the sending side stays open so abandoning the call can reset it
+        guard.stream.send(request_bytes).await
+            .map_err(|e| Error::WebTransport(format!("Failed to send request: {}", e)))?;
+        
+        // Wait for response with timeout
+        let response_bytes = tokio::time::timeout(
+            timeout,
+            guard.stream.receive(),
+        ).await
+        .map_err(|_| Error::Timeout)??
+        .ok_or(Error::ConnectionClosed)?;
+        
+        guard.completed = true;
+        
+        // The call is over, let the server know no more requests follow
+        if let Err(e) = guard.stream.finish().await {
+            debug!("Failed to finish call stream: {}", e);
+        }
+        let response: Response = crate::deserialize(&response_bytes, self.config.format)?;
+        check_response(response)
+    }
+    
+    /// Opens a new bidirectional stream on the current session
+    async fn open_call_stream(&self) -> Result<MessageStream, Error> {
+        let session_guard = self.session.lock().await;
+        let session = session_guard.as_ref()
+            .ok_or(Error::ConnectionClosed)?;
+        
+        let stream = session.open_bi()
+            .await
+            .map_err(|e| Error::WebTransport(format!("Failed to open bidirectional stream: {}", e)))?;
+        
+        Ok(MessageStream::new(stream))
+    }
+    
+    /// Registers a service answering pushes, calls and datagrams sent by the server
+    ///
+    /// Only unary methods are called. Handlers run with a [`CallContext`] carrying the
+    /// server's metadata and deadline; it has no session, identity or peer certificates.
+    pub async fn register_service<S: Service>(&self, name: &str, service: S) -> Result<(), Error> {
+        let mut services = self.services.write().await;
+        services.insert(name.to_string(), Arc::new(service));
+        Ok(())
+    }
+    
+    /// Sends a fire-and-forget datagram to a method with a registered datagram handler
+    ///
+    /// Datagrams may be lost, duplicated or reordered, and the server drops those older
+    /// than the last one it accepted for the same method. The encoded datagram must fit
+    /// in a single packet, see [`Client::max_datagram_size`].
+    pub async fn send_datagram<T>(&self, method: &str, message: &T) -> Result<(), Error>
+    where
+        T: serde::Serialize + prost::Message,
+    {
+        let payload = crate::serialize(message, self.config.format)?;
+        self.send_datagram_raw(method, payload).await
+    }
+    
+    /// Sends a fire-and-forget datagram carrying an already serialized payload
+    pub async fn send_datagram_raw(&self, method: &str, payload: Bytes) -> Result<(), Error> {
+        let frame = DatagramProto {
+            method: method.to_string(),
+            sequence: self.next_datagram_sequence.fetch_add(1, Ordering::Relaxed),
+            payload,
+        };
+        let data = crate::serialize(&frame, self.config.format)?;
+        check_datagram_size(&data, self.max_datagram_size().await)?;
+        
+        let session_guard = self.session.lock().await;
+        let session = session_guard.as_ref()
+            .ok_or(Error::ConnectionClosed)?;
+        session.send_datagram(data)
+            .map_err(|e| Error::WebTransport(format!("Failed to send datagram: {}", e)))
+    }
+    
+    /// Returns the largest encoded datagram that currently fits in a packet, if datagrams are supported
+    ///
+    /// The limit follows the path MTU discovered by the connection and covers the method
+    /// name and sequence number as well as the payload.
+    pub async fn max_datagram_size(&self) -> Option<usize> {
+        let connection_guard = self.connection.lock().await;
+        max_datagram_size(connection_guard.as_ref()?)
+    }
+    
+    /// Lists the services of a server with reflection enabled
+    pub async fn list_services(&self) -> Result<Vec<ServiceInfo>, Error> {
+        let method = format_method_name(REFLECTION_SERVICE_NAME, "list_services");
+        let response: ListServicesResponse = self.call(&method, &ListServicesRequest {}).await?;
+        Ok(response.services)
+    }
+    
+    /// Describes a service of a server with reflection enabled
+    pub async fn describe_service(&self, name: &str) -> Result<DescribeServiceResponse, Error> {
+        let method = format_method_name(REFLECTION_SERVICE_NAME, "describe_service");
+        let request = DescribeServiceRequest { name: name.to_string() };
+        self.call(&method, &request).await
+    }
+    
+    /// Subscribes to the topics matching a glob pattern on a server with pub/sub enabled
+    ///
+    /// Up to `replay` recent events of matching topics are received before live ones.
+    /// Dropping the subscription cancels it.
+    pub async fn subscribe<T>(&self, pattern: &str, replay: u32) -> Result<Subscription<T>, Error>
+    where
+        T: serde::de::DeserializeOwned + prost::Message + Default,
+    {
+        let method = format_method_name(PUBSUB_SERVICE_NAME, "subscribe");
+        let request = SubscribeRequest { pattern: pattern.to_string(), replay };
+        let events: CallStream<Event> = self.call_stream(&method, &request).await?;
+        Ok(Subscription::new(events, self.config.format))
     }
     
     /// Closes the connection to the server
     pub async fn close(&self) -> Result<(), Error> {
         // Close session if open
+        self.connection.lock().await.take();
         let mut session_guard = self.session.lock().await;
         if let Some(session) = session_guard.take() {
             debug!("Closing WebTransport session");
             session.close().await;
         }
         
-        // Close message stream
-        let mut stream_guard = self.message_stream.lock().await;
-        *stream_guard = None;
+        // Close shared message stream
+        let mut writer_guard = self.writer.lock().await;
+        *writer_guard = None;
         
         // Clear pending requests with errors
         let mut pending_guard = self.pending.lock().await;
@@ -260,9 +710,119 @@ impl Client {
     }
 }
 
-impl Drop for Client {
+/// Closes the client's endpoint when dropped
+struct EndpointGuard {
+    /// QUIC endpoint
+    endpoint: Endpoint,
+}
+
+impl Drop for EndpointGuard {
     fn drop(&mut self) {
         // Close the endpoint to prevent resource leaks
         self.endpoint.close(0u32.into(), &[]);
     }
-}
\ No newline at end of file
+}
+
+/// Cancels a call made on the shared stream if it is dropped before completing
+struct PendingGuard {
+    /// Request ID of the call
+    id: u64,
+    /// Pending requests waiting for responses
+    pending: Arc<Mutex<HashMap<u64, ResponseChannel>>>,
+    /// Writing half of the shared message stream
+    writer: Arc<Mutex<Option<MessageWriter>>>,
+    /// Serialization format
+    format: SerializationFormat,
+    /// Whether the response has been received
+    completed: bool,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        
+        // Without a runtime, e.g. during teardown, there is nothing left to notify
+        let runtime = match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => runtime,
+            Err(_) => {
+                debug!("No runtime to cancel call {} on, skipping cancel request", self.id);
+                return;
+            }
+        };
+        
+        let id = self.id;
+        let pending = self.pending.clone();
+        let writer = self.writer.clone();
+        let format = self.format;
+        
+        // Drop can't wait, so forget the call and notify the server from a separate task
+        runtime.spawn(async move {
+            pending.lock().await.remove(&id);
+            
+            let cancel = Request {
+                id,
+                method: String::new(),
+                payload: Bytes::new(),
+                kind: RequestKind::Cancel,
+                end_of_stream: true,
+                timeout_ms: None,
+                metadata: Metadata::new(),
+            };
+            let cancel_bytes = match crate::serialize(&cancel, format) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    error!("Failed to serialize cancel request: {}", e);
+                    return;
+                }
+            };
+            
+            if let Some(writer) = writer.lock().await.as_mut() {
+                if let Err(e) = writer.send(cancel_bytes).await {
+                    debug!("Failed to send cancel request for {}: {}", id, e);
+                }
+            }
+        });
+    }
+}
+
+/// Resets a per-call stream if the call is dropped before completing
+///
+/// The request side stays open until the response arrives, so the peer sees the
+/// reset and cancels the handler while it is still running.
+pub(crate) struct StreamGuard {
+    /// Stream carrying the call
+    pub(crate) stream: MessageStream,
+    /// Whether the response has been received
+    pub(crate) completed: bool,
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.stream.reset(CALL_CANCELLED_CODE);
+            self.stream.stop(CALL_CANCELLED_CODE);
+        }
+    }
+}
+
+/// Returns the deadline budget sent with streaming calls
+///
+/// Streams made while handling another call never outlive that call's deadline.
+fn stream_timeout_ms(requested: Option<Duration>) -> Option<u64> {
+    let remaining = CallContext::current().and_then(|context| context.remaining());
+    let timeout = match (requested, remaining) {
+        (Some(requested), Some(remaining)) => Some(requested.min(remaining)),
+        (requested, remaining) => requested.or(remaining),
+    };
+    timeout.map(|timeout| timeout.as_millis() as u64)
+}
+
+/// Turns a wire response carrying an error into the matching error
+pub(crate) fn check_response(response: Response) -> Result<Response, Error> {
+    match response.error {
+        Some(status) => Err(Error::from(status)),
+        None => Ok(response),
+    }
+}
@@ -0,0 +1,448 @@
+use std::fmt::Write;
+
+use prost_build::{Config, Method, Service};
+
+/// Generates typed QuicServe servers and clients for the services of `.proto` files
+///
+/// For every proto service, `prost_build` then emits next to the message types:
+///
+/// - a `<SERVICE>_NAME` constant holding the name to register the service under,
+/// - an async trait named after the service, with one typed handler per method,
+/// - a `<Service>Server` adapter serving an implementation of the trait as a [`Service`](crate::Service),
+/// - a `<Service>Client` stub calling each method through a [`Client`](crate::Client).
+///
+/// Methods are called by their snake_case names, so `rpc StreamData` of `SampleService`
+/// is called as `SampleService.stream_data`. Unary, server-streaming, client-streaming
+/// and bidirectional streaming methods are all supported. Used from a build script:
+///
+/// ```ignore
+/// let mut config = prost_build::Config::new();
+/// quicserve::codegen::ServiceGenerator::new().configure(&mut config);
+/// config.compile_protos(&["protos/service.proto"], &["protos/"])?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServiceGenerator {
+    /// Path the generated code refers to QuicServe by
+    crate_path: String,
+}
+
+impl Default for ServiceGenerator {
+    fn default() -> Self {
+        Self {
+            crate_path: "::quicserve".to_string(),
+        }
+    }
+}
+
+impl ServiceGenerator {
+    /// Creates a new ServiceGenerator
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Sets the path the generated code refers to QuicServe by, `::quicserve` by default
+    pub fn with_crate_path(mut self, crate_path: impl Into<String>) -> Self {
+        self.crate_path = crate_path.into();
+        self
+    }
+    
+    /// Installs the generator on a `prost_build` configuration
+    ///
+    /// Generated messages also derive serde's `Serialize` and `Deserialize`, which
+    /// QuicServe needs to send them in either serialization format.
+    pub fn configure(self, config: &mut Config) {
+        config
+            .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]")
+            .service_generator(Box::new(self));
+    }
+    
+    /// Emits the name constant and the handler trait of a service
+    fn generate_trait(&self, service: &Service, buf: &mut String) {
+        let c = &self.crate_path;
+        
+        writeln!(buf, "/// Name the `{}` service is registered under", service.proto_name).unwrap();
+        writeln!(buf, "pub const {}: &str = \"{}\";", name_constant(&service.proto_name), service.proto_name).unwrap();
+        writeln!(buf).unwrap();
+        
+        writeln!(buf, "/// Handlers of the `{}` service", service.proto_name).unwrap();
+        if !service.comments.leading.is_empty() {
+            writeln!(buf, "///").unwrap();
+            push_comments(buf, &service.comments.leading, "");
+        }
+        writeln!(buf, "#[{}::async_trait]", c).unwrap();
+        writeln!(buf, "pub trait {}: Send + Sync + 'static {{", service.name).unwrap();
+        for method in &service.methods {
+            push_method_docs(buf, &method.comments.leading, &format!("Handles `{}`", method.proto_name));
+            let request = match method.client_streaming {
+                true => format!("requests: {}::TypedStream<{}>", c, method.input_type),
+                false => format!("request: {}", method.input_type),
+            };
+            let response = match method.server_streaming {
+                true => format!("{}::TypedStream<{}>", c, method.output_type),
+                false => method.output_type.clone(),
+            };
+            writeln!(
+                buf,
+                "    async fn {}(&self, context: &{}::CallContext, {}) -> Result<{}, {}::Error>;",
+                method.name, c, request, response, c
+            ).unwrap();
+        }
+        writeln!(buf, "}}").unwrap();
+        writeln!(buf).unwrap();
+    }
+    
+    /// Emits the adapter serving an implementation of the handler trait
+    fn generate_server(&self, service: &Service, buf: &mut String) {
+        let c = &self.crate_path;
+        let server = format!("{}Server", service.name);
+        
+        writeln!(buf, "/// Adapter serving a [`{}`] implementation as a QuicServe service", service.name).unwrap();
+        writeln!(buf, "///").unwrap();
+        writeln!(buf, "/// Register it under [`{}`].", name_constant(&service.proto_name)).unwrap();
+        writeln!(buf, "pub struct {}<T> {{", server).unwrap();
+        writeln!(buf, "    /// Wrapped implementation").unwrap();
+        writeln!(buf, "    inner: T,").unwrap();
+        writeln!(buf, "}}").unwrap();
+        writeln!(buf).unwrap();
+        
+        writeln!(buf, "impl<T: {}> {}<T> {{", service.name, server).unwrap();
+        writeln!(buf, "    /// Creates a new {}", server).unwrap();
+        writeln!(buf, "    pub fn new(inner: T) -> Self {{").unwrap();
+        writeln!(buf, "        Self {{ inner }}").unwrap();
+        writeln!(buf, "    }}").unwrap();
+        writeln!(buf).unwrap();
+        writeln!(buf, "    /// Returns the wrapped implementation").unwrap();
+        writeln!(buf, "    pub fn into_inner(self) -> T {{").unwrap();
+        writeln!(buf, "        self.inner").unwrap();
+        writeln!(buf, "    }}").unwrap();
+        writeln!(buf, "}}").unwrap();
+        writeln!(buf).unwrap();
+        
+        let unary: Vec<&Method> = service.methods.iter().filter(|m| !m.client_streaming && !m.server_streaming).collect();
+        let server_streaming: Vec<&Method> = service.methods.iter().filter(|m| !m.client_streaming && m.server_streaming).collect();
+        let client_streaming: Vec<&Method> = service.methods.iter().filter(|m| m.client_streaming).collect();
+        
+        writeln!(buf, "#[{}::async_trait]", c).unwrap();
+        writeln!(buf, "impl<T: {}> {}::Service for {}<T> {{", service.name, c, server).unwrap();
+        
+        // Unary methods, always emitted since `call` has no default
+        writeln!(buf, "    async fn call(&self, method: &str, payload: ::prost::bytes::Bytes) -> Result<::prost::bytes::Bytes, {}::Error> {{", c).unwrap();
+        if unary.is_empty() {
+            writeln!(buf, "        let _ = payload;").unwrap();
+            writeln!(buf, "        Err({}::Error::MethodNotFound(method.to_string()))", c).unwrap();
+        } else {
+            self.push_context(service, buf);
+            writeln!(buf, "        match method {{").unwrap();
+            for method in unary {
+                writeln!(buf, "            \"{}\" => {{", method.name).unwrap();
+                writeln!(buf, "                let request = {}::deserialize(&payload, format)?;", c).unwrap();
+                writeln!(buf, "                let response = self.inner.{}(&context, request).await?;", method.name).unwrap();
+                writeln!(buf, "                {}::serialize(&response, format)", c).unwrap();
+                writeln!(buf, "            }}").unwrap();
+            }
+            writeln!(buf, "            _ => Err({}::Error::MethodNotFound(method.to_string())),", c).unwrap();
+            writeln!(buf, "        }}").unwrap();
+        }
+        writeln!(buf, "    }}").unwrap();
+        
+        // Server-streaming methods
+        if !server_streaming.is_empty() {
+            writeln!(buf).unwrap();
+            writeln!(buf, "    async fn call_stream(&self, method: &str, payload: ::prost::bytes::Bytes) -> Result<{}::ResponseStream, {}::Error> {{", c, c).unwrap();
+            self.push_context(service, buf);
+            writeln!(buf, "        match method {{").unwrap();
+            for method in server_streaming {
+                writeln!(buf, "            \"{}\" => {{", method.name).unwrap();
+                writeln!(buf, "                let request = {}::deserialize(&payload, format)?;", c).unwrap();
+                writeln!(buf, "                let responses = self.inner.{}(&context, request).await?;", method.name).unwrap();
+                writeln!(buf, "                Ok({}::streaming::encode_stream(responses, format))", c).unwrap();
+                writeln!(buf, "            }}").unwrap();
+            }
+            writeln!(buf, "            _ => Err({}::Error::MethodNotFound(method.to_string())),", c).unwrap();
+            writeln!(buf, "        }}").unwrap();
+            writeln!(buf, "    }}").unwrap();
+        }
+        
+        // Client-streaming and bidirectional streaming methods
+        if !client_streaming.is_empty() {
+            writeln!(buf).unwrap();
+            writeln!(buf, "    async fn call_bidi(&self, method: &str, requests: {}::RequestStream) -> Result<{}::ResponseStream, {}::Error> {{", c, c, c).unwrap();
+            self.push_context(service, buf);
+            writeln!(buf, "        match method {{").unwrap();
+            for method in client_streaming {
+                writeln!(buf, "            \"{}\" => {{", method.name).unwrap();
+                writeln!(buf, "                let requests = {}::streaming::decode_stream(requests, format);", c).unwrap();
+                if method.server_streaming {
+                    writeln!(buf, "                let responses = self.inner.{}(&context, requests).await?;", method.name).unwrap();
+                    writeln!(buf, "                Ok({}::streaming::encode_stream(responses, format))", c).unwrap();
+                } else {
+                    writeln!(buf, "                let response = self.inner.{}(&context, requests).await?;", method.name).unwrap();
+                    writeln!(buf, "                {}::streaming::encode_single(&response, format)", c).unwrap();
+                }
+                writeln!(buf, "            }}").unwrap();
+            }
+            writeln!(buf, "            _ => Err({}::Error::MethodNotFound(method.to_string())),", c).unwrap();
+            writeln!(buf, "        }}").unwrap();
+            writeln!(buf, "    }}").unwrap();
+        }
+        
+        writeln!(buf).unwrap();
+        writeln!(buf, "    fn methods(&self) -> Vec<String> {{").unwrap();
+        let methods: Vec<String> = service.methods.iter().map(|m| format!("\"{}\".into()", m.name)).collect();
+        writeln!(buf, "        vec![{}]", methods.join(", ")).unwrap();
+        writeln!(buf, "    }}").unwrap();
+        writeln!(buf, "}}").unwrap();
+        writeln!(buf).unwrap();
+    }
+    
+    /// Emits the lookup of the call's context and serialization format
+    fn push_context(&self, service: &Service, buf: &mut String) {
+        let c = &self.crate_path;
+        writeln!(buf, "        let context = {}::CallContext::current()", c).unwrap();
+        writeln!(
+            buf,
+            "            .ok_or_else(|| {}::Error::Internal(\"{} called outside of a call\".to_string()))?;",
+            c, service.proto_name
+        ).unwrap();
+        writeln!(buf, "        let format = context.format();").unwrap();
+    }
+    
+    /// Emits the typed client stub
+    fn generate_client(&self, service: &Service, buf: &mut String) {
+        let c = &self.crate_path;
+        let client = format!("{}Client", service.name);
+        
+        writeln!(buf, "/// Typed client of the `{}` service", service.proto_name).unwrap();
+        writeln!(buf, "#[derive(Clone)]").unwrap();
+        writeln!(buf, "pub struct {} {{", client).unwrap();
+        writeln!(buf, "    /// Client the calls are made through").unwrap();
+        writeln!(buf, "    client: {}::Client,", c).unwrap();
+        writeln!(buf, "}}").unwrap();
+        writeln!(buf).unwrap();
+        
+        writeln!(buf, "impl {} {{", client).unwrap();
+        writeln!(buf, "    /// Creates a new {} making calls through a client", client).unwrap();
+        writeln!(buf, "    pub fn new(client: {}::Client) -> Self {{", c).unwrap();
+        writeln!(buf, "        Self {{ client }}").unwrap();
+        writeln!(buf, "    }}").unwrap();
+        writeln!(buf).unwrap();
+        writeln!(buf, "    /// Returns the client the calls are made through").unwrap();
+        writeln!(buf, "    pub fn inner(&self) -> &{}::Client {{", c).unwrap();
+        writeln!(buf, "        &self.client").unwrap();
+        writeln!(buf, "    }}").unwrap();
+        
+        for method in &service.methods {
+            let full_name = format!("{}.{}", service.proto_name, method.name);
+            let (input, output) = (&method.input_type, &method.output_type);
+            writeln!(buf).unwrap();
+            let verb = if method.client_streaming { "Opens" } else { "Calls" };
+            push_method_docs(buf, &method.comments.leading, &format!("{} `{}`", verb, method.proto_name));
+            
+            match (method.client_streaming, method.server_streaming) {
+                (false, false) => {
+                    writeln!(buf, "    pub async fn {}(&self, request: &{}) -> Result<{}, {}::Error> {{", method.name, input, output, c).unwrap();
+                    writeln!(buf, "        self.client.call(\"{}\", request).await", full_name).unwrap();
+                    writeln!(buf, "    }}").unwrap();
+                    writeln!(buf).unwrap();
+                    writeln!(buf, "    /// Calls `{}` with per-call options", method.proto_name).unwrap();
+                    writeln!(
+                        buf,
+                        "    pub async fn {}_with_options(&self, request: &{}, options: {}::CallOptions) -> Result<{}::CallResponse<{}>, {}::Error> {{",
+                        method.name, input, c, c, output, c
+                    ).unwrap();
+                    writeln!(buf, "        self.client.call_with_options(\"{}\", request, options).await", full_name).unwrap();
+                    writeln!(buf, "    }}").unwrap();
+                }
+                (false, true) => {
+                    writeln!(buf, "    pub async fn {}(&self, request: &{}) -> Result<{}::CallStream<{}>, {}::Error> {{", method.name, input, c, output, c).unwrap();
+                    writeln!(buf, "        self.client.call_stream(\"{}\", request).await", full_name).unwrap();
+                    writeln!(buf, "    }}").unwrap();
+                    writeln!(buf).unwrap();
+                    writeln!(buf, "    /// Calls `{}` with per-call options", method.proto_name).unwrap();
+                    writeln!(
+                        buf,
+                        "    pub async fn {}_with_options(&self, request: &{}, options: {}::CallOptions) -> Result<{}::CallStream<{}>, {}::Error> {{",
+                        method.name, input, c, c, output, c
+                    ).unwrap();
+                    writeln!(buf, "        self.client.call_stream_with_options(\"{}\", request, options).await", full_name).unwrap();
+                    writeln!(buf, "    }}").unwrap();
+                }
+                (true, _) => {
+                    writeln!(
+                        buf,
+                        "    pub async fn {}(&self) -> Result<({}::CallSink<{}>, {}::CallStream<{}>), {}::Error> {{",
+                        method.name, c, input, c, output, c
+                    ).unwrap();
+                    writeln!(buf, "        self.client.open_stream(\"{}\").await", full_name).unwrap();
+                    writeln!(buf, "    }}").unwrap();
+                    writeln!(buf).unwrap();
+                    writeln!(buf, "    /// Opens `{}` with per-call options", method.proto_name).unwrap();
+                    writeln!(
+                        buf,
+                        "    pub async fn {}_with_options(&self, options: {}::CallOptions) -> Result<({}::CallSink<{}>, {}::CallStream<{}>), {}::Error> {{",
+                        method.name, c, c, input, c, output, c
+                    ).unwrap();
+                    writeln!(buf, "        self.client.open_stream_with_options(\"{}\", options).await", full_name).unwrap();
+                    writeln!(buf, "    }}").unwrap();
+                }
+            }
+        }
+        writeln!(buf, "}}").unwrap();
+    }
+}
+
+impl prost_build::ServiceGenerator for ServiceGenerator {
+    fn generate(&mut self, service: Service, buf: &mut String) {
+        self.generate_trait(&service, buf);
+        self.generate_server(&service, buf);
+        self.generate_client(&service, buf);
+    }
+}
+
+/// Emits comments from a `.proto` file as doc comments
+fn push_comments(buf: &mut String, comments: &[String], indent: &str) {
+    for line in comments {
+        writeln!(buf, "{}///{}", indent, line.trim_end()).unwrap();
+    }
+}
+
+/// Emits the comments of a method as doc comments, or a default line if it has none
+///
+/// Generated items are public, so every one of them needs a doc comment.
+fn push_method_docs(buf: &mut String, comments: &[String], default: &str) {
+    if comments.is_empty() {
+        writeln!(buf, "    /// {}", default).unwrap();
+    } else {
+        push_comments(buf, comments, "    ");
+    }
+}
+
+/// Returns the name of the constant holding a service's name, such as `SAMPLE_SERVICE_NAME`
+fn name_constant(service: &str) -> String {
+    let mut constant = String::new();
+    let mut previous_lower = false;
+    for c in service.chars() {
+        if c.is_uppercase() && previous_lower {
+            constant.push('_');
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        constant.extend(c.to_uppercase());
+    }
+    constant.push_str("_NAME");
+    constant
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_build::Comments;
+    
+    /// Returns a method of the sample service
+    fn method(name: &str, proto_name: &str, client_streaming: bool, server_streaming: bool) -> Method {
+        Method {
+            name: name.to_string(),
+            proto_name: proto_name.to_string(),
+            comments: Comments { leading: vec![format!(" Calls {}", proto_name)], ..Default::default() },
+            input_type: "DataRequest".to_string(),
+            output_type: "DataResponse".to_string(),
+            input_proto_type: ".sample.DataRequest".to_string(),
+            output_proto_type: ".sample.DataResponse".to_string(),
+            options: Default::default(),
+            client_streaming,
+            server_streaming,
+        }
+    }
+    
+    /// Returns a service with the given methods
+    fn service(methods: Vec<Method>) -> Service {
+        Service {
+            name: "SampleService".to_string(),
+            proto_name: "SampleService".to_string(),
+            package: "sample".to_string(),
+            comments: Comments { leading: vec![" Sample service".to_string()], ..Default::default() },
+            methods,
+            options: Default::default(),
+        }
+    }
+    
+    /// Returns the code generated for a service
+    fn generate(mut generator: ServiceGenerator, service: Service) -> String {
+        let mut buf = String::new();
+        prost_build::ServiceGenerator::generate(&mut generator, service, &mut buf);
+        buf
+    }
+    
+    #[test]
+    fn generates_every_kind_of_method() {
+        let code = generate(ServiceGenerator::new(), service(vec![
+            method("get_data", "GetData", false, false),
+            method("stream_data", "StreamData", false, true),
+            method("upload_data", "UploadData", true, false),
+            method("exchange_data", "ExchangeData", true, true),
+        ]));
+        
+        // Name constant and handler trait
+        assert!(code.contains("pub const SAMPLE_SERVICE_NAME: &str = \"SampleService\";"));
+        assert!(code.contains("/// Sample service\n#[::quicserve::async_trait]\npub trait SampleService: Send + Sync + 'static {"));
+        assert!(code.contains("    /// Calls GetData\n    async fn get_data(&self, context: &::quicserve::CallContext, request: DataRequest) -> Result<DataResponse, ::quicserve::Error>;"));
+        assert!(code.contains("async fn stream_data(&self, context: &::quicserve::CallContext, request: DataRequest) -> Result<::quicserve::TypedStream<DataResponse>, ::quicserve::Error>;"));
+        assert!(code.contains("async fn upload_data(&self, context: &::quicserve::CallContext, requests: ::quicserve::TypedStream<DataRequest>) -> Result<DataResponse, ::quicserve::Error>;"));
+        assert!(code.contains("async fn exchange_data(&self, context: &::quicserve::CallContext, requests: ::quicserve::TypedStream<DataRequest>) -> Result<::quicserve::TypedStream<DataResponse>, ::quicserve::Error>;"));
+        
+        // Server adapter, dispatching each method from the matching entry point
+        assert!(code.contains("impl<T: SampleService> ::quicserve::Service for SampleServiceServer<T> {"));
+        assert!(code.contains("                let response = self.inner.get_data(&context, request).await?;"));
+        assert!(code.contains("                let responses = self.inner.stream_data(&context, request).await?;"));
+        assert!(code.contains("async fn call_bidi(&self, method: &str, requests: ::quicserve::RequestStream)"));
+        assert!(code.contains("                let response = self.inner.upload_data(&context, requests).await?;\n                ::quicserve::streaming::encode_single(&response, format)"));
+        assert!(code.contains("                let responses = self.inner.exchange_data(&context, requests).await?;\n                Ok(::quicserve::streaming::encode_stream(responses, format))"));
+        assert!(code.contains("vec![\"get_data\".into(), \"stream_data\".into(), \"upload_data\".into(), \"exchange_data\".into()]"));
+        
+        // Client stub, calling methods by their snake_case names
+        assert!(code.contains("pub struct SampleServiceClient {"));
+        assert!(code.contains("self.client.call(\"SampleService.get_data\", request).await"));
+        assert!(code.contains("self.client.call_stream_with_options(\"SampleService.stream_data\", request, options).await"));
+        assert!(code.contains("self.client.open_stream(\"SampleService.upload_data\").await"));
+        assert!(code.contains("self.client.open_stream_with_options(\"SampleService.exchange_data\", options).await"));
+    }
+    
+    #[test]
+    fn services_without_unary_methods_reject_unary_calls() {
+        let code = generate(ServiceGenerator::new(), service(vec![method("upload_data", "UploadData", true, false)]));
+        
+        assert!(code.contains("        let _ = payload;\n        Err(::quicserve::Error::MethodNotFound(method.to_string()))"));
+        assert!(!code.contains("async fn call_stream("));
+        assert!(code.contains("async fn call_bidi("));
+    }
+    
+    #[test]
+    fn generated_code_uses_the_crate_path() {
+        let generator = ServiceGenerator::new().with_crate_path("crate::rpc");
+        let code = generate(generator, service(vec![method("get_data", "GetData", false, false)]));
+        
+        assert!(code.contains("#[crate::rpc::async_trait]"));
+        assert!(code.contains("impl<T: SampleService> crate::rpc::Service for SampleServiceServer<T> {"));
+        assert!(!code.contains("::quicserve"));
+    }
+    
+    #[test]
+    fn methods_without_comments_get_default_docs() {
+        let mut get_data = method("get_data", "GetData", false, false);
+        get_data.comments = Comments::default();
+        let mut upload_data = method("upload_data", "UploadData", true, false);
+        upload_data.comments = Comments::default();
+        let code = generate(ServiceGenerator::new(), service(vec![get_data, upload_data]));
+        
+        assert!(code.contains("    /// Handles `GetData`\n    async fn get_data("));
+        assert!(code.contains("    /// Handles `UploadData`\n    async fn upload_data("));
+        assert!(code.contains("    /// Calls `GetData`\n    pub async fn get_data("));
+        assert!(code.contains("    /// Opens `UploadData`\n    pub async fn upload_data("));
+    }
+    
+    #[test]
+    fn name_constants_are_screaming_snake_case() {
+        assert_eq!(name_constant("SampleService"), "SAMPLE_SERVICE_NAME");
+        assert_eq!(name_constant("Echo"), "ECHO_NAME");
+        assert_eq!(name_constant("Storage2Backup"), "STORAGE2_BACKUP_NAME");
+    }
+}
@@ -1,12 +1,21 @@
+use std::collections::HashMap;
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
+use log::warn;
+use quinn::crypto::rustls::{QuicClientConfig, QuicServerConfig};
 use quinn::{ClientConfig, ServerConfig, TransportConfig};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{DigitallySignedStruct, SignatureScheme};
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
-use crate::SerializationFormat;
+use crate::{ClientAuthMode, SerializationFormat, StreamMode};
 
 /// Configuration for QuicServe
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,18 +32,43 @@ pub struct Config {
     /// Root CA certificate file path for verification (PEM format)
     pub ca_path: Option<PathBuf>,
     
-    /// Whether to verify peer certificates
+    /// Whether the client verifies the server's certificate
+    ///
+    /// Disabling verification accepts any certificate and is only meant for development.
     pub verify_peer: bool,
     
+    /// Whether the server requests and verifies client certificates against `ca_path`
+    pub client_auth: ClientAuthMode,
+    
+    /// Client certificate file path, presented to servers requesting one (PEM format)
+    pub client_cert_path: Option<PathBuf>,
+    
+    /// Client private key file path (PEM format)
+    pub client_key_path: Option<PathBuf>,
+    
     /// Serialization format
     pub format: SerializationFormat,
     
+    /// How client calls are mapped onto WebTransport streams
+    pub stream_mode: StreamMode,
+    
     /// Timeout for RPC calls in milliseconds
     pub timeout_ms: u64,
     
     /// Maximum concurrent streams per connection
     pub max_concurrent_streams: u64,
     
+    /// Maximum number of requests dispatched concurrently within a session
+    ///
+    /// Pub/sub subscriptions and health watches stay open for the life of the session, so
+    /// they don't count towards it.
+    pub max_in_flight_requests: usize,
+    
+    /// Whether to process requests within a session strictly one at a time, in arrival order
+    ///
+    /// Pub/sub subscriptions and health watches run alongside the other requests.
+    pub ordered_dispatch: bool,
+    
     /// Keep-alive interval in milliseconds
     pub keep_alive_ms: Option<u64>,
     
@@ -43,6 +77,15 @@ pub struct Config {
     
     /// Server name for TLS verification
     pub server_name: Option<String>,
+    
+    /// Bearer token sent by the client when establishing its session
+    pub bearer_token: Option<String>,
+    
+    /// API key sent by the client when establishing its session
+    pub api_key: Option<String>,
+    
+    /// Additional headers sent by the client when establishing its session
+    pub connect_headers: HashMap<String, String>,
 }
 
 impl Default for Config {
@@ -53,12 +96,21 @@ impl Default for Config {
             key_path: None,
             ca_path: None,
             verify_peer: true,
+            client_auth: ClientAuthMode::None,
+            client_cert_path: None,
+            client_key_path: None,
             format: SerializationFormat::Protobuf,
+            stream_mode: StreamMode::PerCall,
             timeout_ms: crate::DEFAULT_TIMEOUT_MS,
             max_concurrent_streams: 100,
+            max_in_flight_requests: 100,
+            ordered_dispatch: false,
             keep_alive_ms: Some(5000),
             idle_timeout_ms: Some(30000),
             server_name: None,
+            bearer_token: None,
+            api_key: None,
+            connect_headers: HashMap::new(),
         }
     }
 }
@@ -73,35 +125,41 @@ impl Config {
     }
     
     /// Builds QUIC client configuration
+    ///
+    /// The server's certificate is verified against `ca_path`, or the platform's
+    /// roots if unset, unless `verify_peer` is false. A client certificate is presented
+    /// whenever `client_cert_path` and `client_key_path` are set.
     pub fn build_client_config(&self) -> Result<ClientConfig, Error> {
-        let mut client_config = if let Some(ca_path) = &self.ca_path {
-            // Load CA certificates
-            let ca_cert = std::fs::read(ca_path)
-                .map_err(|e| Error::CertificateError(format!("Failed to read CA cert: {}", e)))?;
-            
-            // Parse CA certificate
-            let cert = rustls::Certificate(ca_cert);
-            let mut cert_store = rustls::RootCertStore::empty();
-            cert_store.add(&cert)
-                .map_err(|e| Error::CertificateError(format!("Failed to add CA cert: {}", e)))?;
-            
-            // Create client config with custom root store
-            let mut crypto = rustls::ClientConfig::builder()
-                .with_safe_defaults()
-                .with_root_certificates(cert_store)
-                .with_no_client_auth();
-            
-            // Set server name if provided
-            if let Some(server_name) = &self.server_name {
-                crypto.enable_sni = true;
-            }
-            
-            quinn::ClientConfig::new(Arc::new(crypto))
+        let provider = crypto_provider();
+        let builder = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .map_err(|e| Error::CertificateError(format!("Unsupported TLS configuration: {}", e)))?;
+        let builder = if !self.verify_peer {
+            // Only meant for development against self-signed certificates
+            warn!("Server certificate verification is disabled");
+            builder.dangerous().with_custom_certificate_verifier(Arc::new(SkipServerVerification(provider)))
+        } else if let Some(ca_path) = &self.ca_path {
+            builder.with_root_certificates(load_root_store(ca_path)?)
         } else {
-            // Use native root certificates
-            quinn::ClientConfig::with_native_roots()
+            builder.with_root_certificates(load_native_root_store()?)
         };
         
+        // Present a certificate if configured
+        let mut crypto = match self.client_identity()? {
+            Some((certs, key)) => builder.with_client_auth_cert(certs, key)
+                .map_err(|e| Error::CertificateError(format!("Invalid client certificate: {}", e)))?,
+            None => builder.with_no_client_auth(),
+        };
+        
+        // Set server name if provided
+        if self.server_name.is_some() {
+            crypto.enable_sni = true;
+        }
+        
+        let crypto = QuicClientConfig::try_from(crypto)
+            .map_err(|e| Error::CertificateError(format!("Unsupported TLS configuration: {}", e)))?;
+        let mut client_config = quinn::ClientConfig::new(Arc::new(crypto));
+        
         // Configure transport parameters
         let mut transport_config = TransportConfig::default();
         
@@ -129,28 +187,40 @@ impl Config {
         let key_path = self.key_path.as_ref()
             .ok_or_else(|| Error::InvalidConfig("Private key file path is required for server".into()))?;
         
-        // Load certificate chain
-        let cert_chain = std::fs::read(cert_path)
-            .map_err(|e| Error::CertificateError(format!("Failed to read certificate file: {}", e)))?;
+        // Load certificate chain and private key
+        let cert_chain = load_certs(cert_path)?;
+        let key = load_private_key(key_path)?;
         
-        // Load private key
-        let key_bytes = std::fs::read(key_path)
-            .map_err(|e| Error::CertificateError(format!("Failed to read private key file: {}", e)))?;
+        // Request client certificates if configured
+        let provider = crypto_provider();
+        let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .map_err(|e| Error::CertificateError(format!("Unsupported TLS configuration: {}", e)))?;
+        let builder = match self.client_auth {
+            ClientAuthMode::None => builder.with_no_client_auth(),
+            mode => {
+                let ca_path = self.ca_path.as_ref()
+                    .ok_or_else(|| Error::InvalidConfig("CA certificate file path is required to verify client certificates".into()))?;
+                let roots = load_root_store(ca_path)?;
+                
+                let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+                let verifier = if mode == ClientAuthMode::Required {
+                    verifier
+                } else {
+                    verifier.allow_unauthenticated()
+                };
+                let verifier = verifier.build()
+                    .map_err(|e| Error::CertificateError(format!("Invalid client certificate verifier: {}", e)))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+        };
         
         // Create server config
-        let server_config = quinn::ServerConfig::with_single_cert(
-            rustls_pemfile::certs(&mut cert_chain.as_slice())
-                .map_err(|e| Error::CertificateError(format!("Failed to parse certificate: {}", e)))?
-                .into_iter()
-                .map(rustls::Certificate)
-                .collect(),
-            rustls::PrivateKey(
-                rustls_pemfile::pkcs8_private_keys(&mut key_bytes.as_slice())
-                    .map_err(|e| Error::CertificateError(format!("Failed to parse private key: {}", e)))?
-                    .remove(0),
-            ),
-        )
-        .map_err(|e| Error::CertificateError(format!("Invalid certificate: {}", e)))?;
+        let crypto = builder.with_single_cert(cert_chain, key)
+            .map_err(|e| Error::CertificateError(format!("Invalid certificate: {}", e)))?;
+        let crypto = QuicServerConfig::try_from(crypto)
+            .map_err(|e| Error::CertificateError(format!("Unsupported TLS configuration: {}", e)))?;
+        let server_config = quinn::ServerConfig::with_crypto(Arc::new(crypto));
         
         // Configure transport parameters
         let mut transport_config = TransportConfig::default();
@@ -174,4 +244,113 @@ impl Config {
         
         Ok(server_config)
     }
-}
\ No newline at end of file
+    
+    /// Loads the client certificate chain and private key, if configured
+    fn client_identity(&self) -> Result<Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>, Error> {
+        match (&self.client_cert_path, &self.client_key_path) {
+            (Some(cert_path), Some(key_path)) => Ok(Some((load_certs(cert_path)?, load_private_key(key_path)?))),
+            (None, None) => Ok(None),
+            _ => Err(Error::InvalidConfig("Client certificate and key file paths must be set together".into())),
+        }
+    }
+}
+
+/// Returns the process-wide crypto provider, falling back to the ring provider quinn is built with
+fn crypto_provider() -> Arc<CryptoProvider> {
+    CryptoProvider::get_default()
+        .cloned()
+        .unwrap_or_else(|| Arc::new(rustls::crypto::ring::default_provider()))
+}
+
+/// Loads a PEM certificate chain
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, Error> {
+    let pem = std::fs::read(path)
+        .map_err(|e| Error::CertificateError(format!("Failed to read certificate file: {}", e)))?;
+    
+    let certs = rustls_pemfile::certs(&mut pem.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| Error::CertificateError(format!("Failed to parse certificate: {}", e)))?;
+    
+    if certs.is_empty() {
+        return Err(Error::CertificateError(format!("No certificates found in {}", path.display())));
+    }
+    Ok(certs)
+}
+
+/// Loads a PEM private key (PKCS#8, PKCS#1 or SEC1)
+fn load_private_key(path: &Path) -> Result<PrivateKeyDer<'static>, Error> {
+    let pem = std::fs::read(path)
+        .map_err(|e| Error::CertificateError(format!("Failed to read private key file: {}", e)))?;
+    
+    rustls_pemfile::private_key(&mut pem.as_slice())
+        .map_err(|e| Error::CertificateError(format!("Failed to parse private key: {}", e)))?
+        .ok_or_else(|| Error::CertificateError(format!("No private key found in {}", path.display())))
+}
+
+/// Loads the platform's trusted root certificates
+fn load_native_root_store() -> Result<rustls::RootCertStore, Error> {
+    let native = rustls_native_certs::load_native_certs();
+    if native.certs.is_empty() {
+        if let Some(e) = native.errors.first() {
+            return Err(Error::CertificateError(format!("Failed to load native root certificates: {}", e)));
+        }
+    }
+    
+    let mut roots = rustls::RootCertStore::empty();
+    let (_, ignored) = roots.add_parsable_certificates(native.certs);
+    if ignored > 0 {
+        warn!("Ignored {} unparsable native root certificates", ignored);
+    }
+    Ok(roots)
+}
+
+/// Accepts any server certificate, used when `verify_peer` is disabled
+///
+/// Handshake signatures are still checked with the provider's algorithms.
+#[derive(Debug)]
+struct SkipServerVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for SkipServerVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+    
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+    
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+    
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Loads a PEM CA bundle into a root certificate store
+fn load_root_store(path: &Path) -> Result<rustls::RootCertStore, Error> {
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots.add(cert)
+            .map_err(|e| Error::CertificateError(format!("Failed to add CA cert: {}", e)))?;
+    }
+    Ok(roots)
+}
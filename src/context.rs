@@ -0,0 +1,291 @@
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use h3_webtransport::{server, Session};
+use uuid::Uuid;
+
+use crate::auth::Identity;
+use crate::error::Error;
+use crate::metadata::Metadata;
+use crate::push::SessionHandle;
+use crate::SerializationFormat;
+
+/// Per-call state available to service handlers
+///
+/// The context of the call being handled is available through [`CallContext::current`]
+/// from anywhere inside the handler's future. Services registered with
+/// [`Client::register_service`](crate::Client::register_service) get a context too when
+/// the server calls them; it has no session, identity or peer certificates, and its
+/// peer address is the server's.
+#[derive(Debug, Clone)]
+pub struct CallContext {
+    /// Shared context state
+    inner: Arc<ContextInner>,
+}
+
+/// Information about an established session, shared by its calls
+pub(crate) struct SessionInfo {
+    /// Unique ID of the session
+    pub(crate) session_id: Uuid,
+    /// Address of the client
+    pub(crate) peer_addr: SocketAddr,
+    /// Verified certificate chain presented by the client, leaf first (DER)
+    pub(crate) peer_certificates: Arc<[Bytes]>,
+    /// Subject of the client's certificate, if one was presented
+    pub(crate) peer_subject: Option<String>,
+    /// Subject alternative names of the client's certificate
+    pub(crate) peer_sans: Vec<String>,
+    /// Identity established by the authenticator, if any
+    pub(crate) identity: Option<Arc<Identity>>,
+    /// Serialization format used on the session
+    pub(crate) format: SerializationFormat,
+    /// WebTransport session, used to open streams to the client
+    pub(crate) session: Arc<Session<server::Connection>>,
+    /// QUIC connection carrying the session, used to size datagrams
+    pub(crate) connection: quinn::Connection,
+    /// Timeout of calls made to the client without one of their own, in milliseconds
+    pub(crate) timeout_ms: u64,
+    /// ID of the next push or call made to the client
+    pub(crate) next_request_id: AtomicU64,
+    /// Sequence number of the next datagram sent to the client
+    pub(crate) next_datagram_sequence: AtomicU64,
+    /// Whether the session has ended, set before it is removed from the server's registries
+    pub(crate) closed: AtomicBool,
+}
+
+impl fmt::Debug for SessionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionInfo")
+            .field("session_id", &self.session_id)
+            .field("peer_addr", &self.peer_addr)
+            .field("peer_subject", &self.peer_subject)
+            .field("identity", &self.identity)
+            .field("format", &self.format)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Shared state of a [`CallContext`]
+#[derive(Debug)]
+struct ContextInner {
+    /// Request ID of the call
+    request_id: u64,
+    /// Full `service.method` name of the call
+    method: String,
+    /// Point in time by which the call must complete
+    deadline: Option<Instant>,
+    /// Metadata sent by the client
+    metadata: Metadata,
+    /// Identity of the caller, if authenticated
+    identity: Option<Arc<Identity>>,
+    /// Address of the peer that made the call
+    peer_addr: SocketAddr,
+    /// Serialization format of the call's messages
+    format: SerializationFormat,
+    /// Session the call was made on, unless the server made it
+    session: Option<Arc<SessionInfo>>,
+    /// Response headers set by the handler
+    headers: Mutex<Metadata>,
+    /// Response trailers set by the handler
+    trailers: Mutex<Metadata>,
+    /// Token cancelled when the client cancels the call
+    cancellation: CancellationToken,
+}
+
+tokio::task_local! {
+    /// Context of the call handled by the current task
+    static CURRENT_CONTEXT: CallContext;
+}
+
+impl CallContext {
+    /// Creates a new CallContext
+    pub(crate) fn new(
+        request_id: u64,
+        method: String,
+        deadline: Option<Instant>,
+        metadata: Metadata,
+        identity: Option<Arc<Identity>>,
+        session: Arc<SessionInfo>,
+        cancellation: CancellationToken,
+    ) -> Self {
+        Self {
+            inner: Arc::new(ContextInner {
+                request_id,
+                method,
+                deadline,
+                metadata,
+                identity,
+                peer_addr: session.peer_addr,
+                format: session.format,
+                session: Some(session),
+                headers: Mutex::new(Metadata::new()),
+                trailers: Mutex::new(Metadata::new()),
+                cancellation,
+            }),
+        }
+    }
+    
+    /// Creates a new CallContext for a push, call or datagram the server sent to a client
+    pub(crate) fn for_reverse_call(
+        request_id: u64,
+        method: String,
+        deadline: Option<Instant>,
+        metadata: Metadata,
+        peer_addr: SocketAddr,
+        format: SerializationFormat,
+    ) -> Self {
+        Self {
+            inner: Arc::new(ContextInner {
+                request_id,
+                method,
+                deadline,
+                metadata,
+                identity: None,
+                peer_addr,
+                format,
+                session: None,
+                headers: Mutex::new(Metadata::new()),
+                trailers: Mutex::new(Metadata::new()),
+                cancellation: CancellationToken::new(),
+            }),
+        }
+    }
+    
+    /// Returns the context of the call being handled, if any
+    pub fn current() -> Option<CallContext> {
+        CURRENT_CONTEXT.try_with(|context| context.clone()).ok()
+    }
+    
+    /// Runs a future with this context as the current call context
+    pub(crate) async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT_CONTEXT.scope(self, future).await
+    }
+    
+    /// Returns the request ID of the call
+    pub fn request_id(&self) -> u64 {
+        self.inner.request_id
+    }
+    
+    /// Returns the full `service.method` name of the call
+    pub fn method(&self) -> &str {
+        &self.inner.method
+    }
+    
+    /// Returns the metadata sent by the client
+    pub fn metadata(&self) -> &Metadata {
+        &self.inner.metadata
+    }
+    
+    /// Returns the unique ID of the session the call was made on
+    ///
+    /// Returns `None` for calls made by the server.
+    pub fn session_id(&self) -> Option<Uuid> {
+        self.inner.session.as_ref().map(|session| session.session_id)
+    }
+    
+    /// Returns a handle to the session the call was made on, used to push messages and make calls to the client
+    ///
+    /// Returns `None` for calls made by the server.
+    pub fn session(&self) -> Option<SessionHandle> {
+        self.inner.session.clone().map(SessionHandle::new)
+    }
+    
+    /// Returns the address of the peer that made the call
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.inner.peer_addr
+    }
+    
+    /// Returns the identity of the caller, if it was authenticated
+    ///
+    /// This is the identity established for the session, unless an interceptor replaced it.
+    pub fn identity(&self) -> Option<&Identity> {
+        self.inner.identity.as_deref()
+    }
+    
+    /// Returns the verified certificate chain presented by the client, leaf first (DER)
+    ///
+    /// Empty unless the server requests client certificates and the client presented one.
+    pub fn peer_certificates(&self) -> &[Bytes] {
+        self.inner.session.as_ref().map_or(&[], |session| &session.peer_certificates)
+    }
+    
+    /// Returns the subject of the client's certificate, if one was presented
+    pub fn peer_subject(&self) -> Option<&str> {
+        self.inner.session.as_ref()?.peer_subject.as_deref()
+    }
+    
+    /// Returns the subject alternative names of the client's certificate
+    ///
+    /// DNS names, email addresses, URIs and IP addresses are included, as text.
+    pub fn peer_sans(&self) -> &[String] {
+        self.inner.session.as_ref().map_or(&[], |session| &session.peer_sans)
+    }
+    
+    /// Returns the serialization format of the call's messages
+    pub fn format(&self) -> SerializationFormat {
+        self.inner.format
+    }
+    
+    /// Sets a response header, sent to the client with the first response
+    ///
+    /// Fails if the value isn't valid UTF-8 and the key doesn't end in `-bin`.
+    pub fn set_header(&self, key: impl Into<String>, value: impl Into<Bytes>) -> Result<(), Error> {
+        self.inner.headers.lock().unwrap().insert(key, value).map(|_| ())
+    }
+    
+    /// Sets a response trailer, sent to the client with the last response
+    ///
+    /// Fails if the value isn't valid UTF-8 and the key doesn't end in `-bin`.
+    pub fn set_trailer(&self, key: impl Into<String>, value: impl Into<Bytes>) -> Result<(), Error> {
+        self.inner.trailers.lock().unwrap().insert(key, value).map(|_| ())
+    }
+    
+    /// Takes the response headers set so far
+    pub(crate) fn take_headers(&self) -> Metadata {
+        std::mem::take(&mut *self.inner.headers.lock().unwrap())
+    }
+    
+    /// Takes the response trailers set so far
+    pub(crate) fn take_trailers(&self) -> Metadata {
+        std::mem::take(&mut *self.inner.trailers.lock().unwrap())
+    }
+    
+    /// Returns the point in time by which the call must complete, if any
+    ///
+    /// This is the earlier of the client's deadline and the server's own timeout.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.inner.deadline
+    }
+    
+    /// Returns the time left before the deadline, if any
+    ///
+    /// Pass this on as the timeout of downstream calls so they don't outlive this one.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.inner.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+    
+    /// Returns whether the call has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancellation.is_cancelled()
+    }
+    
+    /// Waits until the call is cancelled
+    pub async fn cancelled(&self) {
+        self.inner.cancellation.cancelled().await
+    }
+    
+    /// Returns the token cancelled when the call is cancelled
+    ///
+    /// Useful for passing cancellation on to spawned tasks or blocking work.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.inner.cancellation.clone()
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+
+use crate::context::CallContext;
+use crate::error::Error;
+use crate::proto::quicserve::DatagramProto;
+
+/// Bytes reserved in each QUIC datagram for the WebTransport session ID prefix
+pub(crate) const DATAGRAM_PREFIX_LEN: usize = 8;
+
+/// Returns the largest encoded datagram that currently fits in a packet of a connection
+pub(crate) fn max_datagram_size(connection: &quinn::Connection) -> Option<usize> {
+    connection.max_datagram_size()?.checked_sub(DATAGRAM_PREFIX_LEN)
+}
+
+/// Checks that an encoded datagram fits in a packet, datagrams can't be fragmented
+pub(crate) fn check_datagram_size(data: &[u8], max_size: Option<usize>) -> Result<(), Error> {
+    let max_size = max_size
+        .ok_or_else(|| Error::Unavailable("Datagrams are not supported on this connection".to_string()))?;
+    if data.len() > max_size {
+        return Err(Error::InvalidArgument(format!(
+            "Datagram of {} bytes exceeds the maximum of {} bytes", data.len(), max_size
+        )));
+    }
+    Ok(())
+}
+
+/// Handles datagrams sent with [`Client::send_datagram`](crate::Client::send_datagram)
+///
+/// Datagrams are unreliable: they may be lost, duplicated or reordered in transit. The
+/// server drops any datagram older than the last one it accepted for the same method on
+/// the same session, so handlers only ever see newer state. Handlers count towards the
+/// session's `max_in_flight_requests`, and datagrams arriving while it is reached are
+/// dropped rather than queued. Handlers can't reply; the context carries the session's
+/// details and the datagram's sequence number as its request ID, but response headers
+/// and trailers set on it are discarded.
+#[async_trait]
+pub trait DatagramHandler: Send + Sync + 'static {
+    /// Handles a datagram
+    async fn handle(&self, context: &CallContext, payload: Bytes);
+}
+
+/// Latest sequence number accepted for each method of a session
+#[derive(Debug, Default)]
+pub(crate) struct DatagramSequencer {
+    /// Last sequence number by method
+    last: HashMap<String, u64>,
+}
+
+impl DatagramSequencer {
+    /// Records a datagram, returning false if it is stale
+    pub(crate) fn accept(&mut self, frame: &DatagramProto) -> bool {
+        match self.last.get_mut(&frame.method) {
+            Some(last) if frame.sequence <= *last => false,
+            Some(last) => {
+                *last = frame.sequence;
+                true
+            }
+            None => {
+                self.last.insert(frame.method.clone(), frame.sequence);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn frame(method: &str, sequence: u64) -> DatagramProto {
+        DatagramProto {
+            method: method.to_string(),
+            sequence,
+            payload: Bytes::new(),
+        }
+    }
+    
+    #[test]
+    fn accepts_increasing_sequences() {
+        let mut sequencer = DatagramSequencer::default();
+        assert!(sequencer.accept(&frame("game.move", 0)));
+        assert!(sequencer.accept(&frame("game.move", 1)));
+        assert!(sequencer.accept(&frame("game.move", 5)));
+    }
+    
+    #[test]
+    fn drops_stale_and_duplicate_datagrams() {
+        let mut sequencer = DatagramSequencer::default();
+        assert!(sequencer.accept(&frame("game.move", 3)));
+        assert!(!sequencer.accept(&frame("game.move", 3)));
+        assert!(!sequencer.accept(&frame("game.move", 2)));
+        assert!(sequencer.accept(&frame("game.move", 4)));
+        assert!(!sequencer.accept(&frame("game.move", 0)));
+    }
+    
+    #[test]
+    fn sequences_methods_independently() {
+        let mut sequencer = DatagramSequencer::default();
+        assert!(sequencer.accept(&frame("game.move", 10)));
+        assert!(sequencer.accept(&frame("game.chat", 1)));
+        assert!(!sequencer.accept(&frame("game.move", 9)));
+        assert!(sequencer.accept(&frame("game.chat", 2)));
+    }
+}
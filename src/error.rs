@@ -1,6 +1,8 @@
 use std::io;
 use thiserror::Error;
 
+use crate::status::Status;
+
 /// Error types for the QuicServe RPC system
 #[derive(Error, Debug)]
 pub enum Error {
@@ -34,6 +36,33 @@ pub enum Error {
     #[error("Request timeout")]
     Timeout,
 
+    #[error("Call cancelled")]
+    Cancelled,
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    #[error("Resource exhausted: {0}")]
+    ResourceExhausted(String),
+
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+
+    #[error("RPC call failed: {0}")]
+    Rpc(Status),
+
+    /// Failed calls now carry a structured [`Status`] in [`Error::Rpc`]; this variant is never
+    /// returned and is kept so existing matches keep compiling
+    #[deprecated(since = "0.1.0", note = "failed calls return `Error::Rpc` with a structured `Status`")]
     #[error("RPC call failed: {0}")]
     RpcFailed(String),
 
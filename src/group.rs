@@ -0,0 +1,180 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures_util::future::join_all;
+use log::debug;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::error::Error;
+use crate::metadata::Metadata;
+use crate::push::SessionHandle;
+use crate::SerializationFormat;
+
+/// Group memberships, indexed both ways so closing sessions can be removed quickly
+#[derive(Default)]
+struct Groups {
+    /// Members of each group, by session ID
+    members: HashMap<String, HashMap<Uuid, SessionHandle>>,
+    /// Groups each session belongs to
+    memberships: HashMap<Uuid, HashSet<String>>,
+}
+
+/// Registry of named groups of sessions, used to broadcast to sets of clients
+///
+/// Obtained from [`Server::groups`](crate::Server::groups). Handlers typically add the
+/// session of the call they are handling, from [`CallContext::session`](crate::CallContext::session).
+/// Sessions leave every group automatically when they close, and groups without
+/// members cease to exist. Clones share the same groups.
+#[derive(Clone)]
+pub struct GroupRegistry {
+    /// Shared memberships
+    groups: Arc<RwLock<Groups>>,
+    /// Serialization format of broadcast messages
+    format: SerializationFormat,
+}
+
+impl GroupRegistry {
+    /// Creates a new, empty GroupRegistry
+    pub(crate) fn new(format: SerializationFormat) -> Self {
+        Self {
+            groups: Arc::new(RwLock::new(Groups::default())),
+            format,
+        }
+    }
+    
+    /// Adds a session to a group, returning false if it was already a member or has closed
+    pub async fn join(&self, group: &str, session: SessionHandle) -> bool {
+        let mut groups = self.groups.write().await;
+        
+        // Closed sessions have already left every group, and would never be removed again
+        if session.is_closed() {
+            return false;
+        }
+        let session_id = session.id();
+        groups.memberships.entry(session_id).or_default().insert(group.to_string());
+        groups.members.entry(group.to_string()).or_default().insert(session_id, session).is_none()
+    }
+    
+    /// Removes a session from a group, returning false if it wasn't a member
+    pub async fn leave(&self, group: &str, session_id: Uuid) -> bool {
+        let mut groups = self.groups.write().await;
+        if let Some(memberships) = groups.memberships.get_mut(&session_id) {
+            memberships.remove(group);
+            if memberships.is_empty() {
+                groups.memberships.remove(&session_id);
+            }
+        }
+        
+        let members = match groups.members.get_mut(group) {
+            Some(members) => members,
+            None => return false,
+        };
+        let removed = members.remove(&session_id).is_some();
+        if members.is_empty() {
+            groups.members.remove(group);
+        }
+        removed
+    }
+    
+    /// Removes a session from every group it belongs to
+    pub(crate) async fn remove_session(&self, session_id: Uuid) {
+        let mut groups = self.groups.write().await;
+        for group in groups.memberships.remove(&session_id).unwrap_or_default() {
+            if let Some(members) = groups.members.get_mut(&group) {
+                members.remove(&session_id);
+                if members.is_empty() {
+                    groups.members.remove(&group);
+                }
+            }
+        }
+    }
+    
+    /// Returns the sessions in a group
+    pub async fn members(&self, group: &str) -> Vec<SessionHandle> {
+        let groups = self.groups.read().await;
+        groups.members.get(group).map(|members| members.values().cloned().collect()).unwrap_or_default()
+    }
+    
+    /// Returns the groups a session belongs to
+    pub async fn groups_of(&self, session_id: Uuid) -> Vec<String> {
+        let groups = self.groups.read().await;
+        groups.memberships.get(&session_id).map(|groups| groups.iter().cloned().collect()).unwrap_or_default()
+    }
+    
+    /// Returns the names of every group with at least one member
+    pub async fn groups(&self) -> Vec<String> {
+        self.groups.read().await.members.keys().cloned().collect()
+    }
+    
+    /// Pushes a message to a method of every session in a group
+    ///
+    /// Returns the number of sessions the message was sent to. Sessions that can't be
+    /// reached are skipped.
+    pub async fn broadcast<T>(&self, group: &str, method: &str, message: &T) -> Result<usize, Error>
+    where
+        T: serde::Serialize + prost::Message,
+    {
+        let payload = crate::serialize(message, self.format)?;
+        Ok(self.broadcast_raw(group, method, payload, Metadata::new()).await)
+    }
+    
+    /// Pushes an already serialized message to every session in a group
+    pub async fn broadcast_raw(&self, group: &str, method: &str, payload: Bytes, metadata: Metadata) -> usize {
+        let members = self.members(group).await;
+        
+        // Push to every member concurrently, so one slow client doesn't hold up the rest
+        let pushes = members.iter().map(|session| {
+            let payload = payload.clone();
+            let metadata = metadata.clone();
+            async move {
+                let result = session.push_raw(method, payload, metadata).await;
+                if let Err(e) = &result {
+                    debug!("Broadcast to session {} failed: {}", session.id(), e);
+                }
+                result.is_ok()
+            }
+        });
+        join_all(pushes).await.into_iter().filter(|sent| *sent).count()
+    }
+    
+    /// Sends a fire-and-forget datagram to a method of every session in a group
+    ///
+    /// Returns the number of sessions the datagram was sent to. Datagrams may still be
+    /// lost in transit. Fails with [`Error::InvalidArgument`] without sending anything if
+    /// the encoded datagram doesn't fit in a packet of every member.
+    pub async fn broadcast_datagram<T>(&self, group: &str, method: &str, message: &T) -> Result<usize, Error>
+    where
+        T: serde::Serialize + prost::Message,
+    {
+        let payload = crate::serialize(message, self.format)?;
+        self.broadcast_datagram_raw(group, method, payload).await
+    }
+    
+    /// Sends a datagram carrying an already serialized payload to every session in a group
+    pub async fn broadcast_datagram_raw(&self, group: &str, method: &str, payload: Bytes) -> Result<usize, Error> {
+        let members = self.members(group).await;
+        
+        // Encode for every member first, so an oversized datagram reaches none of them
+        let mut datagrams = Vec::with_capacity(members.len());
+        for session in &members {
+            match session.encode_datagram(method, payload.clone()) {
+                Ok(data) => datagrams.push((session, data)),
+                Err(e @ Error::InvalidArgument(_)) => return Err(e),
+                Err(e) => debug!("Datagram broadcast to session {} failed: {}", session.id(), e),
+            }
+        }
+        
+        let sent = datagrams.into_iter()
+            .filter(|(session, data)| match session.send_encoded_datagram(data.clone()) {
+                Ok(()) => true,
+                Err(e) => {
+                    debug!("Datagram broadcast to session {} failed: {}", session.id(), e);
+                    false
+                }
+            })
+            .count();
+        Ok(sent)
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use tokio::sync::watch;
+
+use crate::context::CallContext;
+use crate::error::Error;
+use crate::{deserialize, serialize, ContextService, ResponseStream};
+
+/// Name the health service is registered under
+pub const HEALTH_SERVICE_NAME: &str = "health";
+
+// Messages of the service, generated from `health.proto`
+pub use crate::proto::quicserve::health::health_check_response::ServingStatus;
+pub use crate::proto::quicserve::health::{HealthCheckRequest, HealthCheckResponse};
+
+/// Serving statuses shared by the reporter and the health service
+#[derive(Debug, Default)]
+struct HealthState {
+    /// Status of each known service, the server as a whole being the empty name
+    statuses: HashMap<String, ServingStatus>,
+    /// Whether the server is shutting down, freezing every status at `NotServing`
+    shutting_down: bool,
+}
+
+/// Handle used to report the serving status of services
+///
+/// Every clone reports to the same server. Statuses can't be changed once the server
+/// has started shutting down.
+#[derive(Debug, Clone)]
+pub struct HealthReporter {
+    /// Shared statuses, notifying watchers on change
+    state: Arc<watch::Sender<HealthState>>,
+}
+
+impl HealthReporter {
+    /// Creates a new HealthReporter with the server as a whole serving
+    pub(crate) fn new() -> Self {
+        let mut state = HealthState::default();
+        state.statuses.insert(String::new(), ServingStatus::Serving);
+        Self {
+            state: Arc::new(watch::Sender::new(state)),
+        }
+    }
+    
+    /// Sets the status of a service, or of the server as a whole for an empty name
+    pub fn set_status(&self, service: &str, status: ServingStatus) {
+        self.state.send_if_modified(|state| {
+            if state.shutting_down {
+                return false;
+            }
+            state.statuses.insert(service.to_string(), status) != Some(status)
+        });
+    }
+    
+    /// Marks a service as serving
+    pub fn set_serving(&self, service: &str) {
+        self.set_status(service, ServingStatus::Serving);
+    }
+    
+    /// Marks a service as not serving
+    pub fn set_not_serving(&self, service: &str) {
+        self.set_status(service, ServingStatus::NotServing);
+    }
+    
+    /// Forgets a service, making it unknown to health checks
+    pub fn clear_status(&self, service: &str) {
+        self.state.send_if_modified(|state| !state.shutting_down && state.statuses.remove(service).is_some());
+    }
+    
+    /// Returns the status of a service, if it is known
+    pub fn status(&self, service: &str) -> Option<ServingStatus> {
+        self.state.borrow().statuses.get(service).copied()
+    }
+    
+    /// Marks every service as not serving and freezes the statuses
+    pub(crate) fn shutdown(&self) {
+        self.state.send_modify(|state| {
+            state.shutting_down = true;
+            for status in state.statuses.values_mut() {
+                *status = ServingStatus::NotServing;
+            }
+        });
+    }
+    
+    /// Returns the status of a service and every change to it
+    ///
+    /// The stream ends once the server has started shutting down and `NotServing`
+    /// has been reported, so watchers don't hold up the shutdown.
+    pub fn watch(&self, service: &str) -> impl Stream<Item = ServingStatus> + Send + 'static {
+        let service = service.to_string();
+        let receiver = self.state.subscribe();
+        
+        futures_util::stream::unfold((receiver, None), move |(mut receiver, last)| {
+            let service = service.clone();
+            async move {
+                loop {
+                    let (status, shutting_down) = {
+                        let state = receiver.borrow_and_update();
+                        let status = state.statuses.get(&service).copied().unwrap_or(ServingStatus::ServiceUnknown);
+                        (status, state.shutting_down)
+                    };
+                    
+                    // Report the current status unless it was already reported
+                    if last != Some(status) {
+                        return Some((status, (receiver, Some(status))));
+                    }
+                    if shutting_down {
+                        return None;
+                    }
+                    receiver.changed().await.ok()?;
+                }
+            }
+        })
+    }
+}
+
+/// Built-in service reporting the serving status of services
+///
+/// Enabled with [`Server::enable_health`](crate::Server::enable_health) and registered
+/// as `health`, with two methods taking a [`HealthCheckRequest`]:
+///
+/// - `check` returns the current status as a [`HealthCheckResponse`], or fails with
+///   [`Error::NotFound`] if the service is unknown.
+/// - `watch` streams a [`HealthCheckResponse`] with the current status, then another
+///   whenever it changes. Unknown services are reported as `ServiceUnknown`.
+pub struct HealthService {
+    /// Statuses of the server
+    reporter: HealthReporter,
+}
+
+impl HealthService {
+    /// Creates a new HealthService reporting the given statuses
+    pub(crate) fn new(reporter: HealthReporter) -> Self {
+        Self { reporter }
+    }
+}
+
+#[async_trait]
+impl ContextService for HealthService {
+    async fn call(&self, context: &CallContext, method: &str, payload: Bytes) -> Result<Bytes, Error> {
+        let format = context.format();
+        match method {
+            "check" => {
+                let request: HealthCheckRequest = deserialize(&payload, format)?;
+                let status = self.reporter.status(&request.service)
+                    .ok_or_else(|| Error::NotFound(format!("Unknown service: {}", request.service)))?;
+                serialize(&HealthCheckResponse { status: status as i32 }, format)
+            }
+            _ => Err(Error::MethodNotFound(method.to_string())),
+        }
+    }
+    
+    async fn call_stream(&self, context: &CallContext, method: &str, payload: Bytes) -> Result<ResponseStream, Error> {
+        let format = context.format();
+        match method {
+            "watch" => {
+                let request: HealthCheckRequest = deserialize(&payload, format)?;
+                let statuses = self.reporter.watch(&request.service);
+                Ok(Box::pin(statuses.map(move |status| {
+                    serialize(&HealthCheckResponse { status: status as i32 }, format)
+                })))
+            }
+            _ => Err(Error::MethodNotFound(method.to_string())),
+        }
+    }
+    
+    fn methods(&self) -> Vec<String> {
+        vec!["check".into(), "watch".into()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    
+    /// Returns the next item of a stream, failing the test if it takes too long
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        tokio::time::timeout(Duration::from_secs(5), stream.next()).await.expect("timed out waiting for stream")
+    }
+    
+    #[test]
+    fn only_the_server_is_known_at_first() {
+        let reporter = HealthReporter::new();
+        assert_eq!(reporter.status(""), Some(ServingStatus::Serving));
+        assert_eq!(reporter.status("db"), None);
+        
+        reporter.set_not_serving("db");
+        assert_eq!(reporter.status("db"), Some(ServingStatus::NotServing));
+        reporter.clear_status("db");
+        assert_eq!(reporter.status("db"), None);
+    }
+    
+    #[tokio::test]
+    async fn watch_reports_changes_and_ends_at_shutdown() {
+        let reporter = HealthReporter::new();
+        let mut statuses = Box::pin(reporter.watch("db"));
+        assert_eq!(next(&mut statuses).await, Some(ServingStatus::ServiceUnknown));
+        
+        reporter.set_serving("db");
+        assert_eq!(next(&mut statuses).await, Some(ServingStatus::Serving));
+        
+        // Setting the same status again isn't a change
+        reporter.set_serving("db");
+        reporter.set_not_serving("db");
+        assert_eq!(next(&mut statuses).await, Some(ServingStatus::NotServing));
+        reporter.set_serving("db");
+        assert_eq!(next(&mut statuses).await, Some(ServingStatus::Serving));
+        
+        reporter.shutdown();
+        assert_eq!(next(&mut statuses).await, Some(ServingStatus::NotServing));
+        assert_eq!(next(&mut statuses).await, None);
+    }
+    
+    #[tokio::test]
+    async fn watch_started_during_shutdown_ends_after_the_current_status() {
+        let reporter = HealthReporter::new();
+        reporter.shutdown();
+        
+        let mut statuses = Box::pin(reporter.watch(""));
+        assert_eq!(next(&mut statuses).await, Some(ServingStatus::NotServing));
+        assert_eq!(next(&mut statuses).await, None);
+    }
+    
+    #[test]
+    fn statuses_are_frozen_after_shutdown() {
+        let reporter = HealthReporter::new();
+        reporter.set_serving("db");
+        reporter.shutdown();
+        
+        reporter.set_serving("db");
+        reporter.set_serving("cache");
+        reporter.clear_status("db");
+        assert_eq!(reporter.status(""), Some(ServingStatus::NotServing));
+        assert_eq!(reporter.status("db"), Some(ServingStatus::NotServing));
+        assert_eq!(reporter.status("cache"), None);
+    }
+}
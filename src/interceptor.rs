@@ -0,0 +1,230 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::StreamExt;
+
+use crate::auth::Identity;
+use crate::error::Error;
+use crate::metadata::Metadata;
+use crate::{RequestKind, ResponseStream};
+
+/// A call as seen by interceptors before it is dispatched
+#[derive(Debug, Clone)]
+pub struct InterceptedRequest {
+    /// Full `service.method` name of the call
+    pub method: String,
+    /// Metadata sent by the client
+    pub metadata: Metadata,
+    /// Request payload, empty for bidirectional streaming calls
+    pub payload: Bytes,
+    /// Kind of call being made
+    pub kind: RequestKind,
+    /// Address of the client
+    pub peer_addr: SocketAddr,
+    /// Identity of the client's session, if authenticated
+    pub identity: Option<Arc<Identity>>,
+}
+
+/// Hook wrapping the dispatch of calls on the server
+///
+/// Interceptors are registered on the [`Server`](crate::Server), either globally or for a
+/// single service, and run as an ordered stack: request hooks run in registration order
+/// with global interceptors first, and response hooks run in the reverse order.
+#[async_trait]
+pub trait Interceptor: Send + Sync + 'static {
+    /// Called before the call is dispatched
+    ///
+    /// The request may be changed in place. Returning an error rejects the call with that
+    /// error; neither later interceptors nor the service are invoked.
+    async fn on_request(&self, request: &mut InterceptedRequest) -> Result<(), Error> {
+        let _ = request;
+        Ok(())
+    }
+    
+    /// Called with the result of a unary call, or with each message of a streaming call,
+    /// before it is sent to the client
+    ///
+    /// Only interceptors whose request hook accepted the call see its responses.
+    async fn on_response(&self, request: &InterceptedRequest, response: &mut Result<Bytes, Error>) {
+        let _ = (request, response);
+    }
+}
+
+/// Interceptors applying to a single call, outermost first
+#[derive(Clone, Default)]
+pub(crate) struct InterceptorChain {
+    /// Interceptors of the call
+    interceptors: Vec<Arc<dyn Interceptor>>,
+}
+
+impl InterceptorChain {
+    /// Creates a new InterceptorChain
+    pub(crate) fn new(interceptors: Vec<Arc<dyn Interceptor>>) -> Self {
+        Self { interceptors }
+    }
+    
+    /// Runs the request hooks in order, stopping at the first rejection
+    ///
+    /// Interceptors from the one that rejected the call onwards are dropped from the chain,
+    /// so their response hooks are skipped.
+    pub(crate) async fn intercept_request(&mut self, request: &mut InterceptedRequest) -> Result<(), Error> {
+        for index in 0..self.interceptors.len() {
+            let result = self.interceptors[index].on_request(request).await;
+            if let Err(err) = result {
+                self.interceptors.truncate(index);
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+    
+    /// Runs the response hooks in reverse order
+    pub(crate) async fn intercept_response(&self, request: &InterceptedRequest, response: &mut Result<Bytes, Error>) {
+        for interceptor in self.interceptors.iter().rev() {
+            interceptor.on_response(request, response).await;
+        }
+    }
+    
+    /// Wraps a response stream so every message passes through the response hooks
+    pub(crate) fn intercept_stream(self, request: Arc<InterceptedRequest>, stream: ResponseStream) -> ResponseStream {
+        if self.interceptors.is_empty() {
+            return stream;
+        }
+        
+        let chain = Arc::new(self);
+        Box::pin(stream.then(move |item| {
+            let chain = chain.clone();
+            let request = request.clone();
+            async move {
+                let mut item = item;
+                chain.intercept_response(&request, &mut item).await;
+                item
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    
+    /// Interceptor logging its hooks, optionally rejecting every call
+    struct Logging {
+        /// Name written to the log
+        name: &'static str,
+        /// Whether the request hook rejects the call
+        reject: bool,
+        /// Hooks run so far, shared by every interceptor of a test
+        log: Arc<Mutex<Vec<String>>>,
+    }
+    
+    #[async_trait]
+    impl Interceptor for Logging {
+        async fn on_request(&self, request: &mut InterceptedRequest) -> Result<(), Error> {
+            self.log.lock().unwrap().push(format!("request {}", self.name));
+            if self.reject {
+                return Err(Error::PermissionDenied(self.name.to_string()));
+            }
+            let mut payload = request.payload.to_vec();
+            payload.extend_from_slice(self.name.as_bytes());
+            request.payload = Bytes::from(payload);
+            Ok(())
+        }
+        
+        async fn on_response(&self, _request: &InterceptedRequest, response: &mut Result<Bytes, Error>) {
+            self.log.lock().unwrap().push(format!("response {}", self.name));
+            if let Ok(message) = response {
+                let mut payload = message.to_vec();
+                payload.extend_from_slice(self.name.as_bytes());
+                *message = Bytes::from(payload);
+            }
+        }
+    }
+    
+    /// Returns a chain of logging interceptors, the ones named in `rejecting` rejecting calls
+    fn chain(names: &[&'static str], rejecting: &[&str], log: &Arc<Mutex<Vec<String>>>) -> InterceptorChain {
+        let interceptors = names
+            .iter()
+            .map(|&name| Arc::new(Logging { name, reject: rejecting.contains(&name), log: log.clone() }) as Arc<dyn Interceptor>)
+            .collect();
+        InterceptorChain::new(interceptors)
+    }
+    
+    /// Returns a unary call to intercept
+    fn request() -> InterceptedRequest {
+        InterceptedRequest {
+            method: "service.method".to_string(),
+            metadata: Metadata::new(),
+            payload: Bytes::new(),
+            kind: RequestKind::Unary,
+            peer_addr: "127.0.0.1:4433".parse().unwrap(),
+            identity: None,
+        }
+    }
+    
+    #[tokio::test]
+    async fn response_hooks_run_in_reverse_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut chain = chain(&["a", "b", "c"], &[], &log);
+        let mut request = request();
+        
+        chain.intercept_request(&mut request).await.unwrap();
+        assert_eq!(request.payload, Bytes::from("abc"));
+        let mut response = Ok(Bytes::new());
+        chain.intercept_response(&request, &mut response).await;
+        assert_eq!(response.unwrap(), Bytes::from("cba"));
+        
+        let log = log.lock().unwrap().clone();
+        assert_eq!(log, ["request a", "request b", "request c", "response c", "response b", "response a"]);
+    }
+    
+    #[tokio::test]
+    async fn rejection_skips_later_interceptors() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut chain = chain(&["a", "b", "c"], &["b"], &log);
+        let mut request = request();
+        
+        let result = chain.intercept_request(&mut request).await;
+        assert!(matches!(result, Err(Error::PermissionDenied(name)) if name == "b"));
+        
+        // Only the interceptor that accepted the call sees its response
+        let mut response = Err(Error::PermissionDenied("b".to_string()));
+        chain.intercept_response(&request, &mut response).await;
+        
+        let log = log.lock().unwrap().clone();
+        assert_eq!(log, ["request a", "request b", "response a"]);
+    }
+    
+    #[tokio::test]
+    async fn every_streamed_message_passes_through_the_response_hooks() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut chain = chain(&["a", "b"], &[], &log);
+        let mut request = request();
+        chain.intercept_request(&mut request).await.unwrap();
+        
+        let messages = futures_util::stream::iter(vec![
+            Ok(Bytes::from("1")),
+            Ok(Bytes::from("2")),
+            Err(Error::Internal("failed".to_string())),
+        ]);
+        let stream = chain.intercept_stream(Arc::new(request), Box::pin(messages));
+        let responses: Vec<_> = stream.collect().await;
+        
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0].as_ref().unwrap(), &Bytes::from("1ba"));
+        assert_eq!(responses[1].as_ref().unwrap(), &Bytes::from("2ba"));
+        assert!(matches!(&responses[2], Err(Error::Internal(_))));
+        assert_eq!(log.lock().unwrap().iter().filter(|hook| hook.starts_with("response")).count(), 6);
+    }
+    
+    #[tokio::test]
+    async fn empty_chains_leave_streams_untouched() {
+        let chain = InterceptorChain::default();
+        let stream = chain.intercept_stream(Arc::new(request()), Box::pin(futures_util::stream::iter(vec![Ok(Bytes::from("1"))])));
+        let responses: Vec<_> = stream.collect().await;
+        assert_eq!(responses[0].as_ref().unwrap(), &Bytes::from("1"));
+    }
+}
@@ -0,0 +1,383 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::{AlgorithmParameters, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde_json::{Map, Value};
+
+use crate::auth::{parse_bearer_token, Authenticator, ConnectInfo, Identity};
+use crate::error::Error;
+use crate::interceptor::{InterceptedRequest, Interceptor};
+
+/// Metadata key carrying the bearer token of a single call
+pub const AUTHORIZATION_METADATA_KEY: &str = "authorization";
+
+/// Family of algorithms a key verifies signatures of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyFamily {
+    /// HMAC with a shared secret
+    Hmac,
+    /// RSA, with PKCS#1 or PSS padding
+    Rsa,
+    /// ECDSA
+    Ec,
+    /// EdDSA
+    Ed,
+}
+
+impl KeyFamily {
+    /// Returns the family of an algorithm
+    fn of(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => KeyFamily::Hmac,
+            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => KeyFamily::Rsa,
+            Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512 => KeyFamily::Rsa,
+            Algorithm::ES256 | Algorithm::ES384 => KeyFamily::Ec,
+            Algorithm::EdDSA => KeyFamily::Ed,
+        }
+    }
+}
+
+/// Key used to verify token signatures
+#[derive(Clone)]
+struct VerificationKey {
+    /// ID of the key, matched against the `kid` header of tokens
+    key_id: Option<String>,
+    /// Family of algorithms the key verifies
+    family: KeyFamily,
+    /// Decoding key
+    key: DecodingKey,
+}
+
+/// Authenticator validating JSON Web Tokens against locally configured keys
+///
+/// Tokens must be signed by one of the keys and pass the `exp` and `nbf` checks, as
+/// well as the `aud` and `iss` checks when audiences or issuers are configured. The
+/// `sub` claim becomes the subject of the identity, and every claim becomes an
+/// attribute of it, so claims can be read by handlers and matched by authorization rules.
+/// Array claims contribute one value per element; other non-text claims are kept as JSON.
+///
+/// Registered with [`Server::set_authenticator`](crate::Server::set_authenticator), it
+/// validates the bearer token of the session's CONNECT request. Registered with
+/// [`Server::add_interceptor`](crate::Server::add_interceptor), it validates the bearer
+/// token in the `authorization` metadata of each call, replacing the session's identity
+/// for that call; calls without one keep the session's identity.
+#[derive(Clone)]
+pub struct JwtAuthenticator {
+    /// Keys used to verify signatures
+    keys: Vec<VerificationKey>,
+    /// Accepted audiences, any if empty
+    audiences: Vec<String>,
+    /// Accepted issuers, any if empty
+    issuers: Vec<String>,
+    /// Clock skew tolerated when checking `exp` and `nbf`, in seconds
+    leeway: u64,
+}
+
+impl JwtAuthenticator {
+    /// Creates a new JwtAuthenticator with no keys
+    pub fn new() -> Self {
+        Self {
+            keys: Vec::new(),
+            audiences: Vec::new(),
+            issuers: Vec::new(),
+            leeway: 60,
+        }
+    }
+    
+    /// Adds a shared secret verifying HS256, HS384 and HS512 tokens
+    pub fn with_secret(mut self, secret: impl AsRef<[u8]>) -> Self {
+        self.keys.push(VerificationKey {
+            key_id: None,
+            family: KeyFamily::Hmac,
+            key: DecodingKey::from_secret(secret.as_ref()),
+        });
+        self
+    }
+    
+    /// Adds a PEM-encoded RSA public key verifying RS* and PS* tokens
+    pub fn with_rsa_pem(mut self, pem: &[u8]) -> Result<Self, Error> {
+        let key = DecodingKey::from_rsa_pem(pem)
+            .map_err(|e| Error::InvalidConfig(format!("Invalid RSA public key: {}", e)))?;
+        self.keys.push(VerificationKey {
+            key_id: None,
+            family: KeyFamily::Rsa,
+            key,
+        });
+        Ok(self)
+    }
+    
+    /// Adds a PEM-encoded EC public key verifying ES256 and ES384 tokens
+    pub fn with_ec_pem(mut self, pem: &[u8]) -> Result<Self, Error> {
+        let key = DecodingKey::from_ec_pem(pem)
+            .map_err(|e| Error::InvalidConfig(format!("Invalid EC public key: {}", e)))?;
+        self.keys.push(VerificationKey {
+            key_id: None,
+            family: KeyFamily::Ec,
+            key,
+        });
+        Ok(self)
+    }
+    
+    /// Adds every key of a JWKS file
+    ///
+    /// Keys with a `kid` only verify tokens whose header names them.
+    pub fn with_jwks_file(mut self, path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let json = std::fs::read(path)
+            .map_err(|e| Error::InvalidConfig(format!("Failed to read JWKS file {}: {}", path.display(), e)))?;
+        let jwks: JwkSet = serde_json::from_slice(&json)
+            .map_err(|e| Error::InvalidConfig(format!("Invalid JWKS file {}: {}", path.display(), e)))?;
+        
+        for jwk in &jwks.keys {
+            let family = match jwk.algorithm {
+                AlgorithmParameters::OctetKey(_) => KeyFamily::Hmac,
+                AlgorithmParameters::RSA(_) => KeyFamily::Rsa,
+                AlgorithmParameters::EllipticCurve(_) => KeyFamily::Ec,
+                AlgorithmParameters::OctetKeyPair(_) => KeyFamily::Ed,
+            };
+            let key = DecodingKey::from_jwk(jwk)
+                .map_err(|e| Error::InvalidConfig(format!("Invalid key in JWKS file {}: {}", path.display(), e)))?;
+            self.keys.push(VerificationKey {
+                key_id: jwk.common.key_id.clone(),
+                family,
+                key,
+            });
+        }
+        Ok(self)
+    }
+    
+    /// Accepts tokens issued for `audience`
+    ///
+    /// Once an audience is set, tokens must carry an `aud` claim naming one of them.
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audiences.push(audience.into());
+        self
+    }
+    
+    /// Accepts tokens issued by `issuer`
+    ///
+    /// Once an issuer is set, tokens must carry an `iss` claim naming one of them.
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuers.push(issuer.into());
+        self
+    }
+    
+    /// Sets the clock skew tolerated when checking `exp` and `nbf` (60 seconds by default)
+    pub fn with_leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway.as_secs();
+        self
+    }
+    
+    /// Validates a token, returning the identity it asserts
+    pub fn validate(&self, token: &str) -> Result<Identity, Error> {
+        let header = jsonwebtoken::decode_header(token)
+            .map_err(|e| Error::AuthenticationFailed(format!("Malformed token: {}", e)))?;
+        
+        // Build the claim checks for the token's algorithm
+        let mut validation = Validation::new(header.alg);
+        validation.leeway = self.leeway;
+        validation.validate_nbf = true;
+        if self.audiences.is_empty() {
+            validation.validate_aud = false;
+        } else {
+            validation.set_audience(&self.audiences);
+        }
+        if !self.issuers.is_empty() {
+            validation.set_issuer(&self.issuers);
+        }
+        
+        // Try every key able to verify the token until one accepts its signature
+        let family = KeyFamily::of(header.alg);
+        let candidates = self.keys.iter()
+            .filter(|key| key.family == family)
+            .filter(|key| match (&key.key_id, &header.kid) {
+                (Some(key_id), Some(kid)) => key_id == kid,
+                _ => true,
+            });
+        
+        let mut last_error = None;
+        for candidate in candidates {
+            match jsonwebtoken::decode::<Map<String, Value>>(token, &candidate.key, &validation) {
+                Ok(data) => return identity_from_claims(data.claims),
+                Err(e) if matches!(e.kind(), ErrorKind::InvalidSignature) => last_error = Some(e),
+                Err(e) => return Err(Error::AuthenticationFailed(format!("Invalid token: {}", e))),
+            }
+        }
+        
+        Err(match last_error {
+            Some(e) => Error::AuthenticationFailed(format!("Invalid token: {}", e)),
+            None => Error::AuthenticationFailed(format!("No key to verify {:?} tokens", header.alg)),
+        })
+    }
+}
+
+impl Default for JwtAuthenticator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Authenticator for JwtAuthenticator {
+    async fn authenticate(&self, info: &ConnectInfo) -> Result<Identity, Error> {
+        let token = info.bearer_token()
+            .ok_or_else(|| Error::AuthenticationFailed("Missing bearer token".to_string()))?;
+        self.validate(token)
+    }
+}
+
+#[async_trait]
+impl Interceptor for JwtAuthenticator {
+    async fn on_request(&self, request: &mut InterceptedRequest) -> Result<(), Error> {
+        // Calls without a token of their own keep the session's identity
+        let value = match request.metadata.get_str(AUTHORIZATION_METADATA_KEY) {
+            Some(value) => value,
+            None => return Ok(()),
+        };
+        let token = parse_bearer_token(value)
+            .ok_or_else(|| Error::AuthenticationFailed("Malformed authorization metadata".to_string()))?;
+        
+        let identity = self.validate(token)?;
+        request.identity = Some(Arc::new(identity));
+        Ok(())
+    }
+}
+
+/// Builds the identity asserted by the claims of a validated token
+fn identity_from_claims(claims: Map<String, Value>) -> Result<Identity, Error> {
+    let subject = match claims.get("sub") {
+        Some(Value::String(subject)) => subject.clone(),
+        _ => return Err(Error::AuthenticationFailed("Token has no subject".to_string())),
+    };
+    
+    let mut identity = Identity::new(subject);
+    for (name, value) in claims {
+        match value {
+            Value::Null => {}
+            Value::Array(values) => {
+                for value in values {
+                    identity = identity.with_attribute(name.clone(), claim_text(value));
+                }
+            }
+            value => identity = identity.with_attribute(name, claim_text(value)),
+        }
+    }
+    Ok(identity)
+}
+
+/// Returns a claim value as text, keeping non-text values as JSON
+fn claim_text(value: Value) -> String {
+    match value {
+        Value::String(text) => text,
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use base64::Engine;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::json;
+    
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+    
+    fn sign(header: Header, claims: Value, secret: &[u8]) -> String {
+        jsonwebtoken::encode(&header, &claims, &EncodingKey::from_secret(secret)).unwrap()
+    }
+    
+    fn hmac_key(key_id: &str, secret: &[u8]) -> VerificationKey {
+        VerificationKey {
+            key_id: Some(key_id.to_string()),
+            family: KeyFamily::Hmac,
+            key: DecodingKey::from_secret(secret),
+        }
+    }
+    
+    fn assert_rejected(result: Result<Identity, Error>, message: &str) {
+        match result {
+            Err(Error::AuthenticationFailed(e)) => assert!(e.contains(message), "unexpected error: {}", e),
+            other => panic!("expected an authentication failure, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn accepts_valid_token() {
+        let authenticator = JwtAuthenticator::new().with_secret("secret");
+        let claims = json!({ "sub": "alice", "exp": now() + 600, "roles": ["admin", "ops"], "level": 3 });
+        let token = sign(Header::default(), claims, b"secret");
+        
+        let identity = authenticator.validate(&token).unwrap();
+        assert_eq!(identity.subject, "alice");
+        assert_eq!(identity.attribute_values("roles"), ["admin", "ops"]);
+        assert_eq!(identity.attribute("level"), Some("3"));
+    }
+    
+    #[test]
+    fn rejects_expired_token() {
+        let authenticator = JwtAuthenticator::new()
+            .with_secret("secret")
+            .with_leeway(Duration::from_secs(30));
+        
+        // Expired within the leeway
+        let token = sign(Header::default(), json!({ "sub": "alice", "exp": now() - 10 }), b"secret");
+        assert!(authenticator.validate(&token).is_ok());
+        
+        // Expired beyond the leeway
+        let token = sign(Header::default(), json!({ "sub": "alice", "exp": now() - 120 }), b"secret");
+        assert_rejected(authenticator.validate(&token), "ExpiredSignature");
+    }
+    
+    #[test]
+    fn rejects_token_without_subject() {
+        let authenticator = JwtAuthenticator::new().with_secret("secret");
+        let token = sign(Header::default(), json!({ "exp": now() + 600 }), b"secret");
+        assert_rejected(authenticator.validate(&token), "no subject");
+    }
+    
+    #[test]
+    fn rejects_wrong_algorithm_family() {
+        // A token claiming RS256 must never be checked against an HMAC secret
+        let authenticator = JwtAuthenticator::new().with_secret("secret");
+        let header = URL_SAFE_NO_PAD.encode(br#"{"alg":"RS256","typ":"JWT"}"#);
+        let claims = URL_SAFE_NO_PAD.encode(json!({ "sub": "alice", "exp": now() + 600 }).to_string());
+        let token = format!("{}.{}.{}", header, claims, URL_SAFE_NO_PAD.encode(b"signature"));
+        assert_rejected(authenticator.validate(&token), "No key to verify RS256 tokens");
+        
+        // No key at all for the token's family
+        let authenticator = JwtAuthenticator::new();
+        let token = sign(Header::default(), json!({ "sub": "alice", "exp": now() + 600 }), b"secret");
+        assert_rejected(authenticator.validate(&token), "No key to verify HS256 tokens");
+    }
+    
+    #[test]
+    fn selects_key_by_kid() {
+        let mut authenticator = JwtAuthenticator::new();
+        authenticator.keys.push(hmac_key("a", b"secret-a"));
+        authenticator.keys.push(hmac_key("b", b"secret-b"));
+        let claims = json!({ "sub": "alice", "exp": now() + 600 });
+        
+        let mut header = Header::default();
+        header.kid = Some("b".to_string());
+        assert!(authenticator.validate(&sign(header.clone(), claims.clone(), b"secret-b")).is_ok());
+        
+        // The key named by the token must be the one that signed it
+        assert_rejected(authenticator.validate(&sign(header, claims.clone(), b"secret-a")), "InvalidSignature");
+        
+        // Unknown key IDs match no key
+        let mut header = Header::default();
+        header.kid = Some("c".to_string());
+        assert_rejected(authenticator.validate(&sign(header, claims.clone(), b"secret-a")), "No key to verify");
+        
+        // Tokens without a key ID try every key of their family
+        assert!(authenticator.validate(&sign(Header::default(), claims, b"secret-a")).is_ok());
+    }
+}
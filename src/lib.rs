@@ -15,7 +15,8 @@ use std::time::Duration;
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use bytes::{Buf, BufMut, Bytes, BytesMut};
+use bytes::{Buf, BufMut, BytesMut};
+use futures_util::Stream;
 use h3::quic::Connection;
 use h3_webtransport::{server, Session};
 use log::{debug, error, info, warn};
@@ -27,18 +28,58 @@ use tokio::sync::{mpsc, oneshot, RwLock};
 use tokio::time;
 
 // Public modules
+pub mod apikey;
+pub mod auth;
+pub mod authz;
 pub mod client;
+#[cfg(feature = "codegen")]
+pub mod codegen;
 pub mod config;
+pub mod context;
+pub mod datagram;
 pub mod error;
+pub mod group;
+pub mod health;
+pub mod interceptor;
+pub mod jwt;
+pub mod metadata;
+pub mod proto;
+pub mod pubsub;
+pub mod push;
+pub mod reflection;
 pub mod server;
+pub mod status;
+pub mod streaming;
+#[cfg(feature = "tower")]
+pub mod tower_compat;
 pub mod transport;
 pub mod utils;
 pub mod bindings;
 
 // Re-exports
-pub use client::Client;
+pub use async_trait::async_trait;
+pub use bytes::Bytes;
+pub use apikey::ApiKeyAuthenticator;
+pub use auth::{Authenticator, ConnectInfo, Identity};
+pub use authz::{Effect, Policy, Rule};
+pub use client::{CallOptions, CallResponse, Client};
+pub use context::CallContext;
+pub use datagram::DatagramHandler;
 pub use error::Error;
-pub use server::Server;
+pub use group::GroupRegistry;
+pub use health::{HealthReporter, HealthService, ServingStatus};
+pub use interceptor::{InterceptedRequest, Interceptor};
+pub use jwt::JwtAuthenticator;
+pub use metadata::Metadata;
+pub use pubsub::{PubSub, PubSubConfig, SlowConsumerPolicy, Subscription, TopicEvent};
+pub use push::SessionHandle;
+pub use quicserve_macros::service;
+pub use reflection::ReflectionService;
+pub use server::{Server, ServerHandle};
+pub use status::{Status, StatusCode};
+pub use streaming::{CallSink, CallStream};
+#[cfg(feature = "tower")]
+pub use tower_compat::{RpcRequest, TowerService};
 pub use transport::Transport;
 
 
@@ -48,14 +89,152 @@ pub const WEBTRANSPORT_PROTOCOL: &[u8] = b"webtransport";
 /// Default timeout for RPC calls
 pub const DEFAULT_TIMEOUT_MS: u64 = 30_000;
 
+/// Stream of response messages produced by a streaming method
+pub type ResponseStream = Pin<Box<dyn Stream<Item = Result<Bytes, Error>> + Send>>;
+
+/// Stream of request messages received by a bidirectional streaming method
+///
+/// The stream ends when the client half-closes its side of the call.
+pub type RequestStream = Pin<Box<dyn Stream<Item = Result<Bytes, Error>> + Send>>;
+
+/// Stream of typed messages, as taken and returned by generated service traits
+pub type TypedStream<T> = Pin<Box<dyn Stream<Item = Result<T, Error>> + Send>>;
+
 /// Service trait that represents a collection of procedures that can be called remotely
 #[async_trait]
 pub trait Service: Send + Sync + 'static {
     /// Executes a method on the service
     async fn call(&self, method: &str, payload: Bytes) -> Result<Bytes, Error>;
     
+    /// Executes a server-streaming method on the service
+    ///
+    /// Each item of the returned stream is sent to the client as a separate message.
+    /// An `Err` item ends the stream and is delivered to the client as an error trailer.
+    async fn call_stream(&self, method: &str, payload: Bytes) -> Result<ResponseStream, Error> {
+        let _ = payload;
+        Err(Error::MethodNotFound(method.to_string()))
+    }
+    
+    /// Executes a client-streaming or bidirectional streaming method on the service
+    ///
+    /// The handler consumes the client's messages from `requests` and may reply with
+    /// any number of messages. A client-streaming method simply replies with one.
+    async fn call_bidi(&self, method: &str, requests: RequestStream) -> Result<ResponseStream, Error> {
+        let _ = requests;
+        Err(Error::MethodNotFound(method.to_string()))
+    }
+    
+    /// Returns a list of available methods
+    fn methods(&self) -> Vec<String>;
+    
+    /// Returns the encoded protobuf `FileDescriptorSet` of the service's request and response types
+    ///
+    /// Served by the reflection service so clients can discover the API at runtime.
+    fn file_descriptor_set(&self) -> Option<Bytes> {
+        None
+    }
+}
+
+/// Service whose handlers receive the [`CallContext`] of each call explicitly
+///
+/// Register implementations with [`Server::register_context_service`], which wraps
+/// them in a [`ContextServiceAdapter`]. Plain [`Service`] implementations keep
+/// working unchanged and can still reach the context through [`CallContext::current`].
+#[async_trait]
+pub trait ContextService: Send + Sync + 'static {
+    /// Executes a method on the service
+    async fn call(&self, context: &CallContext, method: &str, payload: Bytes) -> Result<Bytes, Error>;
+    
+    /// Executes a server-streaming method on the service
+    async fn call_stream(&self, context: &CallContext, method: &str, payload: Bytes) -> Result<ResponseStream, Error> {
+        let _ = (context, payload);
+        Err(Error::MethodNotFound(method.to_string()))
+    }
+    
+    /// Executes a client-streaming or bidirectional streaming method on the service
+    async fn call_bidi(&self, context: &CallContext, method: &str, requests: RequestStream) -> Result<ResponseStream, Error> {
+        let _ = (context, requests);
+        Err(Error::MethodNotFound(method.to_string()))
+    }
+    
     /// Returns a list of available methods
     fn methods(&self) -> Vec<String>;
+    
+    /// Returns the encoded protobuf `FileDescriptorSet` of the service's request and response types
+    fn file_descriptor_set(&self) -> Option<Bytes> {
+        None
+    }
+}
+
+/// Adapter exposing a [`ContextService`] as a [`Service`]
+pub struct ContextServiceAdapter<S> {
+    /// Wrapped service
+    inner: S,
+}
+
+impl<S> ContextServiceAdapter<S> {
+    /// Creates a new ContextServiceAdapter
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+    
+    /// Returns the wrapped service
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+/// Returns the context of the call being handled, failing outside of a call
+fn require_context() -> Result<CallContext, Error> {
+    CallContext::current()
+        .ok_or_else(|| Error::Internal("Context service called outside of a call".to_string()))
+}
+
+#[async_trait]
+impl<S: ContextService> Service for ContextServiceAdapter<S> {
+    async fn call(&self, method: &str, payload: Bytes) -> Result<Bytes, Error> {
+        self.inner.call(&require_context()?, method, payload).await
+    }
+    
+    async fn call_stream(&self, method: &str, payload: Bytes) -> Result<ResponseStream, Error> {
+        self.inner.call_stream(&require_context()?, method, payload).await
+    }
+    
+    async fn call_bidi(&self, method: &str, requests: RequestStream) -> Result<ResponseStream, Error> {
+        self.inner.call_bidi(&require_context()?, method, requests).await
+    }
+    
+    fn methods(&self) -> Vec<String> {
+        self.inner.methods()
+    }
+    
+    fn file_descriptor_set(&self) -> Option<Bytes> {
+        self.inner.file_descriptor_set()
+    }
+}
+
+/// Kind of an RPC request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RequestKind {
+    /// A single request answered by a single response
+    Unary,
+    /// A single request answered by a stream of responses
+    ServerStreaming,
+    /// A stream of requests answered by one or many responses
+    ///
+    /// The opening request carries no message; each message the client sends
+    /// follows as a request with the same ID.
+    BidiStreaming,
+    /// Cancels the in-flight call with the same ID
+    Cancel,
+    /// A single request expecting no response, only sent by servers to their clients
+    Push,
+}
+
+impl Default for RequestKind {
+    fn default() -> Self {
+        RequestKind::Unary
+    }
 }
 
 /// RPC request type
@@ -67,17 +246,46 @@ pub struct Request {
     pub method: String,
     /// Serialized payload
     pub payload: Bytes,
+    /// Kind of call being made
+    #[serde(default)]
+    pub kind: RequestKind,
+    /// Whether the client has finished sending messages for a streaming call
+    #[serde(default)]
+    pub end_of_stream: bool,
+    /// Time in milliseconds the client is willing to wait for the call to complete
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Call metadata such as auth tokens or trace IDs
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
 }
 
 /// RPC response type
+///
+/// Streaming calls produce one response per message followed by a trailer
+/// with `end_of_stream` set, carrying the error if the stream failed.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Response {
     /// Request ID this response corresponds to
     pub id: u64,
     /// Response payload (if successful)
     pub payload: Option<Bytes>,
-    /// Error message (if failed)
-    pub error: Option<String>,
+    /// Status of the call (if failed)
+    pub error: Option<Status>,
+    /// Whether this is the last response for the request
+    #[serde(default = "default_end_of_stream")]
+    pub end_of_stream: bool,
+    /// Response headers, sent with the first response of a call
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub headers: Metadata,
+    /// Response trailers, sent with the last response of a call
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub trailers: Metadata,
+}
+
+/// Responses without an explicit end-of-stream flag are unary and therefore final
+fn default_end_of_stream() -> bool {
+    true
 }
 
 /// Serialization format for RPC messages
@@ -104,6 +312,57 @@ impl fmt::Display for SerializationFormat {
     }
 }
 
+/// How RPC calls are mapped onto WebTransport streams
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamMode {
+    /// Every call is multiplexed over a single shared bidirectional stream
+    Shared,
+    /// Every call opens its own bidirectional stream
+    PerCall,
+}
+
+impl Default for StreamMode {
+    fn default() -> Self {
+        StreamMode::PerCall
+    }
+}
+
+impl fmt::Display for StreamMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamMode::Shared => write!(f, "shared"),
+            StreamMode::PerCall => write!(f, "per-call"),
+        }
+    }
+}
+
+/// Whether a server requests and verifies client certificates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientAuthMode {
+    /// Client certificates are not requested
+    None,
+    /// Client certificates are requested and verified if presented
+    Optional,
+    /// Every client must present a valid certificate
+    Required,
+}
+
+impl Default for ClientAuthMode {
+    fn default() -> Self {
+        ClientAuthMode::None
+    }
+}
+
+impl fmt::Display for ClientAuthMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientAuthMode::None => write!(f, "none"),
+            ClientAuthMode::Optional => write!(f, "optional"),
+            ClientAuthMode::Required => write!(f, "required"),
+        }
+    }
+}
+
 /// Serializes data based on the specified format
 pub fn serialize<T: Serialize + Message>(
     value: &T,
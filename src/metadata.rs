@@ -0,0 +1,255 @@
+use std::collections::hash_map;
+use std::collections::HashMap;
+use std::fmt;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use bytes::Bytes;
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::ser::{self, SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Suffix of metadata keys whose values are binary
+pub const BINARY_KEY_SUFFIX: &str = "-bin";
+
+/// String-to-bytes metadata attached to requests and responses
+///
+/// Keys are case-insensitive and stored in lowercase. Values of keys ending in
+/// `-bin` may hold arbitrary bytes; all other values must be UTF-8 text, which is
+/// checked when they are inserted. In
+/// text formats such as JSON, binary values are base64-encoded while text values
+/// are written as plain strings. Binary formats carry the raw bytes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// Metadata entries
+    entries: HashMap<String, Bytes>,
+}
+
+impl Metadata {
+    /// Creates empty metadata
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Inserts a value, returning the previous value of the key
+    ///
+    /// Fails with [`Error::InvalidArgument`] if the value isn't valid UTF-8 and the key
+    /// doesn't end in `-bin`.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<Bytes>) -> Result<Option<Bytes>, Error> {
+        let key = key.into().to_ascii_lowercase();
+        let value = value.into();
+        if !is_binary_key(&key) && std::str::from_utf8(&value).is_err() {
+            return Err(Error::InvalidArgument(format!(
+                "Metadata value for '{}' is not valid UTF-8, binary values need a key ending in '{}'",
+                key, BINARY_KEY_SUFFIX
+            )));
+        }
+        Ok(self.entries.insert(key, value))
+    }
+    
+    /// Inserts a text value, returning the previous value of the key
+    pub fn insert_str(&mut self, key: impl Into<String>, value: &str) -> Option<Bytes> {
+        self.entries.insert(key.into().to_ascii_lowercase(), Bytes::copy_from_slice(value.as_bytes()))
+    }
+    
+    /// Returns the value of a key
+    pub fn get(&self, key: &str) -> Option<&Bytes> {
+        self.entries.get(&key.to_ascii_lowercase())
+    }
+    
+    /// Returns the value of a key as text, if it is valid UTF-8
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(|value| std::str::from_utf8(value).ok())
+    }
+    
+    /// Removes a key, returning its value
+    pub fn remove(&mut self, key: &str) -> Option<Bytes> {
+        self.entries.remove(&key.to_ascii_lowercase())
+    }
+    
+    /// Returns whether the key is present
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.entries.contains_key(&key.to_ascii_lowercase())
+    }
+    
+    /// Returns an iterator over the entries
+    pub fn iter(&self) -> hash_map::Iter<'_, String, Bytes> {
+        self.entries.iter()
+    }
+    
+    /// Returns the number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    
+    /// Returns whether there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    
+    /// Moves every entry of `other` into this metadata, replacing existing keys
+    pub fn merge(&mut self, other: Metadata) {
+        self.entries.extend(other.entries);
+    }
+}
+
+/// Returns whether values of the key are binary
+fn is_binary_key(key: &str) -> bool {
+    key.ends_with(BINARY_KEY_SUFFIX)
+}
+
+impl Serialize for Metadata {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let human_readable = serializer.is_human_readable();
+        let mut map = serializer.serialize_map(Some(self.entries.len()))?;
+        
+        for (key, value) in &self.entries {
+            if !human_readable {
+                map.serialize_entry(key, value)?;
+            } else if is_binary_key(key) {
+                map.serialize_entry(key, &BASE64.encode(value))?;
+            } else {
+                let text = std::str::from_utf8(value).map_err(|_| {
+                    ser::Error::custom(format!("Metadata value for '{}' is not valid UTF-8", key))
+                })?;
+                map.serialize_entry(key, text)?;
+            }
+        }
+        
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Metadata {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let human_readable = deserializer.is_human_readable();
+        deserializer.deserialize_map(MetadataVisitor { human_readable })
+    }
+}
+
+/// Visitor decoding [`Metadata`] maps
+struct MetadataVisitor {
+    /// Whether values are text-encoded
+    human_readable: bool,
+}
+
+impl<'de> Visitor<'de> for MetadataVisitor {
+    type Value = Metadata;
+    
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a map of metadata keys to values")
+    }
+    
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut metadata = Metadata::new();
+        
+        while let Some(key) = access.next_key::<String>()? {
+            let value = if !self.human_readable {
+                access.next_value::<Bytes>()?
+            } else if is_binary_key(&key) {
+                let encoded = access.next_value::<String>()?;
+                let decoded = BASE64.decode(encoded.as_bytes()).map_err(|e| {
+                    de::Error::custom(format!("Invalid base64 metadata value for '{}': {}", key, e))
+                })?;
+                Bytes::from(decoded)
+            } else {
+                Bytes::from(access.next_value::<String>()?)
+            };
+            metadata.insert(key, value).map_err(de::Error::custom)?;
+        }
+        
+        Ok(metadata)
+    }
+}
+
+impl<'a> IntoIterator for &'a Metadata {
+    type Item = (&'a String, &'a Bytes);
+    type IntoIter = hash_map::Iter<'a, String, Bytes>;
+    
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+impl FromIterator<(String, String)> for Metadata {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        let mut metadata = Metadata::new();
+        for (key, value) in iter {
+            metadata.insert_str(key, &value);
+        }
+        metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_test::{assert_tokens, Configure, Token};
+    
+    use super::*;
+    
+    #[test]
+    fn keys_are_case_insensitive() {
+        let mut metadata = Metadata::new();
+        metadata.insert_str("X-Trace-Id", "abc");
+        
+        assert_eq!(metadata.get_str("x-trace-id"), Some("abc"));
+        assert_eq!(metadata.iter().next().map(|(key, _)| key.as_str()), Some("x-trace-id"));
+    }
+    
+    #[test]
+    fn insert_rejects_binary_values_of_text_keys() {
+        let mut metadata = Metadata::new();
+        
+        assert!(matches!(metadata.insert("x-raw", vec![0xff, 0x00]), Err(Error::InvalidArgument(_))));
+        assert!(metadata.is_empty());
+        assert!(metadata.insert("x-raw-bin", vec![0xff, 0x00]).is_ok());
+        assert!(metadata.insert("x-text", "plain").is_ok());
+    }
+    
+    #[test]
+    fn json_round_trip() {
+        let mut metadata = Metadata::new();
+        metadata.insert_str("x-trace-id", "abc");
+        metadata.insert("x-key-bin", vec![0xff, 0x00]).unwrap();
+        
+        let json = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(json, serde_json::json!({ "x-trace-id": "abc", "x-key-bin": "/wA=" }));
+        
+        let decoded: Metadata = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, metadata);
+    }
+    
+    #[test]
+    fn json_rejects_invalid_base64() {
+        let result = serde_json::from_str::<Metadata>(r#"{ "x-key-bin": "not base64!" }"#);
+        assert!(result.is_err());
+    }
+    
+    #[test]
+    fn compact_round_trip() {
+        let mut metadata = Metadata::new();
+        metadata.insert("x-key-bin", vec![0xff, 0x00]).unwrap();
+        
+        assert_tokens(&metadata.compact(), &[
+            Token::Map { len: Some(1) },
+            Token::Str("x-key-bin"),
+            Token::Bytes(&[0xff, 0x00]),
+            Token::MapEnd,
+        ]);
+    }
+    
+    #[test]
+    fn readable_round_trip() {
+        let mut metadata = Metadata::new();
+        metadata.insert_str("x-trace-id", "abc");
+        
+        assert_tokens(&metadata.readable(), &[
+            Token::Map { len: Some(1) },
+            Token::Str("x-trace-id"),
+            Token::Str("abc"),
+            Token::MapEnd,
+        ]);
+    }
+}
@@ -1,4 +1,19 @@
 // The module generated by prost
 pub mod quicserve {
     include!(concat!(env!("OUT_DIR"), "/quicserve.rs"));
+    
+    /// Messages of the built-in reflection service
+    pub mod reflection {
+        include!(concat!(env!("OUT_DIR"), "/quicserve.reflection.rs"));
+    }
+    
+    /// Messages of the built-in health service
+    pub mod health {
+        include!(concat!(env!("OUT_DIR"), "/quicserve.health.rs"));
+    }
+    
+    /// Messages of the built-in pub/sub service
+    pub mod pubsub {
+        include!(concat!(env!("OUT_DIR"), "/quicserve.pubsub.rs"));
+    }
 }
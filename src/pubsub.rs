@@ -0,0 +1,486 @@
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use crate::context::CallContext;
+use crate::error::Error;
+use crate::streaming::CallStream;
+use crate::utils::glob_match;
+use crate::{deserialize, serialize, ContextService, ResponseStream, SerializationFormat};
+
+/// Name the pub/sub service is registered under
+pub const PUBSUB_SERVICE_NAME: &str = "pubsub";
+
+/// What happens when a subscriber's buffer is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowConsumerPolicy {
+    /// The oldest buffered event is dropped to make room
+    DropOldest,
+    /// The subscription ends with [`Error::ResourceExhausted`]
+    Disconnect,
+}
+
+impl Default for SlowConsumerPolicy {
+    fn default() -> Self {
+        SlowConsumerPolicy::DropOldest
+    }
+}
+
+/// Configuration of the pub/sub subsystem
+#[derive(Debug, Clone)]
+pub struct PubSubConfig {
+    /// Number of events buffered for each subscriber
+    pub buffer_size: usize,
+    /// What happens when a subscriber's buffer is full
+    pub slow_consumer: SlowConsumerPolicy,
+    /// Number of recent events kept per topic for replay on subscribe
+    pub replay_depth: usize,
+    /// Number of topics recent events are kept for
+    ///
+    /// Once reached, publishing to a new topic drops the history of the topic that was
+    /// published to least recently.
+    pub replay_topics: usize,
+}
+
+impl Default for PubSubConfig {
+    fn default() -> Self {
+        Self {
+            buffer_size: 64,
+            slow_consumer: SlowConsumerPolicy::DropOldest,
+            replay_depth: 0,
+            replay_topics: 1024,
+        }
+    }
+}
+
+// Messages of the service, generated from `pubsub.proto`
+pub use crate::proto::quicserve::pubsub::{Event, SubscribeRequest};
+
+/// Stream of events received by a subscriber
+pub type EventStream = Pin<Box<dyn Stream<Item = Result<Event, Error>> + Send>>;
+
+/// A subscriber and its buffered events
+struct Subscriber {
+    /// Glob pattern over topic names
+    pattern: String,
+    /// Events waiting to be delivered
+    queue: Mutex<VecDeque<Event>>,
+    /// Notified when an event is buffered or the subscriber is disconnected
+    notify: Notify,
+    /// Whether the subscriber was disconnected for falling behind
+    disconnected: AtomicBool,
+}
+
+impl Subscriber {
+    /// Buffers an event, returning false if the subscriber was disconnected
+    fn push(&self, event: Event, config: &PubSubConfig) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.len() >= config.buffer_size {
+            match config.slow_consumer {
+                SlowConsumerPolicy::DropOldest => {
+                    queue.pop_front();
+                }
+                SlowConsumerPolicy::Disconnect => {
+                    drop(queue);
+                    self.disconnected.store(true, Ordering::Release);
+                    self.notify.notify_one();
+                    return false;
+                }
+            }
+        }
+        queue.push_back(event);
+        drop(queue);
+        self.notify.notify_one();
+        true
+    }
+}
+
+/// Subscribers and recent events, updated together so replays neither miss nor repeat events
+#[derive(Default)]
+struct PubSubState {
+    /// Sequence number of the next event
+    next_sequence: u64,
+    /// Live subscribers; dropped subscriptions are pruned on publish and subscribe
+    subscribers: Vec<Weak<Subscriber>>,
+    /// Recent events by topic, oldest first
+    history: HashMap<String, VecDeque<Event>>,
+}
+
+/// Topic-based publish/subscribe hub of a server
+///
+/// Obtained from [`Server::enable_pubsub`](crate::Server::enable_pubsub), which also
+/// registers the `pubsub` service clients subscribe through. Clones publish to the same
+/// subscribers, so a clone can be handed to any service that needs to publish. Every
+/// subscription ends when the server shuts down.
+#[derive(Clone)]
+pub struct PubSub {
+    /// Shared hub state
+    inner: Arc<PubSubInner>,
+}
+
+/// Shared state of a [`PubSub`]
+struct PubSubInner {
+    /// Configuration
+    config: PubSubConfig,
+    /// Serialization format of published messages
+    format: SerializationFormat,
+    /// Subscribers and recent events
+    state: Mutex<PubSubState>,
+    /// Token cancelled when the server shuts down
+    shutdown: CancellationToken,
+}
+
+impl PubSub {
+    /// Creates a new PubSub
+    pub(crate) fn new(config: PubSubConfig, format: SerializationFormat, shutdown: CancellationToken) -> Self {
+        Self {
+            inner: Arc::new(PubSubInner {
+                config,
+                format,
+                state: Mutex::new(PubSubState::default()),
+                shutdown,
+            }),
+        }
+    }
+    
+    /// Publishes a message to a topic, returning the number of subscribers it was delivered to
+    pub fn publish<T>(&self, topic: &str, message: &T) -> Result<usize, Error>
+    where
+        T: Serialize + prost::Message,
+    {
+        let payload = serialize(message, self.inner.format)?;
+        Ok(self.publish_raw(topic, payload))
+    }
+    
+    /// Publishes an already serialized message to a topic
+    pub fn publish_raw(&self, topic: &str, payload: Bytes) -> usize {
+        let config = &self.inner.config;
+        let mut state = self.inner.state.lock().unwrap();
+        
+        let event = Event {
+            topic: topic.to_string(),
+            sequence: state.next_sequence,
+            payload,
+        };
+        state.next_sequence += 1;
+        
+        // Keep the event for replay
+        if config.replay_depth > 0 && config.replay_topics > 0 {
+            if !state.history.contains_key(&event.topic) && state.history.len() >= config.replay_topics {
+                evict_stalest_topic(&mut state.history);
+            }
+            let history = state.history.entry(event.topic.clone()).or_default();
+            if history.len() >= config.replay_depth {
+                history.pop_front();
+            }
+            history.push_back(event.clone());
+        }
+        
+        // Deliver to matching subscribers, forgetting those that are gone or disconnected
+        let mut delivered = 0;
+        state.subscribers.retain(|subscriber| {
+            let subscriber = match subscriber.upgrade() {
+                Some(subscriber) => subscriber,
+                None => return false,
+            };
+            if !glob_match(&subscriber.pattern, &event.topic) {
+                return true;
+            }
+            let accepted = subscriber.push(event.clone(), config);
+            if accepted {
+                delivered += 1;
+            }
+            accepted
+        });
+        delivered
+    }
+    
+    /// Subscribes to the topics matching a glob pattern
+    ///
+    /// Up to `replay` recent events of matching topics are delivered first, limited by
+    /// the configured replay depth.
+    pub fn subscribe(&self, pattern: &str, replay: usize) -> EventStream {
+        let subscriber = Arc::new(Subscriber {
+            pattern: pattern.to_string(),
+            queue: Mutex::new(VecDeque::new()),
+            notify: Notify::new(),
+            disconnected: AtomicBool::new(false),
+        });
+        
+        {
+            let mut state = self.inner.state.lock().unwrap();
+            
+            // Replay the most recent matching events, oldest first
+            let mut replayed: Vec<Event> = state.history.iter()
+                .filter(|(topic, _)| glob_match(pattern, topic))
+                .flat_map(|(_, events)| events.iter().cloned())
+                .collect();
+            replayed.sort_by_key(|event| event.sequence);
+            let skip = replayed.len().saturating_sub(replay);
+            subscriber.queue.lock().unwrap().extend(replayed.into_iter().skip(skip));
+            
+            // Forget dropped subscriptions, topics nobody publishes to would keep them forever
+            state.subscribers.retain(|subscriber| subscriber.strong_count() > 0);
+            state.subscribers.push(Arc::downgrade(&subscriber));
+        }
+        
+        let shutdown = self.inner.shutdown.clone();
+        Box::pin(futures_util::stream::unfold(Some(subscriber), move |subscriber| {
+            let shutdown = shutdown.clone();
+            async move {
+                let subscriber = subscriber?;
+                loop {
+                    if let Some(event) = subscriber.queue.lock().unwrap().pop_front() {
+                        return Some((Ok(event), Some(subscriber)));
+                    }
+                    
+                    // Buffered events are delivered before the subscriber is cut off
+                    if subscriber.disconnected.load(Ordering::Acquire) {
+                        let err = Error::ResourceExhausted("Subscriber fell too far behind".to_string());
+                        return Some((Err(err), None));
+                    }
+                    
+                    tokio::select! {
+                        _ = subscriber.notify.notified() => {}
+                        _ = shutdown.cancelled() => return None,
+                    }
+                }
+            }
+        }))
+    }
+}
+
+/// Drops the history of the topic that was published to least recently
+fn evict_stalest_topic(history: &mut HashMap<String, VecDeque<Event>>) {
+    let stalest = history.iter()
+        .min_by_key(|(_, events)| events.back().map_or(0, |event| event.sequence))
+        .map(|(topic, _)| topic.clone());
+    if let Some(topic) = stalest {
+        history.remove(&topic);
+    }
+}
+
+/// Built-in service letting clients subscribe to topics
+///
+/// Registered as `pubsub` by [`Server::enable_pubsub`](crate::Server::enable_pubsub).
+/// Its server-streaming `subscribe` method takes a [`SubscribeRequest`] and streams
+/// every matching [`Event`].
+pub struct PubSubService {
+    /// Hub the subscriptions are made on
+    pubsub: PubSub,
+}
+
+impl PubSubService {
+    /// Creates a new PubSubService
+    pub(crate) fn new(pubsub: PubSub) -> Self {
+        Self { pubsub }
+    }
+}
+
+#[async_trait]
+impl ContextService for PubSubService {
+    async fn call(&self, _context: &CallContext, method: &str, _payload: Bytes) -> Result<Bytes, Error> {
+        Err(Error::MethodNotFound(method.to_string()))
+    }
+    
+    async fn call_stream(&self, context: &CallContext, method: &str, payload: Bytes) -> Result<ResponseStream, Error> {
+        let format = context.format();
+        match method {
+            "subscribe" => {
+                let request: SubscribeRequest = deserialize(&payload, format)?;
+                let events = self.pubsub.subscribe(&request.pattern, request.replay as usize);
+                Ok(Box::pin(events.map(move |event| serialize(&event?, format))))
+            }
+            _ => Err(Error::MethodNotFound(method.to_string())),
+        }
+    }
+    
+    fn methods(&self) -> Vec<String> {
+        vec!["subscribe".into()]
+    }
+}
+
+/// Event received by a [`Subscription`], with its message deserialized
+#[derive(Debug, Clone)]
+pub struct TopicEvent<T> {
+    /// Topic the event was published to
+    pub topic: String,
+    /// Sequence number of the event
+    pub sequence: u64,
+    /// Published message
+    pub message: T,
+}
+
+/// Typed stream of events received from a server's `pubsub` service
+///
+/// Created with [`Client::subscribe`](crate::Client::subscribe). Dropping the
+/// subscription cancels it.
+pub struct Subscription<T> {
+    /// Underlying server-streaming call
+    events: CallStream<Event>,
+    /// Serialization format of the messages
+    format: SerializationFormat,
+    /// Type of the messages
+    _message: PhantomData<fn() -> T>,
+}
+
+impl<T> Subscription<T> {
+    /// Creates a new Subscription
+    pub(crate) fn new(events: CallStream<Event>, format: SerializationFormat) -> Self {
+        Self {
+            events,
+            format,
+            _message: PhantomData,
+        }
+    }
+}
+
+impl<T> Stream for Subscription<T>
+where
+    T: DeserializeOwned + prost::Message + Default,
+{
+    type Item = Result<TopicEvent<T>, Error>;
+    
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let format = self.format;
+        self.events.poll_next_unpin(cx).map(|event| {
+            event.map(|event| {
+                let event = event?;
+                Ok(TopicEvent {
+                    message: deserialize(&event.payload, format)?,
+                    topic: event.topic,
+                    sequence: event.sequence,
+                })
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+    
+    fn pubsub(config: PubSubConfig) -> PubSub {
+        PubSub::new(config, SerializationFormat::Json, CancellationToken::new())
+    }
+    
+    /// Returns the topic and sequence of every event buffered for a subscriber
+    fn buffered(events: &mut EventStream) -> Vec<(String, u64)> {
+        let mut buffered = Vec::new();
+        while let Some(Some(event)) = events.next().now_or_never() {
+            let event = event.unwrap();
+            buffered.push((event.topic, event.sequence));
+        }
+        buffered
+    }
+    
+    fn event(topic: &str, sequence: u64) -> (String, u64) {
+        (topic.to_string(), sequence)
+    }
+    
+    #[tokio::test]
+    async fn delivers_to_matching_subscribers_only() {
+        let pubsub = pubsub(PubSubConfig::default());
+        let mut orders = pubsub.subscribe("orders.*", 0);
+        let mut everything = pubsub.subscribe("*", 0);
+        
+        assert_eq!(pubsub.publish_raw("orders.created", Bytes::new()), 2);
+        assert_eq!(pubsub.publish_raw("users.created", Bytes::new()), 1);
+        
+        assert_eq!(buffered(&mut orders), vec![event("orders.created", 0)]);
+        assert_eq!(buffered(&mut everything), vec![event("orders.created", 0), event("users.created", 1)]);
+    }
+    
+    #[tokio::test]
+    async fn replays_the_most_recent_events_oldest_first() {
+        let pubsub = pubsub(PubSubConfig { replay_depth: 2, ..Default::default() });
+        pubsub.publish_raw("a", Bytes::new());
+        pubsub.publish_raw("b", Bytes::new());
+        pubsub.publish_raw("a", Bytes::new());
+        pubsub.publish_raw("a", Bytes::new());
+        
+        // The first event of `a` is beyond the replay depth
+        let mut all = pubsub.subscribe("*", 10);
+        assert_eq!(buffered(&mut all), vec![event("b", 1), event("a", 2), event("a", 3)]);
+        
+        let mut recent = pubsub.subscribe("*", 2);
+        assert_eq!(buffered(&mut recent), vec![event("a", 2), event("a", 3)]);
+        
+        // Live events follow the replayed ones
+        pubsub.publish_raw("b", Bytes::new());
+        assert_eq!(buffered(&mut recent), vec![event("b", 4)]);
+    }
+    
+    #[tokio::test]
+    async fn drop_oldest_keeps_the_newest_events() {
+        let pubsub = pubsub(PubSubConfig { buffer_size: 2, ..Default::default() });
+        let mut events = pubsub.subscribe("*", 0);
+        for _ in 0..3 {
+            assert_eq!(pubsub.publish_raw("a", Bytes::new()), 1);
+        }
+        
+        assert_eq!(buffered(&mut events), vec![event("a", 1), event("a", 2)]);
+    }
+    
+    #[tokio::test]
+    async fn disconnect_ends_slow_subscribers_after_their_buffered_events() {
+        let config = PubSubConfig { buffer_size: 1, slow_consumer: SlowConsumerPolicy::Disconnect, ..Default::default() };
+        let pubsub = pubsub(config);
+        let mut events = pubsub.subscribe("*", 0);
+        assert_eq!(pubsub.publish_raw("a", Bytes::new()), 1);
+        assert_eq!(pubsub.publish_raw("a", Bytes::new()), 0);
+        
+        assert_eq!(events.next().await.unwrap().unwrap().sequence, 0);
+        assert!(matches!(events.next().await, Some(Err(Error::ResourceExhausted(_)))));
+        assert!(events.next().await.is_none());
+        
+        // The subscriber is forgotten once disconnected
+        assert_eq!(pubsub.publish_raw("a", Bytes::new()), 0);
+        assert!(pubsub.inner.state.lock().unwrap().subscribers.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn evicts_the_history_of_the_stalest_topic() {
+        let pubsub = pubsub(PubSubConfig { replay_depth: 1, replay_topics: 2, ..Default::default() });
+        pubsub.publish_raw("a", Bytes::new());
+        pubsub.publish_raw("b", Bytes::new());
+        pubsub.publish_raw("a", Bytes::new());
+        pubsub.publish_raw("c", Bytes::new());
+        
+        let mut events = pubsub.subscribe("*", 10);
+        assert_eq!(buffered(&mut events), vec![event("a", 2), event("c", 3)]);
+    }
+    
+    #[tokio::test]
+    async fn subscribing_prunes_dropped_subscriptions() {
+        let pubsub = pubsub(PubSubConfig::default());
+        for _ in 0..100 {
+            drop(pubsub.subscribe("quiet", 0));
+        }
+        let _events = pubsub.subscribe("quiet", 0);
+        
+        assert_eq!(pubsub.inner.state.lock().unwrap().subscribers.len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn subscriptions_end_at_shutdown() {
+        let shutdown = CancellationToken::new();
+        let pubsub = PubSub::new(PubSubConfig::default(), SerializationFormat::Json, shutdown.clone());
+        let mut events = pubsub.subscribe("*", 0);
+        
+        shutdown.cancel();
+        assert!(events.next().await.is_none());
+    }
+}
@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use h3_webtransport::client;
+use log::{debug, warn};
+use tokio::sync::{RwLock, Semaphore};
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use crate::auth::Identity;
+use crate::client::{check_response, CallOptions, CallResponse, StreamGuard};
+use crate::context::{CallContext, SessionInfo};
+use crate::datagram::{check_datagram_size, max_datagram_size, DatagramSequencer};
+use crate::error::Error;
+use crate::metadata::Metadata;
+use crate::proto::quicserve::DatagramProto;
+use crate::status::Status;
+use crate::transport::MessageStream;
+use crate::{Request, RequestKind, Response, SerializationFormat, Service};
+
+/// Handle to an established session, used to push messages and make calls to its client
+///
+/// Each push or call opens a new bidirectional stream from the server to the client,
+/// where it is dispatched to a service registered with
+/// [`Client::register_service`](crate::Client::register_service). Handles are obtained
+/// from [`CallContext::session`](crate::CallContext::session) or from the
+/// [`ServerHandle`](crate::ServerHandle), and stop working once the session closes.
+#[derive(Clone)]
+pub struct SessionHandle {
+    /// Session being addressed
+    info: Arc<SessionInfo>,
+}
+
+impl SessionHandle {
+    /// Creates a new SessionHandle
+    pub(crate) fn new(info: Arc<SessionInfo>) -> Self {
+        Self { info }
+    }
+    
+    /// Returns the unique ID of the session
+    pub fn id(&self) -> Uuid {
+        self.info.session_id
+    }
+    
+    /// Returns the address of the client
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.info.peer_addr
+    }
+    
+    /// Returns the identity established for the session, if it was authenticated
+    pub fn identity(&self) -> Option<&Identity> {
+        self.info.identity.as_deref()
+    }
+    
+    /// Returns whether the session has ended
+    pub fn is_closed(&self) -> bool {
+        self.info.closed.load(Ordering::Acquire)
+    }
+    
+    /// Pushes a one-way message to a method of the client
+    pub async fn push<T>(&self, method: &str, message: &T) -> Result<(), Error>
+    where
+        T: serde::Serialize + prost::Message,
+    {
+        let payload = crate::serialize(message, self.info.format)?;
+        self.push_raw(method, payload, Metadata::new()).await
+    }
+    
+    /// Pushes a one-way message carrying an already serialized payload
+    ///
+    /// Returns once the message is sent; whether the client handled it is not reported.
+    pub async fn push_raw(&self, method: &str, payload: Bytes, metadata: Metadata) -> Result<(), Error> {
+        let request = Request {
+            id: self.next_request_id(),
+            method: method.to_string(),
+            payload,
+            kind: RequestKind::Push,
+            end_of_stream: true,
+            timeout_ms: None,
+            metadata,
+        };
+        
+        let mut stream = self.open_stream().await?;
+        stream.send(crate::serialize(&request, self.info.format)?).await?;
+        stream.finish().await
+    }
+    
+    /// Sends a fire-and-forget datagram to a method of the client
+    ///
+    /// Datagrams may be lost, duplicated or reordered, and the client drops those older
+    /// than the last one it accepted for the same method. The encoded datagram must fit
+    /// in a single packet, see [`SessionHandle::max_datagram_size`].
+    pub fn send_datagram<T>(&self, method: &str, message: &T) -> Result<(), Error>
+    where
+        T: serde::Serialize + prost::Message,
+    {
+        let payload = crate::serialize(message, self.info.format)?;
+        self.send_datagram_raw(method, payload)
+    }
+    
+    /// Sends a fire-and-forget datagram carrying an already serialized payload
+    pub fn send_datagram_raw(&self, method: &str, payload: Bytes) -> Result<(), Error> {
+        let data = self.encode_datagram(method, payload)?;
+        self.send_encoded_datagram(data)
+    }
+    
+    /// Returns the largest encoded datagram that currently fits in a packet, if datagrams are supported
+    ///
+    /// The limit follows the path MTU discovered by the connection and covers the method
+    /// name and sequence number as well as the payload.
+    pub fn max_datagram_size(&self) -> Option<usize> {
+        max_datagram_size(&self.info.connection)
+    }
+    
+    /// Encodes a datagram with the next sequence number, failing if it doesn't fit in a packet
+    pub(crate) fn encode_datagram(&self, method: &str, payload: Bytes) -> Result<Bytes, Error> {
+        let frame = DatagramProto {
+            method: method.to_string(),
+            sequence: self.info.next_datagram_sequence.fetch_add(1, Ordering::Relaxed),
+            payload,
+        };
+        let data = crate::serialize(&frame, self.info.format)?;
+        check_datagram_size(&data, self.max_datagram_size())?;
+        Ok(data)
+    }
+    
+    /// Sends a datagram encoded by [`SessionHandle::encode_datagram`]
+    pub(crate) fn send_encoded_datagram(&self, data: Bytes) -> Result<(), Error> {
+        self.info.session.send_datagram(data)
+            .map_err(|e| Error::WebTransport(format!("Failed to send datagram: {}", e)))
+    }
+    
+    /// Calls a method of the client and returns the result
+    pub async fn call<T, R>(&self, method: &str, request: &T) -> Result<R, Error>
+    where
+        T: serde::Serialize + prost::Message,
+        R: serde::de::DeserializeOwned + prost::Message + Default,
+    {
+        let payload = crate::serialize(request, self.info.format)?;
+        let response = self.call_raw(method, payload, CallOptions::default()).await?;
+        crate::deserialize(&response.message, self.info.format)
+    }
+    
+    /// Calls a method of the client with an already serialized payload
+    ///
+    /// Calls without a timeout of their own use the server's `timeout_ms`. If the call
+    /// times out or is dropped, its stream is reset and the client's handler cancelled.
+    pub async fn call_raw(&self, method: &str, payload: Bytes, options: CallOptions) -> Result<CallResponse<Bytes>, Error> {
+        // The client is told how long we are going to wait
+        let timeout = options.timeout.unwrap_or(Duration::from_millis(self.info.timeout_ms));
+        let request = Request {
+            id: self.next_request_id(),
+            method: method.to_string(),
+            payload,
+            kind: RequestKind::Unary,
+            end_of_stream: false,
+            timeout_ms: Some(timeout.as_millis() as u64),
+            metadata: options.metadata,
+        };
+        
+        // Each call has its own stream, reset if the call is abandoned before the response arrives
+        let mut guard = StreamGuard {
+            stream: self.open_stream().await?,
+            completed: false,
+        };
+        guard.stream.send(crate::serialize(&request, self.info.format)?).await?;
+        let response_bytes = tokio::time::timeout(timeout, guard.stream.receive())
+            .await
+            .map_err(|_| Error::Timeout)??
+            .ok_or(Error::ConnectionClosed)?;
+        
+        guard.completed = true;
+        if let Err(e) = guard.stream.finish().await {
+            debug!("Failed to finish call stream: {}", e);
+        }
+        
+        let response: Response = crate::deserialize(&response_bytes, self.info.format)?;
+        let response = check_response(response)?;
+        Ok(CallResponse {
+            message: response.payload.unwrap_or_default(),
+            headers: response.headers,
+            trailers: response.trailers,
+        })
+    }
+    
+    /// Returns the ID of the next push or call made to the client
+    fn next_request_id(&self) -> u64 {
+        self.info.next_request_id.fetch_add(1, Ordering::Relaxed)
+    }
+    
+    /// Opens a new bidirectional stream to the client
+    async fn open_stream(&self) -> Result<MessageStream, Error> {
+        let stream = self.info.session.open_bi()
+            .await
+            .map_err(|e| Error::WebTransport(format!("Failed to open bidirectional stream to client: {}", e)))?;
+        Ok(MessageStream::new(stream))
+    }
+}
+
+/// Serves pushes, calls and datagrams sent by the server on a client's session, until the session closes
+///
+/// At most `max_datagrams` datagrams are handled at once; further datagrams are dropped
+/// until a handler finishes.
+pub(crate) async fn serve_reverse_calls(
+    session: Arc<client::Session>,
+    services: Arc<RwLock<HashMap<String, Arc<dyn Service>>>>,
+    peer_addr: SocketAddr,
+    format: SerializationFormat,
+    max_datagrams: usize,
+) {
+    let datagram_slots = Arc::new(Semaphore::new(max_datagrams));
+    let mut sequencer = DatagramSequencer::default();
+    let mut datagrams_open = true;
+    
+    loop {
+        let stream = tokio::select! {
+            accepted = session.accept_bi() => match accepted {
+                Ok(Some(stream)) => stream,
+                Ok(None) => break,
+                Err(e) => {
+                    debug!("Stopped accepting streams from server: {}", e);
+                    break;
+                }
+            },
+            datagram = session.accept_datagram(), if datagrams_open => {
+                match datagram {
+                    Ok(Some((_, data))) => dispatch_datagram(data, &services, peer_addr, format, &mut sequencer, &datagram_slots).await,
+                    Ok(None) => datagrams_open = false,
+                    Err(e) => {
+                        debug!("Stopped reading datagrams from server: {}", e);
+                        datagrams_open = false;
+                    }
+                }
+                continue;
+            }
+        };
+        
+        let services = services.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_reverse_call(MessageStream::new(stream), &services, peer_addr, format).await {
+                debug!("Stream from server closed with error: {}", e);
+            }
+        });
+    }
+}
+
+/// Decodes a datagram sent by the server and hands it to the registered service
+///
+/// Datagrams are handled like pushes: the service's result is discarded. Datagrams for
+/// unregistered services, stale datagrams and datagrams arriving while every slot is
+/// taken are dropped.
+async fn dispatch_datagram(
+    data: Bytes,
+    services: &Arc<RwLock<HashMap<String, Arc<dyn Service>>>>,
+    peer_addr: SocketAddr,
+    format: SerializationFormat,
+    sequencer: &mut DatagramSequencer,
+    slots: &Arc<Semaphore>,
+) {
+    let frame: DatagramProto = match crate::deserialize(&data, format) {
+        Ok(frame) => frame,
+        Err(e) => {
+            debug!("Dropping malformed datagram from server: {}", e);
+            return;
+        }
+    };
+    
+    // Check the service before sequencing the method, so only registered services are ever tracked
+    let registered = match frame.method.split_once('.') {
+        Some((service_name, _)) => services.read().await.contains_key(service_name),
+        None => false,
+    };
+    if !registered {
+        debug!("Dropping datagram for {}: no service registered", frame.method);
+        return;
+    }
+    
+    let permit = match slots.clone().try_acquire_owned() {
+        Ok(permit) => permit,
+        Err(_) => {
+            debug!("Dropping datagram {} for {}: too many datagrams in flight", frame.sequence, frame.method);
+            return;
+        }
+    };
+    if !sequencer.accept(&frame) {
+        debug!("Dropping stale datagram {} for {}", frame.sequence, frame.method);
+        return;
+    }
+    
+    let request = Request {
+        id: frame.sequence,
+        method: frame.method,
+        payload: frame.payload,
+        kind: RequestKind::Push,
+        end_of_stream: true,
+        timeout_ms: None,
+        metadata: Metadata::new(),
+    };
+    let services = services.clone();
+    tokio::spawn(async move {
+        let _permit = permit;
+        if let Err(e) = call_service(&request, &services, peer_addr, format).await {
+            debug!("Handling datagram for {} failed: {}", request.method, e);
+        }
+    });
+}
+
+/// Handles a single push or call made by the server
+async fn handle_reverse_call(
+    mut stream: MessageStream,
+    services: &RwLock<HashMap<String, Arc<dyn Service>>>,
+    peer_addr: SocketAddr,
+    format: SerializationFormat,
+) -> Result<(), Error> {
+    let request_bytes = match stream.receive().await? {
+        Some(bytes) => bytes,
+        None => return Ok(()),
+    };
+    let request: Request = crate::deserialize(&request_bytes, format)?;
+    debug!("Received {:?} request from server - method: {}", request.kind, request.method);
+    
+    // The server resets the stream if it abandons the call, which cancels the handler
+    let call = async {
+        match request.kind {
+            RequestKind::Unary | RequestKind::Push => call_service(&request, services, peer_addr, format).await,
+            // Servers only make unary calls
+            _ => Err(Error::MethodNotFound(request.method.clone())),
+        }
+    };
+    let abandoned = async {
+        match stream.receive().await {
+            Err(e) => e,
+            // Finishing the request side is not abandoning the call
+            Ok(_) => std::future::pending().await,
+        }
+    };
+    let result = tokio::select! {
+        result = call => result,
+        e = abandoned => {
+            debug!("Server abandoned call to {}: {}", request.method, e);
+            return Ok(());
+        }
+    };
+    
+    // Pushes get no response
+    if request.kind == RequestKind::Push {
+        if let Err(e) = result {
+            warn!("Handling push to {} failed: {}", request.method, e);
+        }
+        return Ok(());
+    }
+    
+    let response = match result {
+        Ok(payload) => Response {
+            id: request.id,
+            payload: Some(payload),
+            error: None,
+            end_of_stream: true,
+            headers: Metadata::new(),
+            trailers: Metadata::new(),
+        },
+        Err(err) => Response {
+            id: request.id,
+            payload: None,
+            error: Some(Status::from(err)),
+            end_of_stream: true,
+            headers: Metadata::new(),
+            trailers: Metadata::new(),
+        },
+    };
+    stream.send(crate::serialize(&response, format)?).await?;
+    stream.finish().await
+}
+
+/// Dispatches a request to the registered service, within the server's deadline
+///
+/// The service runs with a [`CallContext`] carrying the request's metadata and deadline,
+/// so handlers generated from proto files or with the `service` macro work unchanged.
+async fn call_service(
+    request: &Request,
+    services: &RwLock<HashMap<String, Arc<dyn Service>>>,
+    peer_addr: SocketAddr,
+    format: SerializationFormat,
+) -> Result<Bytes, Error> {
+    let (service_name, method_name) = request.method.split_once('.')
+        .ok_or_else(|| Error::InvalidArgument(format!("Invalid method format. Expected 'service.method', got '{}'", request.method)))?;
+    let service = services.read().await.get(service_name).cloned()
+        .ok_or_else(|| Error::MethodNotFound(request.method.clone()))?;
+    
+    let deadline = request.timeout_ms.map(|ms| Instant::now() + Duration::from_millis(ms));
+    let context = CallContext::for_reverse_call(
+        request.id,
+        request.method.clone(),
+        deadline,
+        request.metadata.clone(),
+        peer_addr,
+        format,
+    );
+    let call = context.scope(service.call(method_name, request.payload.clone()));
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, call)
+            .await
+            .map_err(|_| Error::Timeout)?,
+        None => call.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    
+    /// Service echoing details of the context it runs with
+    struct ContextEcho;
+    
+    #[async_trait]
+    impl Service for ContextEcho {
+        async fn call(&self, _method: &str, _payload: Bytes) -> Result<Bytes, Error> {
+            let context = CallContext::current()
+                .ok_or_else(|| Error::Internal("No call context".to_string()))?;
+            assert!(context.session().is_none());
+            assert!(context.identity().is_none());
+            assert!(context.deadline().is_some());
+            let echo = format!(
+                "{} {} {} {}",
+                context.method(),
+                context.peer_addr(),
+                context.metadata().get_str("trace-id").unwrap_or_default(),
+                context.format() == SerializationFormat::Json,
+            );
+            Ok(Bytes::from(echo))
+        }
+    }
+    
+    #[tokio::test]
+    async fn reverse_calls_run_with_context() {
+        let services = RwLock::new(HashMap::new());
+        services.write().await.insert("echo".to_string(), Arc::new(ContextEcho) as Arc<dyn Service>);
+        
+        let mut metadata = Metadata::new();
+        metadata.insert_str("trace-id", "abc");
+        let request = Request {
+            id: 7,
+            method: "echo.context".to_string(),
+            payload: Bytes::new(),
+            kind: RequestKind::Unary,
+            end_of_stream: false,
+            timeout_ms: Some(1000),
+            metadata,
+        };
+        
+        let peer_addr: SocketAddr = "127.0.0.1:4433".parse().unwrap();
+        let response = call_service(&request, &services, peer_addr, SerializationFormat::Json).await.unwrap();
+        assert_eq!(response, Bytes::from("echo.context 127.0.0.1:4433 abc true"));
+    }
+}
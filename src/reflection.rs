@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use tokio::sync::RwLock;
+
+use crate::context::CallContext;
+use crate::error::Error;
+use crate::{deserialize, serialize, ContextService, Service};
+
+/// Name the reflection service is registered under
+pub const REFLECTION_SERVICE_NAME: &str = "reflection";
+
+/// Encoded `FileDescriptorSet` of QuicServe's own protobuf definitions
+pub const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/quicserve_descriptor.bin"));
+
+// Messages of the service, generated from `reflection.proto`
+pub use crate::proto::quicserve::reflection::{
+    DescribeServiceRequest, DescribeServiceResponse, ListServicesRequest, ListServicesResponse, ServiceInfo,
+};
+
+/// Built-in service describing the services registered on a server
+///
+/// Enabled with [`Server::enable_reflection`](crate::Server::enable_reflection) and
+/// registered as `reflection`, with two methods:
+///
+/// - `list_services` takes a [`ListServicesRequest`] and returns every service with
+///   its methods as a [`ListServicesResponse`].
+/// - `describe_service` takes a [`DescribeServiceRequest`] and returns a
+///   [`DescribeServiceResponse`], including the protobuf descriptors the service
+///   provides through [`Service::file_descriptor_set`].
+pub struct ReflectionService {
+    /// Services of the server, not kept alive by the reflection service itself
+    services: Weak<RwLock<HashMap<String, Arc<dyn Service>>>>,
+}
+
+impl ReflectionService {
+    /// Creates a new ReflectionService describing the given services
+    pub(crate) fn new(services: Weak<RwLock<HashMap<String, Arc<dyn Service>>>>) -> Self {
+        Self { services }
+    }
+    
+    /// Returns the services of the server
+    fn services(&self) -> Result<Arc<RwLock<HashMap<String, Arc<dyn Service>>>>, Error> {
+        self.services.upgrade()
+            .ok_or_else(|| Error::Unavailable("Server is shutting down".to_string()))
+    }
+    
+    /// Lists every registered service
+    async fn list_services(&self) -> Result<ListServicesResponse, Error> {
+        let services = self.services()?;
+        let mut services: Vec<ServiceInfo> = services.read().await.iter()
+            .map(|(name, service)| ServiceInfo {
+                name: name.clone(),
+                methods: service.methods(),
+            })
+            .collect();
+        services.sort_by(|a, b| a.name.cmp(&b.name));
+        
+        Ok(ListServicesResponse { services })
+    }
+    
+    /// Describes a single service
+    async fn describe_service(&self, request: DescribeServiceRequest) -> Result<DescribeServiceResponse, Error> {
+        let services = self.services()?;
+        let service = services.read().await.get(&request.name).cloned()
+            .ok_or_else(|| Error::NotFound(format!("Unknown service: {}", request.name)))?;
+        
+        Ok(DescribeServiceResponse {
+            service: Some(ServiceInfo {
+                name: request.name,
+                methods: service.methods(),
+            }),
+            file_descriptor_set: service.file_descriptor_set().unwrap_or_default(),
+        })
+    }
+}
+
+#[async_trait]
+impl ContextService for ReflectionService {
+    async fn call(&self, context: &CallContext, method: &str, payload: Bytes) -> Result<Bytes, Error> {
+        let format = context.format();
+        match method {
+            "list_services" => {
+                let _: ListServicesRequest = deserialize(&payload, format)?;
+                serialize(&self.list_services().await?, format)
+            }
+            "describe_service" => {
+                let request: DescribeServiceRequest = deserialize(&payload, format)?;
+                serialize(&self.describe_service(request).await?, format)
+            }
+            _ => Err(Error::MethodNotFound(method.to_string())),
+        }
+    }
+    
+    fn methods(&self) -> Vec<String> {
+        vec!["list_services".into(), "describe_service".into()]
+    }
+    
+    fn file_descriptor_set(&self) -> Option<Bytes> {
+        Some(Bytes::from_static(FILE_DESCRIPTOR_SET))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Service with the given methods and descriptors
+    struct Described {
+        /// Methods of the service
+        methods: Vec<String>,
+        /// Descriptors of the service, if it provides any
+        descriptors: Option<Bytes>,
+    }
+    
+    #[async_trait]
+    impl Service for Described {
+        async fn call(&self, method: &str, _payload: Bytes) -> Result<Bytes, Error> {
+            Err(Error::MethodNotFound(method.to_string()))
+        }
+        
+        fn methods(&self) -> Vec<String> {
+            self.methods.clone()
+        }
+        
+        fn file_descriptor_set(&self) -> Option<Bytes> {
+            self.descriptors.clone()
+        }
+    }
+    
+    /// Returns the services of a server, with `greeter` providing descriptors
+    fn services() -> Arc<RwLock<HashMap<String, Arc<dyn Service>>>> {
+        let mut services: HashMap<String, Arc<dyn Service>> = HashMap::new();
+        services.insert("greeter".to_string(), Arc::new(Described {
+            methods: vec!["hello".to_string(), "goodbye".to_string()],
+            descriptors: Some(Bytes::from_static(b"descriptors")),
+        }));
+        services.insert("echo".to_string(), Arc::new(Described { methods: vec!["echo".to_string()], descriptors: None }));
+        Arc::new(RwLock::new(services))
+    }
+    
+    /// Returns a request describing the named service
+    fn describe(name: &str) -> DescribeServiceRequest {
+        DescribeServiceRequest { name: name.to_string() }
+    }
+    
+    #[tokio::test]
+    async fn lists_services_by_name() {
+        let services = services();
+        let reflection = ReflectionService::new(Arc::downgrade(&services));
+        
+        let response = reflection.list_services().await.unwrap();
+        let names: Vec<&str> = response.services.iter().map(|service| service.name.as_str()).collect();
+        assert_eq!(names, ["echo", "greeter"]);
+        assert_eq!(response.services[1].methods, ["hello", "goodbye"]);
+    }
+    
+    #[tokio::test]
+    async fn describes_services_with_their_descriptors() {
+        let services = services();
+        let reflection = ReflectionService::new(Arc::downgrade(&services));
+        
+        let response = reflection.describe_service(describe("greeter")).await.unwrap();
+        assert_eq!(response.service.unwrap().methods, ["hello", "goodbye"]);
+        assert_eq!(response.file_descriptor_set, Bytes::from_static(b"descriptors"));
+        
+        let response = reflection.describe_service(describe("echo")).await.unwrap();
+        assert!(response.file_descriptor_set.is_empty());
+        
+        let result = reflection.describe_service(describe("missing")).await;
+        assert!(matches!(result, Err(Error::NotFound(_))));
+    }
+    
+    #[tokio::test]
+    async fn fails_once_the_server_is_gone() {
+        let services = services();
+        let reflection = ReflectionService::new(Arc::downgrade(&services));
+        drop(services);
+        
+        assert!(matches!(reflection.list_services().await, Err(Error::Unavailable(_))));
+        assert!(matches!(reflection.describe_service(describe("echo")).await, Err(Error::Unavailable(_))));
+    }
+    
+    #[test]
+    fn provides_its_own_descriptors() {
+        let reflection = ReflectionService::new(Weak::new());
+        assert_eq!(ContextService::file_descriptor_set(&reflection).unwrap(), FILE_DESCRIPTOR_SET);
+    }
+}
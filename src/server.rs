@@ -1,27 +1,87 @@
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
 
 use anyhow::Result;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use futures_util::{SinkExt, StreamExt};
 use h3::quic::Connection;
-use h3_webtransport::{server, session::AcceptRequest, Session};
+use h3_webtransport::{server, session::AcceptRequest};
 use log::{debug, error, info, warn};
-use quinn::{Endpoint, ServerConfig};
-use tokio::sync::{Mutex, RwLock};
+use quinn::{Endpoint, ServerConfig, VarInt};
+use tokio::sync::{mpsc, AcquireError, Mutex, OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio::task::{AbortHandle, JoinSet};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use uuid::Uuid;
+use x509_parser::extensions::GeneralName;
 
-use crate::{config::Config, error::Error, Request, Response, Service, WEBTRANSPORT_PROTOCOL};
+use crate::{config::Config, error::Error, ContextService, ContextServiceAdapter, Request, RequestKind, RequestStream, Response, ResponseStream, Service, WEBTRANSPORT_PROTOCOL};
+use crate::auth::{rejection_status, Authenticator, ConnectInfo, Identity};
+use crate::authz::Policy;
+use crate::context::{CallContext, SessionInfo};
+use crate::datagram::{DatagramHandler, DatagramSequencer};
+use crate::group::GroupRegistry;
+use crate::health::{HealthReporter, HealthService, HEALTH_SERVICE_NAME};
+use crate::interceptor::{InterceptedRequest, Interceptor, InterceptorChain};
+use crate::metadata::Metadata;
+use crate::proto::quicserve::DatagramProto;
+use crate::pubsub::{PubSub, PubSubConfig, PubSubService, PUBSUB_SERVICE_NAME};
+use crate::push::SessionHandle;
+use crate::reflection::{ReflectionService, REFLECTION_SERVICE_NAME};
+use crate::status::Status;
 use crate::transport::MessageStream;
 
 /// RPC Server implementation
 pub struct Server {
-    /// Configuration
-    config: Config,
     /// QUIC endpoint
     endpoint: Endpoint,
+    /// State shared with every connection
+    state: Arc<ServerState>,
+}
+
+/// State shared by every connection of a server
+struct ServerState {
+    /// Configuration
+    config: Config,
     /// Registered services
     services: Arc<RwLock<HashMap<String, Arc<dyn Service>>>>,
+    /// Interceptors applied to every call, outermost first
+    interceptors: RwLock<Vec<Arc<dyn Interceptor>>>,
+    /// Interceptors applied to the calls of a single service, by service name
+    service_interceptors: RwLock<HashMap<String, Vec<Arc<dyn Interceptor>>>>,
+    /// Authenticator run when a session is established
+    authenticator: RwLock<Option<Arc<dyn Authenticator>>>,
+    /// Authorization policy checked before every call is dispatched
+    policy: RwLock<Option<Arc<Policy>>>,
+    /// Serving status of the server and its services
+    health: HealthReporter,
+    /// Handlers of datagrams, by full `service.method` name
+    datagram_handlers: RwLock<HashMap<String, Arc<dyn DatagramHandler>>>,
+    /// Established sessions, by ID
+    sessions: Arc<RwLock<HashMap<Uuid, SessionHandle>>>,
+    /// Named groups of sessions
+    groups: GroupRegistry,
+    /// Token cancelled when shutdown begins
+    shutdown: CancellationToken,
+}
+
+impl ServerState {
+    /// Returns the interceptors applying to a call of the given method
+    async fn interceptor_chain(&self, method: &str) -> InterceptorChain {
+        let mut interceptors = self.interceptors.read().await.clone();
+        let service_name = method.split_once('.').map_or(method, |(service_name, _)| service_name);
+        if let Some(service_interceptors) = self.service_interceptors.read().await.get(service_name) {
+            interceptors.extend(service_interceptors.iter().cloned());
+        }
+        InterceptorChain::new(interceptors)
+    }
 }
 
 impl Server {
@@ -37,29 +97,166 @@ impl Server {
         // Set ALPN protocols for HTTP/3
         endpoint.set_protocols(&[WEBTRANSPORT_PROTOCOL.to_vec()]);
         
+        let groups = GroupRegistry::new(config.format);
         Ok(Self {
-            config,
             endpoint,
-            services: Arc::new(RwLock::new(HashMap::new())),
+            state: Arc::new(ServerState {
+                config,
+                services: Arc::new(RwLock::new(HashMap::new())),
+                interceptors: RwLock::new(Vec::new()),
+                service_interceptors: RwLock::new(HashMap::new()),
+                authenticator: RwLock::new(None),
+                policy: RwLock::new(None),
+                health: HealthReporter::new(),
+                datagram_handlers: RwLock::new(HashMap::new()),
+                sessions: Arc::new(RwLock::new(HashMap::new())),
+                groups,
+                shutdown: CancellationToken::new(),
+            }),
         })
     }
     
+    /// Returns the local address the server is bound to
+    ///
+    /// Useful when the configured address asks for any free port.
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.endpoint.local_addr()
+            .map_err(|e| Error::Quic(format!("Failed to get local address: {}", e)))
+    }
+    
     /// Registers a service with the server
+    ///
+    /// The service is reported as serving by the health service.
     pub async fn register_service<S: Service>(&self, name: &str, service: S) -> Result<(), Error> {
-        let mut services = self.services.write().await;
+        let mut services = self.state.services.write().await;
         services.insert(name.to_string(), Arc::new(service));
+        self.state.health.set_serving(name);
+        Ok(())
+    }
+    
+    /// Registers a service whose handlers receive the call context explicitly
+    pub async fn register_context_service<S: ContextService>(&self, name: &str, service: S) -> Result<(), Error> {
+        self.register_service(name, ContextServiceAdapter::new(service)).await
+    }
+    
+    /// Registers the handler of datagrams sent to a `service.method` name
+    ///
+    /// Datagrams for methods without a handler are dropped.
+    pub async fn register_datagram_handler<H: DatagramHandler>(&self, method: &str, handler: H) -> Result<(), Error> {
+        let mut handlers = self.state.datagram_handlers.write().await;
+        handlers.insert(method.to_string(), Arc::new(handler));
+        Ok(())
+    }
+    
+    /// Registers the built-in [`ReflectionService`], describing every registered service
+    pub async fn enable_reflection(&self) -> Result<(), Error> {
+        let reflection = ReflectionService::new(Arc::downgrade(&self.state.services));
+        self.register_context_service(REFLECTION_SERVICE_NAME, reflection).await
+    }
+    
+    /// Registers the built-in [`HealthService`], reporting the status of the server and its services
+    pub async fn enable_health(&self) -> Result<(), Error> {
+        let health = HealthService::new(self.state.health.clone());
+        self.register_context_service(HEALTH_SERVICE_NAME, health).await
+    }
+    
+    /// Returns the handle used to report the serving status of services
+    pub fn health_reporter(&self) -> HealthReporter {
+        self.state.health.clone()
+    }
+    
+    /// Returns the registry of named session groups, used to broadcast to sets of clients
+    pub fn groups(&self) -> GroupRegistry {
+        self.state.groups.clone()
+    }
+    
+    /// Registers the built-in [`PubSubService`] and returns the hub services publish through
+    ///
+    /// Messages are published in the server's configured serialization format. Every
+    /// subscription ends when the server shuts down.
+    pub async fn enable_pubsub(&self, config: PubSubConfig) -> Result<PubSub, Error> {
+        if config.buffer_size == 0 {
+            return Err(Error::InvalidArgument("Pub/sub buffer size must be at least 1".to_string()));
+        }
+        
+        let pubsub = PubSub::new(config, self.state.config.format, self.state.shutdown.child_token());
+        self.register_context_service(PUBSUB_SERVICE_NAME, PubSubService::new(pubsub.clone())).await?;
+        Ok(pubsub)
+    }
+    
+    /// Sets the authenticator run on every new session, replacing any previous one
+    ///
+    /// Without an authenticator every session is accepted anonymously.
+    pub async fn set_authenticator<A: Authenticator>(&self, authenticator: A) -> Result<(), Error> {
+        let mut current = self.state.authenticator.write().await;
+        *current = Some(Arc::new(authenticator));
+        Ok(())
+    }
+    
+    /// Sets the authorization policy checked before every call, replacing any previous one
+    ///
+    /// The policy sees the identity left by interceptors. Denied calls fail with
+    /// [`Error::PermissionDenied`]. Without a policy every call is allowed.
+    pub async fn set_policy(&self, policy: Policy) -> Result<(), Error> {
+        let mut current = self.state.policy.write().await;
+        *current = Some(Arc::new(policy));
+        Ok(())
+    }
+    
+    /// Adds an interceptor applied to every call
+    ///
+    /// Interceptors run in the order they were added, before any per-service interceptor.
+    pub async fn add_interceptor<I: Interceptor>(&self, interceptor: I) -> Result<(), Error> {
+        let mut interceptors = self.state.interceptors.write().await;
+        interceptors.push(Arc::new(interceptor));
+        Ok(())
+    }
+    
+    /// Adds an interceptor applied to the calls of a single service
+    ///
+    /// Interceptors of a service run in the order they were added, after the global ones.
+    pub async fn add_service_interceptor<I: Interceptor>(&self, service: &str, interceptor: I) -> Result<(), Error> {
+        let mut service_interceptors = self.state.service_interceptors.write().await;
+        service_interceptors.entry(service.to_string()).or_default().push(Arc::new(interceptor));
         Ok(())
     }
     
     /// Starts the server and begins accepting connections
-    pub async fn serve(self) -> Result<(), Error> {
-        info!("Server listening on {}", self.config.addr);
+    ///
+    /// Connections are accepted on a background task. Use the returned handle to
+    /// shut the server down or to wait until it has stopped.
+    pub async fn serve(self) -> Result<ServerHandle, Error> {
+        info!("Server listening on {}", self.state.config.addr);
+        
+        let handle = ServerHandle {
+            endpoint: self.endpoint.clone(),
+            shutdown: self.state.shutdown.clone(),
+            stopped: CancellationToken::new(),
+            connections: TaskTracker::new(),
+            health: self.state.health.clone(),
+            sessions: self.state.sessions.clone(),
+            groups: self.state.groups.clone(),
+        };
         
+        // Accept connections until the server is shut down
         let server = Arc::new(self);
+        let accept_handle = handle.clone();
+        tokio::spawn(async move {
+            server.accept_connections(accept_handle).await;
+        });
         
+        Ok(handle)
+    }
+    
+    /// Accepts new QUIC connections until shutdown or until the endpoint closes
+    async fn accept_connections(self: Arc<Self>, handle: ServerHandle) {
         loop {
             // Accept new QUIC connections
-            let connection = match server.endpoint.accept().await {
+            let incoming = tokio::select! {
+                incoming = self.endpoint.accept() => incoming,
+                _ = handle.shutdown.cancelled() => break,
+            };
+            let connection = match incoming {
                 Some(conn) => match conn.await {
                     Ok(conn) => conn,
                     Err(e) => {
@@ -69,18 +266,20 @@ impl Server {
                 },
                 None => {
                     error!("Endpoint closed");
-                    break Ok(());
+                    handle.stopped.cancel();
+                    break;
                 }
             };
             
             info!("Accepted connection from {}", connection.remote_address());
             
             // Clone server reference for the new connection
-            let server_clone = server.clone();
+            let server_clone = self.clone();
+            let shutdown = handle.shutdown.clone();
             
             // Spawn a new task to handle the connection
-            tokio::spawn(async move {
-                if let Err(e) = server_clone.handle_connection(connection).await {
+            handle.connections.spawn(async move {
+                if let Err(e) = server_clone.handle_connection(connection, shutdown).await {
                     error!("Connection error: {}", e);
                 }
             });
@@ -88,8 +287,16 @@ impl Server {
     }
     
     /// Handles a new QUIC connection
-    async fn handle_connection(&self, connection: quinn::Connection) -> Result<(), Error> {
-        debug!("New connection from {}", connection.remote_address());
+    ///
+    /// On shutdown the connection stops accepting sessions, sends GOAWAY and waits
+    /// for its sessions to finish their in-flight calls.
+    async fn handle_connection(&self, connection: quinn::Connection, shutdown: CancellationToken) -> Result<(), Error> {
+        let peer_addr = connection.remote_address();
+        debug!("New connection from {}", peer_addr);
+        
+        // The certificate chain the client presented, if any, has been verified during the handshake
+        let peer_certificates = peer_certificates(&connection);
+        let quic_connection = connection.clone();
         
         // Create HTTP/3 connection
         let h3_conn = h3::server::Connection::new(h3::quic::Connection::new(connection))
@@ -105,30 +312,39 @@ impl Server {
             .await
             .map_err(|e| Error::WebTransport(format!("Failed to create WebTransport server: {}", e)))?;
         
+        // Sessions of this connection, the connection is kept open until they finish
+        let sessions = TaskTracker::new();
+        
         // Accept WebTransport sessions
-        while let Some(accept_request) = acceptor.accept().await {
+        loop {
+            let accept_request = tokio::select! {
+                accept_request = acceptor.accept() => match accept_request {
+                    Some(accept_request) => accept_request,
+                    None => break,
+                },
+                _ = shutdown.cancelled() => {
+                    // Tell the client not to open any further sessions
+                    debug!("Sending GOAWAY");
+                    if let Err(e) = acceptor.shutdown(0).await {
+                        debug!("Failed to send GOAWAY: {}", e);
+                    }
+                    break;
+                }
+            };
+            
             let path = accept_request.request().uri().path().to_string();
             debug!("New session request to path: {}", path);
             
             if path == "/rpc" {
-                // Accept the session
-                match accept_request.accept().await {
-                    Ok(session) => {
-                        debug!("Session accepted");
-                        let services = self.services.clone();
-                        let config = self.config.clone();
-                        
-                        // Spawn a new task to handle the session
-                        tokio::spawn(async move {
-                            if let Err(e) = handle_session(session, services, config).await {
-                                error!("Session error: {}", e);
-                            }
-                        });
-                    }
-                    Err(e) => {
-                        error!("Failed to accept session: {}", e);
-                    }
-                }
+                // Authenticate and serve the session on its own task, so a slow
+                // authenticator doesn't hold up other sessions of the connection
+                let state = self.state.clone();
+                let connection = quic_connection.clone();
+                let peer_certificates = peer_certificates.clone();
+                let shutdown = shutdown.clone();
+                sessions.spawn(async move {
+                    establish_session(state, accept_request, connection, peer_certificates, shutdown).await;
+                });
             } else {
                 // Reject sessions with unknown paths
                 debug!("Rejecting session with unknown path: {}", path);
@@ -137,100 +353,959 @@ impl Server {
             }
         }
         
+        // Let in-flight calls finish before the connection is dropped
+        sessions.close();
+        sessions.wait().await;
+        
         Ok(())
     }
 }
 
+/// Handle used to shut down a running [`Server`]
+#[derive(Clone)]
+pub struct ServerHandle {
+    /// QUIC endpoint of the server
+    endpoint: Endpoint,
+    /// Token cancelled when shutdown begins
+    shutdown: CancellationToken,
+    /// Token cancelled once the server has stopped
+    stopped: CancellationToken,
+    /// Tasks handling accepted connections
+    connections: TaskTracker,
+    /// Serving status of the server and its services
+    health: HealthReporter,
+    /// Established sessions, by ID
+    sessions: Arc<RwLock<HashMap<Uuid, SessionHandle>>>,
+    /// Named groups of sessions
+    groups: GroupRegistry,
+}
+
+impl ServerHandle {
+    /// Shuts the server down, waiting for every in-flight call to finish
+    pub async fn shutdown(&self) {
+        self.drain(None).await;
+    }
+    
+    /// Shuts the server down, giving in-flight calls until `grace_period` has elapsed to finish
+    ///
+    /// Calls still running once the grace period is over are cut off when the endpoint closes.
+    pub async fn shutdown_with_deadline(&self, grace_period: Duration) {
+        self.drain(Some(Instant::now() + grace_period)).await;
+    }
+    
+    /// Waits until the server has stopped
+    pub async fn wait(&self) {
+        self.stopped.cancelled().await;
+    }
+    
+    /// Returns whether shutdown has begun
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.is_cancelled()
+    }
+    
+    /// Returns the handle used to report the serving status of services
+    pub fn health_reporter(&self) -> HealthReporter {
+        self.health.clone()
+    }
+    
+    /// Returns the established session with the given ID, if it is still open
+    pub async fn session(&self, session_id: Uuid) -> Option<SessionHandle> {
+        self.sessions.read().await.get(&session_id).cloned()
+    }
+    
+    /// Returns every established session
+    pub async fn sessions(&self) -> Vec<SessionHandle> {
+        self.sessions.read().await.values().cloned().collect()
+    }
+    
+    /// Returns the registry of named session groups
+    pub fn groups(&self) -> GroupRegistry {
+        self.groups.clone()
+    }
+    
+    /// Stops accepting work, drains in-flight calls and closes the endpoint
+    async fn drain(&self, deadline: Option<Instant>) {
+        info!("Shutting down server");
+        
+        // Tell health checks the server is going away before anything else
+        self.health.shutdown();
+        
+        // Refuse new connections, and stop accepting sessions and calls on existing ones
+        self.endpoint.set_server_config(None);
+        self.shutdown.cancel();
+        
+        // Let in-flight calls finish
+        self.connections.close();
+        match deadline {
+            Some(deadline) => {
+                if tokio::time::timeout_at(deadline, self.connections.wait()).await.is_err() {
+                    warn!("Shutdown deadline expired with calls still in flight");
+                }
+            }
+            None => self.connections.wait().await,
+        }
+        
+        // Close remaining connections and wait for the peers to be notified
+        self.endpoint.close(VarInt::from_u32(0), b"server shutdown");
+        self.endpoint.wait_idle().await;
+        
+        self.stopped.cancel();
+        info!("Server stopped");
+    }
+}
+
+/// Authenticates a session request, then accepts and serves the session until it ends
+async fn establish_session(
+    state: Arc<ServerState>,
+    accept_request: AcceptRequest,
+    connection: quinn::Connection,
+    peer_certificates: Arc<[Bytes]>,
+    shutdown: CancellationToken,
+) {
+    let peer_addr = connection.remote_address();
+    
+    // Authenticate the session before accepting it, unless shutdown began meanwhile
+    let identity = match authenticate(&state, &accept_request, peer_addr, &peer_certificates).await {
+        Ok(_) if shutdown.is_cancelled() => {
+            debug!("Rejecting session from {}: server is shutting down", peer_addr);
+            if let Err(e) = accept_request.reject_with_status(http::StatusCode::SERVICE_UNAVAILABLE).await {
+                error!("Failed to reject session: {}", e);
+            }
+            return;
+        }
+        Ok(identity) => identity,
+        Err(e) => {
+            warn!("Rejecting session from {}: {}", peer_addr, e);
+            if let Err(e) = accept_request.reject_with_status(rejection_status(&e)).await {
+                error!("Failed to reject session: {}", e);
+            }
+            return;
+        }
+    };
+    
+    // Accept the session
+    let session = match accept_request.accept().await {
+        Ok(session) => session,
+        Err(e) => {
+            error!("Failed to accept session: {}", e);
+            return;
+        }
+    };
+    debug!("Session accepted");
+    
+    let info = Arc::new(SessionInfo {
+        session_id: Uuid::new_v4(),
+        peer_addr,
+        peer_certificates: peer_certificates.clone(),
+        peer_subject: peer_certificates.first().and_then(|cert| certificate_subject(cert)),
+        peer_sans: peer_certificates.first().map(|cert| certificate_sans(cert)).unwrap_or_default(),
+        identity,
+        format: state.config.format,
+        session: Arc::new(session),
+        connection,
+        timeout_ms: state.config.timeout_ms,
+        next_request_id: AtomicU64::new(0),
+        next_datagram_sequence: AtomicU64::new(0),
+        closed: AtomicBool::new(false),
+    });
+    debug!("Session {} established with {}", info.session_id, peer_addr);
+    
+    // Let the server address the session until it ends
+    let session_id = info.session_id;
+    state.sessions.write().await.insert(session_id, SessionHandle::new(info.clone()));
+    
+    if let Err(e) = handle_session(state.clone(), info.clone(), shutdown).await {
+        error!("Session error: {}", e);
+    }
+    
+    // Mark the session closed first, so it can't join a group once its memberships are removed
+    info.closed.store(true, Ordering::Release);
+    state.sessions.write().await.remove(&session_id);
+    state.groups.remove_session(session_id).await;
+}
+
+/// Runs the authenticator, if any, on a session request
+async fn authenticate(
+    state: &ServerState,
+    accept_request: &AcceptRequest,
+    peer_addr: SocketAddr,
+    peer_certificates: &Arc<[Bytes]>,
+) -> Result<Option<Arc<Identity>>, Error> {
+    let authenticator = match state.authenticator.read().await.clone() {
+        Some(authenticator) => authenticator,
+        None => return Ok(None),
+    };
+    
+    let request = accept_request.request();
+    let info = ConnectInfo {
+        peer_addr,
+        authority: request.uri().authority().map(|authority| authority.to_string()),
+        path: request.uri().path().to_string(),
+        headers: request.headers().clone(),
+        peer_certificates: peer_certificates.to_vec(),
+    };
+    
+    let identity = authenticator.authenticate(&info).await?;
+    debug!("Authenticated session from {} as {}", peer_addr, identity.subject);
+    Ok(Some(Arc::new(identity)))
+}
+
 /// Handles a WebTransport session
 async fn handle_session(
-    session: Session<server::Connection>,
-    services: Arc<RwLock<HashMap<String, Arc<dyn Service>>>>,
-    config: Config,
+    state: Arc<ServerState>,
+    info: Arc<SessionInfo>,
+    shutdown: CancellationToken,
 ) -> Result<(), Error> {
-    // Create a bidirectional stream for RPC communication
-    let stream = match session.accept_bi().await {
-        Ok(stream) => stream,
+    // Limit the number of requests dispatched at once across all streams of the session
+    let in_flight = Arc::new(Semaphore::new(max_in_flight(&state.config)));
+    
+    // Streams and datagram handlers of this session, the session is kept open until they finish
+    let streams = TaskTracker::new();
+    
+    // Datagrams older than the last one accepted for their method are dropped
+    let mut sequencer = DatagramSequencer::default();
+    let mut datagrams_open = true;
+    
+    // Accept bidirectional streams and datagrams until the session closes or the server shuts down
+    let result = loop {
+        let accepted = tokio::select! {
+            accepted = info.session.accept_bi() => accepted,
+            datagram = info.session.accept_datagram(), if datagrams_open => {
+                match datagram {
+                    Ok(Some((_, data))) => dispatch_datagram(data, &state, &info, &mut sequencer, &in_flight, &streams).await,
+                    Ok(None) => datagrams_open = false,
+                    Err(e) => {
+                        debug!("Stopped reading datagrams: {}", e);
+                        datagrams_open = false;
+                    }
+                }
+                continue;
+            }
+            _ = shutdown.cancelled() => break Ok(()),
+        };
+        let stream = match accepted {
+            Ok(Some(stream)) => stream,
+            Ok(None) => break Ok(()),
+            Err(e) => {
+                break Err(Error::WebTransport(format!("Failed to accept bidirectional stream: {}", e)));
+            }
+        };
+        
+        debug!("Accepted bidirectional stream");
+        let state = state.clone();
+        let info = info.clone();
+        let in_flight = in_flight.clone();
+        let shutdown = shutdown.clone();
+        
+        // Spawn a new task to serve the stream
+        streams.spawn(async move {
+            if let Err(e) = serve_stream(stream, state, info, in_flight, shutdown).await {
+                debug!("Stream closed with error: {}", e);
+            }
+        });
+    };
+    
+    // Let in-flight calls finish before the session is dropped
+    streams.close();
+    streams.wait().await;
+    
+    result
+}
+
+/// Decodes a datagram and hands it to its handler on a new task
+///
+/// Malformed datagrams, datagrams without a handler, datagrams denied by the authorization
+/// policy and stale datagrams are dropped. Handlers share the session's dispatch slots
+/// with calls; a datagram arriving while every slot is taken is dropped rather than queued.
+async fn dispatch_datagram(
+    data: Bytes,
+    state: &Arc<ServerState>,
+    info: &Arc<SessionInfo>,
+    sequencer: &mut DatagramSequencer,
+    in_flight: &Arc<Semaphore>,
+    tasks: &TaskTracker,
+) {
+    let frame: DatagramProto = match crate::deserialize(&data, info.format) {
+        Ok(frame) => frame,
         Err(e) => {
-            return Err(Error::WebTransport(format!("Failed to accept bidirectional stream: {}", e)));
+            debug!("Dropping malformed datagram: {}", e);
+            return;
+        }
+    };
+    
+    // Check the method before sequencing it, so only registered methods are ever tracked
+    let handler = match state.datagram_handlers.read().await.get(&frame.method).cloned() {
+        Some(handler) => handler,
+        None => {
+            debug!("Dropping datagram for {}: no handler registered", frame.method);
+            return;
+        }
+    };
+    
+    // Datagrams are subject to the same authorization policy as calls
+    let context = CallContext::new(
+        frame.sequence,
+        frame.method.clone(),
+        None,
+        Metadata::new(),
+        info.identity.clone(),
+        info.clone(),
+        CancellationToken::new(),
+    );
+    if let Some(policy) = state.policy.read().await.clone() {
+        if let Err(e) = policy.authorize(&context) {
+            debug!("Dropping datagram: {}", e);
+            return;
+        }
+    }
+    
+    let permit = match in_flight.clone().try_acquire_owned() {
+        Ok(permit) => permit,
+        Err(_) => {
+            debug!("Dropping datagram {} for {}: too many requests in flight", frame.sequence, frame.method);
+            return;
         }
     };
+    if !sequencer.accept(&frame) {
+        debug!("Dropping stale datagram {} for {}", frame.sequence, frame.method);
+        return;
+    }
+    
+    tasks.spawn(async move {
+        let _permit = permit;
+        let scope = context.clone();
+        scope.scope(handler.handle(&context, frame.payload)).await;
+    });
+}
+
+/// Returns the verified certificate chain presented by the client of a connection
+fn peer_certificates(connection: &quinn::Connection) -> Arc<[Bytes]> {
+    connection.peer_identity()
+        .and_then(|identity| identity.downcast::<Vec<rustls::pki_types::CertificateDer<'static>>>().ok())
+        .map(|certs| certs.into_iter().map(|cert| Bytes::copy_from_slice(&cert)).collect())
+        .unwrap_or_else(|| Arc::from(Vec::new()))
+}
+
+/// Returns the subject of a DER certificate, if it can be parsed
+fn certificate_subject(der: &[u8]) -> Option<String> {
+    x509_parser::parse_x509_certificate(der)
+        .ok()
+        .map(|(_, cert)| cert.subject().to_string())
+}
+
+/// Returns the DNS names, email addresses, URIs and IP addresses a certificate is issued for
+fn certificate_sans(der: &[u8]) -> Vec<String> {
+    let cert = match x509_parser::parse_x509_certificate(der) {
+        Ok((_, cert)) => cert,
+        Err(_) => return Vec::new(),
+    };
+    let sans = match cert.subject_alternative_name() {
+        Ok(Some(extension)) => extension.value,
+        _ => return Vec::new(),
+    };
+    
+    sans.general_names.iter()
+        .filter_map(|name| match name {
+            GeneralName::DNSName(name) | GeneralName::RFC822Name(name) | GeneralName::URI(name) => Some(name.to_string()),
+            GeneralName::IPAddress(bytes) => match bytes.len() {
+                4 => <[u8; 4]>::try_from(*bytes).ok().map(|ip| IpAddr::from(ip).to_string()),
+                16 => <[u8; 16]>::try_from(*bytes).ok().map(|ip| IpAddr::from(ip).to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Number of messages buffered for a streaming call whose handler hasn't consumed them yet
+///
+/// A call whose client sends further messages while its buffer is full fails with
+/// `RESOURCE_EXHAUSTED`, so one slow handler never holds up the other calls of its stream.
+const STREAM_INPUT_BUFFER: usize = 16;
+
+/// Time a cancelled handler has to notice [`CallContext::cancelled`] and clean up before it is aborted
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Returns the number of requests a session may dispatch at once
+fn max_in_flight(config: &Config) -> usize {
+    // Ordered dispatch processes a single request at a time
+    if config.ordered_dispatch {
+        1
+    } else {
+        config.max_in_flight_requests.max(1)
+    }
+}
+
+/// Serves RPC requests arriving on a single bidirectional stream
+///
+/// A stream either carries a single call or, in shared mode, every call made by the client.
+/// If the peer resets the stream, all calls started on it are cancelled. On shutdown the
+/// stream refuses new calls and stops reading once no running call expects further input.
+async fn serve_stream(
+    stream: h3_webtransport::session::BidiStream,
+    state: Arc<ServerState>,
+    info: Arc<SessionInfo>,
+    in_flight: Arc<Semaphore>,
+    shutdown: CancellationToken,
+) -> Result<(), Error> {
+    let config = &state.config;
+    // Split the stream so responses can be written while further requests are read
+    let (mut reader, mut writer) = MessageStream::new(stream).split();
+    
+    // Write responses in completion order from a dedicated task
+    let (response_tx, mut response_rx) = mpsc::channel::<Response>(max_in_flight(config));
+    let format = config.format;
+    let writer_task = tokio::spawn(async move {
+        while let Some(response) = response_rx.recv().await {
+            // A response that can't be serialized fails its own call, not the whole stream
+            let response_bytes = match crate::serialize(&response, format) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    error!("Failed to serialize response to request {}: {}", response.id, e);
+                    let status = error_response(response.id, Error::Internal(format!("Failed to serialize response: {}", e)));
+                    crate::serialize(&status, format)?
+                }
+            };
+            writer.send(response_bytes).await?;
+        }
+        Ok::<(), Error>(())
+    });
+    
+    // Handlers for calls made on this stream, and the calls they are running
+    let mut handlers = JoinSet::new();
+    let mut running: HashMap<u64, RunningCall> = HashMap::new();
+    
+    // Inputs of bidirectional streaming calls that are still receiving messages
+    let mut open_inputs: HashMap<u64, mpsc::Sender<Result<Bytes, Error>>> = HashMap::new();
     
-    // Create message stream wrapper
-    let mut message_stream = MessageStream::new(stream);
+    // Whether the server is shutting down
+    let mut draining = false;
     
     // Process RPC requests
-    while let Some(request_bytes) = message_stream.receive().await? {
-        // Deserialize request
-        let request: Request = crate::deserialize(&request_bytes, config.format)?;
-        debug!("Received request: {} - method: {}", request.id, request.method);
+    loop {
+        // Once draining, stop reading as soon as no call needs further input
+        if draining && open_inputs.is_empty() {
+            break;
+        }
         
-        // Split method name into service and method parts
-        let parts: Vec<&str> = request.method.splitn(2, '.').collect();
-        if parts.len() != 2 {
-            let error_response = Response {
-                id: request.id,
-                payload: None,
-                error: Some(format!("Invalid method format. Expected 'service.method', got '{}'", request.method)),
-            };
-            
-            // Serialize and send error response
-            let response_bytes = crate::serialize(&error_response, config.format)?;
-            message_stream.send(response_bytes).await?;
+        let received = tokio::select! {
+            received = reader.receive() => received,
+            _ = shutdown.cancelled(), if !draining => {
+                draining = true;
+                continue;
+            }
+        };
+        let request_bytes = match received {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => break,
+            Err(e) => {
+                // The stream was reset, cancel every call made on it. Their handlers
+                // are left to clean up, and aborted once their grace period ends
+                for (_, call) in running.drain() {
+                    call.cancel();
+                }
+                handlers.detach_all();
+                writer_task.abort();
+                return Err(e);
+            }
+        };
+        
+        // Deserialize request, a malformed frame is dropped without ending the stream's other calls
+        let mut request: Request = match crate::deserialize(&request_bytes, config.format) {
+            Ok(request) => request,
+            Err(e) => {
+                warn!("Dropping malformed request frame from {}: {}", info.peer_addr, e);
+                continue;
+            }
+        };
+        
+        // The client gave up on a call, abort its handler
+        if request.kind == RequestKind::Cancel {
+            debug!("Cancelling request: {}", request.id);
+            open_inputs.remove(&request.id);
+            if let Some(call) = running.remove(&request.id) {
+                call.cancel();
+            }
             continue;
         }
         
-        let service_name = parts[0];
-        let method_name = parts[1];
+        // Only servers push messages
+        if request.kind == RequestKind::Push {
+            let response = error_response(request.id, Error::InvalidArgument("Clients can't push messages".to_string()));
+            if response_tx.send(response).await.is_err() {
+                debug!("Response writer closed before response could be sent");
+            }
+            continue;
+        }
         
-        // Look up service
-        let services_read = services.read().await;
-        let service = match services_read.get(service_name) {
-            Some(service) => service.clone(),
-            None => {
-                let error_response = Response {
-                    id: request.id,
-                    payload: None,
-                    error: Some(format!("Service not found: {}", service_name)),
-                };
-                
-                // Serialize and send error response
-                let response_bytes = crate::serialize(&error_response, config.format)?;
-                message_stream.send(response_bytes).await?;
+        // Forward messages belonging to a streaming call that is already running
+        if let Some(input) = open_inputs.get(&request.id) {
+            if request.end_of_stream {
+                // The client half-closed its side of the call
+                open_inputs.remove(&request.id);
                 continue;
             }
-        };
-        drop(services_read);
-        
-        // Create response future with timeout
-        let timeout = tokio::time::timeout(
-            std::time::Duration::from_millis(config.timeout_ms),
-            service.call(method_name, request.payload),
-        );
-        
-        // Execute service call with timeout
-        let result = match timeout.await {
-            Ok(result) => result,
-            Err(_) => Err(Error::Timeout),
+            
+            // Never wait on a handler here, it would stall every other call on the stream
+            match input.try_send(Ok(request.payload)) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    // The client outpaced its handler, fail just this call
+                    debug!("Input buffer of call {} is full, cancelling it", request.id);
+                    open_inputs.remove(&request.id);
+                    if let Some(call) = running.remove(&request.id) {
+                        call.cancel();
+                    }
+                    let response = error_response(request.id, Error::ResourceExhausted(
+                        "Client sent messages faster than the handler consumed them".to_string(),
+                    ));
+                    if response_tx.send(response).await.is_err() {
+                        debug!("Response writer closed before response could be sent");
+                    }
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    // The handler stopped consuming its input
+                    open_inputs.remove(&request.id);
+                }
+            }
+            continue;
+        }
+        
+        // Messages of a streaming call whose input is already closed are dropped,
+        // only an opening request names a method
+        if request.kind == RequestKind::BidiStreaming && request.method.is_empty() {
+            debug!("Dropping message for closed input of call {}", request.id);
+            continue;
+        }
+        
+        // The server is shutting down, refuse new calls
+        if draining {
+            let response = error_response(request.id, Error::Unavailable("Server is shutting down".to_string()));
+            if response_tx.send(response).await.is_err() {
+                debug!("Response writer closed before response could be sent");
+            }
+            continue;
+        }
+        
+        debug!("Received request: {} - method: {}", request.id, request.method);
+        
+        // The deadline runs from the request's arrival, so time spent queued counts against it
+        let deadline = call_deadline(&request, config);
+        
+        // Take a place in the queue for a dispatch slot, the call's task waits for it
+        let permit = if is_long_lived(&request) {
+            None
+        } else {
+            Some(queue_for_slot(&in_flight).await)
         };
         
-        // Create response
-        let response = match result {
-            Ok(payload) => Response {
-                id: request.id,
-                payload: Some(payload),
-                error: None,
-            },
-            Err(err) => Response {
-                id: request.id,
-                payload: None,
-                error: Some(err.to_string()),
-            },
+        // Each call can be cancelled on its own
+        let id = request.id;
+        let cancellation = CancellationToken::new();
+        
+        // Messages that follow the opening request of a bidirectional call are fed to its handler
+        let requests = if request.kind == RequestKind::BidiStreaming {
+            let (input_tx, input_rx) = mpsc::channel(STREAM_INPUT_BUFFER);
+            if !request.end_of_stream {
+                open_inputs.insert(id, input_tx);
+            }
+            let requests: RequestStream = Box::pin(futures_util::stream::unfold(input_rx, |mut rx| async move {
+                rx.recv().await.map(|message| (message, rx))
+            }));
+            Some(requests)
+        } else {
+            None
         };
         
-        // Serialize and send response
-        let response_bytes = crate::serialize(&response, config.format)?;
-        message_stream.send(response_bytes).await?;
+        // Dispatch the request on its own task so neither queued nor slow calls block the stream
+        let call_state = state.clone();
+        let call_info = info.clone();
+        let call_cancellation = cancellation.clone();
+        let response_tx = response_tx.clone();
+        let abort_handle = handlers.spawn(async move {
+            // Wait for the dispatch slot, but no longer than the call's deadline
+            let permit = match permit {
+                Some(permit) => match with_deadline(deadline, async {
+                    permit.await.map_err(|_| Error::ConnectionClosed)
+                }).await {
+                    Ok(permit) => Some(permit),
+                    Err(err) => {
+                        debug!("Request {} not dispatched: {}", id, err);
+                        if response_tx.send(error_response(id, err)).await.is_err() {
+                            debug!("Response writer closed before response could be sent");
+                        }
+                        return id;
+                    }
+                },
+                None => None,
+            };
+            
+            handle_call(request, requests, deadline, &call_state, &call_info, call_cancellation, &response_tx).await;
+            drop(permit);
+            id
+        });
+        running.insert(id, RunningCall { abort_handle, cancellation, deadline });
+        
+        // Reap handlers that have already finished
+        while let Some(result) = handlers.try_join_next() {
+            if let Ok(id) = result {
+                running.remove(&id);
+            }
+        }
     }
     
+    // The client finished sending, so no streaming call will receive further input
+    open_inputs.clear();
+    
+    // Let in-flight requests finish and flush their responses
+    while handlers.join_next().await.is_some() {}
+    drop(response_tx);
+    writer_task.await
+        .map_err(|e| Error::Other(format!("Response writer task failed: {}", e)))??;
+    
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Returns whether a call is a built-in subscription, which doesn't take a dispatch slot
+///
+/// Pub/sub subscriptions and health watches stay open for the life of the session. Holding
+/// a slot for them would starve later calls, and block the session outright with
+/// ordered dispatch.
+fn is_long_lived(request: &Request) -> bool {
+    request.kind == RequestKind::ServerStreaming
+        && matches!(
+            request.method.split_once('.'),
+            Some((PUBSUB_SERVICE_NAME, "subscribe")) | Some((HEALTH_SERVICE_NAME, "watch"))
+        )
+}
+
+/// Future resolving to a dispatch slot once the calls queued before it have theirs
+type QueuedSlot = Pin<Box<dyn Future<Output = Result<OwnedSemaphorePermit, AcquireError>> + Send>>;
+
+/// Takes a place in the queue for a dispatch slot without waiting for one
+///
+/// The semaphore hands out slots in queue order, so calls start in arrival order
+/// even though each waits for its slot on its own task.
+async fn queue_for_slot(in_flight: &Arc<Semaphore>) -> QueuedSlot {
+    let mut acquire = Box::pin(in_flight.clone().acquire_owned());
+    
+    // Polling once joins the queue, or takes a slot right away if one is free
+    match futures_util::poll!(acquire.as_mut()) {
+        Poll::Ready(result) => Box::pin(std::future::ready(result)),
+        Poll::Pending => acquire,
+    }
+}
+
+/// A call whose handler is still running
+struct RunningCall {
+    /// Handle used to abort the handler task
+    abort_handle: AbortHandle,
+    /// Token observed by the handler for cooperative cancellation
+    cancellation: CancellationToken,
+    /// Deadline of the call, if it has one
+    deadline: Option<Instant>,
+}
+
+impl RunningCall {
+    /// Cancels the call, aborting its handler if it is still running once its grace period ends
+    ///
+    /// The grace period never extends past the call's deadline.
+    fn cancel(self) {
+        self.cancellation.cancel();
+        if self.abort_handle.is_finished() {
+            return;
+        }
+        
+        let grace_end = Instant::now() + CANCEL_GRACE_PERIOD;
+        let abort_at = self.deadline.map_or(grace_end, |deadline| deadline.min(grace_end));
+        tokio::spawn(async move {
+            tokio::time::sleep_until(abort_at).await;
+            self.abort_handle.abort();
+        });
+    }
+}
+
+/// Resolves a `service.method` name to the registered service and its method name
+async fn resolve_method<'a>(
+    method: &'a str,
+    services: &RwLock<HashMap<String, Arc<dyn Service>>>,
+) -> Result<(Arc<dyn Service>, &'a str), Error> {
+    // Split method name into service and method parts
+    let (service_name, method_name) = method.split_once('.')
+        .ok_or_else(|| Error::InvalidArgument(format!("Invalid method format. Expected 'service.method', got '{}'", method)))?;
+    
+    // Look up service
+    let service = services.read().await.get(service_name).cloned()
+        .ok_or_else(|| Error::MethodNotFound(method.to_string()))?;
+    
+    Ok((service, method_name))
+}
+
+/// Builds a final response carrying the status of an error
+fn error_response(id: u64, err: Error) -> Response {
+    Response {
+        id,
+        payload: None,
+        error: Some(Status::from(err)),
+        end_of_stream: true,
+        headers: Metadata::new(),
+        trailers: Metadata::new(),
+    }
+}
+
+/// Returns the deadline of a call, counted from now
+///
+/// Unary calls must finish within both the client's deadline and the server's own
+/// timeout. Streams may be long-lived, so they are only bound by the client's deadline.
+/// Called as soon as a request arrives, so time spent waiting for a dispatch slot
+/// counts against the deadline.
+fn call_deadline(request: &Request, config: &Config) -> Option<Instant> {
+    let now = Instant::now();
+    let client_deadline = request.timeout_ms.map(|ms| now + Duration::from_millis(ms));
+    
+    match request.kind {
+        RequestKind::Unary => {
+            let server_deadline = now + Duration::from_millis(config.timeout_ms);
+            Some(client_deadline.map_or(server_deadline, |deadline| deadline.min(server_deadline)))
+        }
+        _ => client_deadline,
+    }
+}
+
+/// Runs a fallible future, failing with a timeout if the deadline passes first
+async fn with_deadline<T, F>(deadline: Option<Instant>, future: F) -> Result<T, Error>
+where
+    F: Future<Output = Result<T, Error>>,
+{
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, future)
+            .await
+            .map_err(|_| Error::Timeout)?,
+        None => future.await,
+    }
+}
+
+/// Runs a call through its interceptors, dispatches it and sends its responses
+async fn handle_call(
+    mut request: Request,
+    requests: Option<RequestStream>,
+    deadline: Option<Instant>,
+    state: &ServerState,
+    info: &Arc<SessionInfo>,
+    cancellation: CancellationToken,
+    response_tx: &mpsc::Sender<Response>,
+) {
+    let id = request.id;
+    
+    // Let interceptors inspect, change or reject the call
+    let mut intercepted = InterceptedRequest {
+        method: std::mem::take(&mut request.method),
+        metadata: std::mem::take(&mut request.metadata),
+        payload: std::mem::take(&mut request.payload),
+        kind: request.kind,
+        peer_addr: info.peer_addr,
+        identity: info.identity.clone(),
+    };
+    let mut chain = state.interceptor_chain(&intercepted.method).await;
+    let accepted = chain.intercept_request(&mut intercepted).await;
+    request.method = intercepted.method.clone();
+    request.payload = intercepted.payload.clone();
+    
+    // The handler runs with its own call context
+    let context = CallContext::new(
+        id,
+        request.method.clone(),
+        deadline,
+        intercepted.metadata.clone(),
+        intercepted.identity.clone(),
+        info.clone(),
+        cancellation,
+    );
+    
+    // Check the call against the authorization policy, as seen once interceptors have run
+    let accepted = match (accepted, state.policy.read().await.clone()) {
+        (Ok(()), Some(policy)) => policy.authorize(&context),
+        (accepted, _) => accepted,
+    };
+    let intercepted = Arc::new(intercepted);
+    
+    context.clone().scope(async {
+        if request.kind == RequestKind::Unary {
+            let mut result = match accepted {
+                Ok(()) => call_unary(request, &state.services, &context).await,
+                Err(err) => Err(err),
+            };
+            chain.intercept_response(&intercepted, &mut result).await;
+            
+            // The client no longer waits for a cancelled call
+            if context.is_cancelled() {
+                return;
+            }
+            if response_tx.send(unary_response(id, result, &context)).await.is_err() {
+                debug!("Response writer closed before response could be sent");
+            }
+            return;
+        }
+        
+        // Open the response stream of a streaming call
+        let stream = match (accepted, requests) {
+            (Ok(()), Some(requests)) => {
+                open_bidi_stream(request, requests, &state.services, &state.config, deadline).await
+            }
+            (Ok(()), None) => open_response_stream(request, &state.services, &state.config, deadline).await,
+            (Err(err), _) => Err(err),
+        };
+        
+        // Every message passes through the response hooks, as does a failure to open the stream
+        let stream = match stream {
+            Ok(stream) => Ok(chain.intercept_stream(intercepted, stream)),
+            Err(err) => {
+                let mut result = Err(err);
+                chain.intercept_response(&intercepted, &mut result).await;
+                result.map(|payload| -> ResponseStream {
+                    Box::pin(futures_util::stream::once(async move { Ok(payload) }))
+                })
+            }
+        };
+        forward_stream(id, stream, &context, response_tx).await;
+    })
+    .await;
+}
+
+/// Calls the service method of a unary request within the call's deadline
+async fn call_unary(
+    request: Request,
+    services: &RwLock<HashMap<String, Arc<dyn Service>>>,
+    context: &CallContext,
+) -> Result<Bytes, Error> {
+    let (service, method_name) = resolve_method(&request.method, services).await?;
+    with_deadline(context.deadline(), service.call(method_name, request.payload)).await
+}
+
+/// Builds the response of a unary call, which carries both headers and trailers
+fn unary_response(id: u64, result: Result<Bytes, Error>, context: &CallContext) -> Response {
+    let mut response = match result {
+        Ok(payload) => Response {
+            id,
+            payload: Some(payload),
+            error: None,
+            end_of_stream: true,
+            headers: Metadata::new(),
+            trailers: Metadata::new(),
+        },
+        Err(err) => error_response(id, err),
+    };
+    response.headers = context.take_headers();
+    response.trailers = context.take_trailers();
+    response
+}
+
+/// Forwards the messages of a response stream to the client, followed by an end-of-stream trailer
+///
+/// Response headers go out with the first message and trailers with the end-of-stream trailer.
+async fn forward_stream(
+    id: u64,
+    stream: Result<ResponseStream, Error>,
+    context: &CallContext,
+    response_tx: &mpsc::Sender<Response>,
+) {
+    let mut trailer = match stream {
+        Ok(mut stream) => loop {
+            // End the stream with a timeout trailer once the deadline passes
+            let next = async {
+                match context.deadline() {
+                    Some(deadline) => tokio::time::timeout_at(deadline, stream.next())
+                        .await
+                        .unwrap_or(Some(Err(Error::Timeout))),
+                    None => stream.next().await,
+                }
+            };
+            
+            // The client no longer waits for a cancelled call, dropping the stream ends it
+            let next = tokio::select! {
+                next = next => next,
+                _ = context.cancelled() => {
+                    debug!("Stream {} cancelled", id);
+                    return;
+                }
+            };
+            
+            match next {
+                Some(Ok(payload)) => {
+                    let response = Response {
+                        id,
+                        payload: Some(payload),
+                        error: None,
+                        end_of_stream: false,
+                        headers: context.take_headers(),
+                        trailers: Metadata::new(),
+                    };
+                    
+                    // Stop producing once the client has gone away
+                    if response_tx.send(response).await.is_err() {
+                        debug!("Response writer closed, abandoning stream {}", id);
+                        return;
+                    }
+                }
+                Some(Err(err)) => break error_response(id, err),
+                None => break Response {
+                    id,
+                    payload: None,
+                    error: None,
+                    end_of_stream: true,
+                    headers: Metadata::new(),
+                    trailers: Metadata::new(),
+                },
+            }
+        },
+        Err(err) => error_response(id, err),
+    };
+    
+    // Send the end-of-stream trailer, with any headers not sent yet
+    trailer.headers = context.take_headers();
+    trailer.trailers = context.take_trailers();
+    if response_tx.send(trailer).await.is_err() {
+        debug!("Response writer closed before trailer could be sent");
+    }
+}
+
+/// Resolves and opens the response stream for a server-streaming request
+async fn open_response_stream(
+    request: Request,
+    services: &RwLock<HashMap<String, Arc<dyn Service>>>,
+    config: &Config,
+    deadline: Option<Instant>,
+) -> Result<ResponseStream, Error> {
+    let (service, method_name) = resolve_method(&request.method, services).await?;
+    
+    // Opening the stream is also bound by the server's own timeout
+    with_deadline(
+        Some(open_deadline(config, deadline)),
+        service.call_stream(method_name, request.payload),
+    )
+    .await
+}
+
+/// Resolves and opens the response stream for a bidirectional streaming request
+async fn open_bidi_stream(
+    request: Request,
+    requests: RequestStream,
+    services: &RwLock<HashMap<String, Arc<dyn Service>>>,
+    config: &Config,
+    deadline: Option<Instant>,
+) -> Result<ResponseStream, Error> {
+    let (service, method_name) = resolve_method(&request.method, services).await?;
+    
+    // Opening the stream is also bound by the server's own timeout
+    with_deadline(
+        Some(open_deadline(config, deadline)),
+        service.call_bidi(method_name, requests),
+    )
+    .await
+}
+
+/// Returns the deadline for opening a stream, the earlier of the call deadline and the server's timeout
+fn open_deadline(config: &Config, deadline: Option<Instant>) -> Instant {
+    let server_deadline = Instant::now() + Duration::from_millis(config.timeout_ms);
+    deadline.map_or(server_deadline, |deadline| deadline.min(server_deadline))
+}
@@ -0,0 +1,306 @@
+use std::fmt;
+
+use bytes::Bytes;
+use serde::de::{Deserializer, Error as _};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Status codes carried by failed responses
+///
+/// The codes and their numeric values follow gRPC's, so they map one-to-one
+/// onto statuses of other RPC systems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatusCode {
+    /// Not an error
+    Ok = 0,
+    /// The call was cancelled, typically by the caller
+    Cancelled = 1,
+    /// Unknown error
+    Unknown = 2,
+    /// The client specified an invalid argument
+    InvalidArgument = 3,
+    /// The deadline expired before the call could complete
+    DeadlineExceeded = 4,
+    /// A requested entity was not found
+    NotFound = 5,
+    /// An entity the client attempted to create already exists
+    AlreadyExists = 6,
+    /// The caller does not have permission to execute the call
+    PermissionDenied = 7,
+    /// Some resource has been exhausted, such as a quota or the server's capacity
+    ResourceExhausted = 8,
+    /// The system is not in a state required for the call's execution
+    FailedPrecondition = 9,
+    /// The call was aborted, typically due to a concurrency issue
+    Aborted = 10,
+    /// The call was attempted past the valid range
+    OutOfRange = 11,
+    /// The method is not implemented or not supported
+    Unimplemented = 12,
+    /// Internal error
+    Internal = 13,
+    /// The service is currently unavailable
+    Unavailable = 14,
+    /// Unrecoverable data loss or corruption
+    DataLoss = 15,
+    /// The request does not have valid authentication credentials
+    Unauthenticated = 16,
+}
+
+impl StatusCode {
+    /// Returns the code with the given numeric value, or `Unknown` if there is none
+    pub fn from_u32(value: u32) -> Self {
+        match value {
+            0 => StatusCode::Ok,
+            1 => StatusCode::Cancelled,
+            3 => StatusCode::InvalidArgument,
+            4 => StatusCode::DeadlineExceeded,
+            5 => StatusCode::NotFound,
+            6 => StatusCode::AlreadyExists,
+            7 => StatusCode::PermissionDenied,
+            8 => StatusCode::ResourceExhausted,
+            9 => StatusCode::FailedPrecondition,
+            10 => StatusCode::Aborted,
+            11 => StatusCode::OutOfRange,
+            12 => StatusCode::Unimplemented,
+            13 => StatusCode::Internal,
+            14 => StatusCode::Unavailable,
+            15 => StatusCode::DataLoss,
+            16 => StatusCode::Unauthenticated,
+            _ => StatusCode::Unknown,
+        }
+    }
+    
+    /// Returns the numeric value of the code
+    pub fn as_u32(self) -> u32 {
+        self as u32
+    }
+}
+
+impl Default for StatusCode {
+    fn default() -> Self {
+        StatusCode::Unknown
+    }
+}
+
+impl fmt::Display for StatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            StatusCode::Ok => "ok",
+            StatusCode::Cancelled => "cancelled",
+            StatusCode::Unknown => "unknown",
+            StatusCode::InvalidArgument => "invalid argument",
+            StatusCode::DeadlineExceeded => "deadline exceeded",
+            StatusCode::NotFound => "not found",
+            StatusCode::AlreadyExists => "already exists",
+            StatusCode::PermissionDenied => "permission denied",
+            StatusCode::ResourceExhausted => "resource exhausted",
+            StatusCode::FailedPrecondition => "failed precondition",
+            StatusCode::Aborted => "aborted",
+            StatusCode::OutOfRange => "out of range",
+            StatusCode::Unimplemented => "unimplemented",
+            StatusCode::Internal => "internal",
+            StatusCode::Unavailable => "unavailable",
+            StatusCode::DataLoss => "data loss",
+            StatusCode::Unauthenticated => "unauthenticated",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Codes travel as their numeric value in every format
+impl Serialize for StatusCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.as_u32())
+    }
+}
+
+impl<'de> Deserialize<'de> for StatusCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = u64::deserialize(deserializer)?;
+        let value = u32::try_from(value)
+            .map_err(|_| D::Error::custom(format!("Invalid status code: {}", value)))?;
+        Ok(StatusCode::from_u32(value))
+    }
+}
+
+/// Status of a failed call, as sent on the wire
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    /// Status code
+    pub code: StatusCode,
+    /// Human-readable error message
+    #[serde(default)]
+    pub message: String,
+    /// Application-defined binary error details
+    #[serde(default, skip_serializing_if = "Bytes::is_empty")]
+    pub details: Bytes,
+}
+
+impl Status {
+    /// Creates a new Status
+    pub fn new(code: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: Bytes::new(),
+        }
+    }
+    
+    /// Attaches binary error details to the status
+    pub fn with_details(mut self, details: impl Into<Bytes>) -> Self {
+        self.details = details.into();
+        self
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.message.is_empty() {
+            write!(f, "{}", self.code)
+        } else {
+            write!(f, "{}: {}", self.code, self.message)
+        }
+    }
+}
+
+impl std::error::Error for Status {}
+
+impl From<&Error> for Status {
+    fn from(err: &Error) -> Self {
+        match err {
+            Error::Rpc(status) => status.clone(),
+            #[allow(deprecated)]
+            Error::RpcFailed(message) => Status::new(StatusCode::Unknown, message.clone()),
+            Error::MethodNotFound(method) => Status::new(StatusCode::Unimplemented, method.clone()),
+            Error::Timeout => Status::new(StatusCode::DeadlineExceeded, err.to_string()),
+            Error::Cancelled => Status::new(StatusCode::Cancelled, err.to_string()),
+            Error::NotFound(message) => Status::new(StatusCode::NotFound, message.clone()),
+            Error::InvalidArgument(message) => Status::new(StatusCode::InvalidArgument, message.clone()),
+            Error::PermissionDenied(message) => Status::new(StatusCode::PermissionDenied, message.clone()),
+            Error::ResourceExhausted(message) => Status::new(StatusCode::ResourceExhausted, message.clone()),
+            Error::Unavailable(message) => Status::new(StatusCode::Unavailable, message.clone()),
+            Error::Internal(message) => Status::new(StatusCode::Internal, message.clone()),
+            Error::AuthenticationFailed(message) => Status::new(StatusCode::Unauthenticated, message.clone()),
+            Error::ConnectionClosed => Status::new(StatusCode::Unavailable, err.to_string()),
+            Error::Deserialization(_) | Error::Decoding(_) => Status::new(StatusCode::InvalidArgument, err.to_string()),
+            Error::Serialization(_) | Error::Encoding(_) => Status::new(StatusCode::Internal, err.to_string()),
+            _ => Status::new(StatusCode::Unknown, err.to_string()),
+        }
+    }
+}
+
+impl From<Error> for Status {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Rpc(status) => status,
+            err => Status::from(&err),
+        }
+    }
+}
+
+impl From<Status> for Error {
+    /// Rebuilds the error matching a status received from the server
+    ///
+    /// Statuses carrying details, or whose code has no dedicated variant, are kept
+    /// whole as [`Error::Rpc`].
+    fn from(status: Status) -> Self {
+        if !status.details.is_empty() {
+            return Error::Rpc(status);
+        }
+        
+        match status.code {
+            StatusCode::Unimplemented => Error::MethodNotFound(status.message),
+            StatusCode::DeadlineExceeded => Error::Timeout,
+            StatusCode::Cancelled => Error::Cancelled,
+            StatusCode::NotFound => Error::NotFound(status.message),
+            StatusCode::InvalidArgument => Error::InvalidArgument(status.message),
+            StatusCode::PermissionDenied => Error::PermissionDenied(status.message),
+            StatusCode::ResourceExhausted => Error::ResourceExhausted(status.message),
+            StatusCode::Unavailable => Error::Unavailable(status.message),
+            StatusCode::Internal => Error::Internal(status.message),
+            StatusCode::Unauthenticated => Error::AuthenticationFailed(status.message),
+            _ => Error::Rpc(status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn code_round_trips_through_its_numeric_value() {
+        for value in 0..=16 {
+            assert_eq!(StatusCode::from_u32(value).as_u32(), value);
+        }
+        assert_eq!(StatusCode::from_u32(17), StatusCode::Unknown);
+    }
+    
+    #[test]
+    fn errors_map_to_matching_codes() {
+        let cases = [
+            (Error::MethodNotFound("echo.missing".to_string()), StatusCode::Unimplemented),
+            (Error::Timeout, StatusCode::DeadlineExceeded),
+            (Error::Cancelled, StatusCode::Cancelled),
+            (Error::NotFound("user".to_string()), StatusCode::NotFound),
+            (Error::InvalidArgument("bad".to_string()), StatusCode::InvalidArgument),
+            (Error::PermissionDenied("no".to_string()), StatusCode::PermissionDenied),
+            (Error::ResourceExhausted("full".to_string()), StatusCode::ResourceExhausted),
+            (Error::Unavailable("down".to_string()), StatusCode::Unavailable),
+            (Error::Internal("bug".to_string()), StatusCode::Internal),
+            (Error::AuthenticationFailed("who".to_string()), StatusCode::Unauthenticated),
+            (Error::ConnectionClosed, StatusCode::Unavailable),
+            (Error::Other("other".to_string()), StatusCode::Unknown),
+        ];
+        
+        for (err, code) in cases {
+            assert_eq!(Status::from(err).code, code);
+        }
+    }
+    
+    #[test]
+    fn statuses_rebuild_matching_errors() {
+        let err = Error::from(Status::new(StatusCode::NotFound, "user"));
+        assert!(matches!(err, Error::NotFound(message) if message == "user"));
+        
+        let err = Error::from(Status::new(StatusCode::DeadlineExceeded, "late"));
+        assert!(matches!(err, Error::Timeout));
+        
+        let err = Error::from(Status::new(StatusCode::Unauthenticated, "who"));
+        assert!(matches!(err, Error::AuthenticationFailed(message) if message == "who"));
+    }
+    
+    #[test]
+    fn statuses_without_dedicated_variant_are_kept_whole() {
+        let status = Status::new(StatusCode::AlreadyExists, "user");
+        let err = Error::from(status.clone());
+        assert!(matches!(&err, Error::Rpc(kept) if *kept == status));
+        assert_eq!(Status::from(err), status);
+    }
+    
+    #[test]
+    fn statuses_with_details_are_kept_whole() {
+        let status = Status::new(StatusCode::InvalidArgument, "bad").with_details(vec![1, 2, 3]);
+        let err = Error::from(status.clone());
+        assert!(matches!(&err, Error::Rpc(kept) if *kept == status));
+    }
+    
+    #[test]
+    fn message_survives_a_round_trip() {
+        let status = Status::from(Error::PermissionDenied("admins only".to_string()));
+        assert_eq!(status.message, "admins only");
+        assert!(matches!(Error::from(status), Error::PermissionDenied(message) if message == "admins only"));
+    }
+    
+    #[test]
+    fn code_serializes_as_its_numeric_value() {
+        let json = serde_json::to_value(Status::new(StatusCode::NotFound, "user")).unwrap();
+        assert_eq!(json, serde_json::json!({ "code": 5, "message": "user" }));
+        
+        let status: Status = serde_json::from_value(json).unwrap();
+        assert_eq!(status.code, StatusCode::NotFound);
+    }
+}
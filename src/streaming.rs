@@ -0,0 +1,288 @@
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use log::debug;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::metadata::Metadata;
+use crate::transport::{MessageReader, MessageWriter, CALL_CANCELLED_CODE};
+use crate::{Request, RequestKind, RequestStream, Response, ResponseStream, SerializationFormat, TypedStream};
+
+/// Typed stream of messages returned by a streaming call
+///
+/// The stream ends after the server's end-of-stream trailer. If the trailer
+/// carries an error, it is yielded as the final item. Dropping the stream
+/// before it has ended resets the underlying QUIC stream, cancelling the call
+/// on the server.
+pub struct CallStream<R> {
+    /// Reading half of the call's stream
+    reader: MessageReader,
+    /// Writing half of the call's stream, kept open until the trailer so the call can be cancelled
+    writer: Option<MessageWriter>,
+    /// Serialization format
+    format: SerializationFormat,
+    /// Whether the end-of-stream trailer has been received
+    finished: bool,
+    /// Response headers received so far
+    headers: Metadata,
+    /// Response trailers, available once the stream has ended
+    trailers: Metadata,
+    /// Phantom data to use the type parameter
+    _marker: PhantomData<fn() -> R>,
+}
+
+impl<R> CallStream<R> {
+    /// Creates a new CallStream over the halves of a per-call stream
+    pub(crate) fn new(
+        reader: MessageReader,
+        writer: Option<MessageWriter>,
+        format: SerializationFormat,
+    ) -> Self {
+        Self {
+            reader,
+            writer,
+            format,
+            finished: false,
+            headers: Metadata::new(),
+            trailers: Metadata::new(),
+            _marker: PhantomData,
+        }
+    }
+    
+    /// Returns the response headers sent by the server
+    pub fn headers(&self) -> &Metadata {
+        &self.headers
+    }
+    
+    /// Returns the response trailers sent by the server, once the stream has ended
+    pub fn trailers(&self) -> &Metadata {
+        &self.trailers
+    }
+    
+    /// Cancels the call, resetting its stream
+    pub fn cancel(mut self) {
+        self.reset();
+    }
+    
+    /// Resets both directions of the stream if the call has not finished
+    fn reset(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        
+        debug!("Cancelling streaming call");
+        if let Some(writer) = self.writer.as_mut() {
+            writer.reset(CALL_CANCELLED_CODE);
+        }
+        self.reader.stop(CALL_CANCELLED_CODE);
+    }
+}
+
+impl<R> Stream for CallStream<R>
+where
+    R: DeserializeOwned + prost::Message + Default,
+{
+    type Item = Result<R, Error>;
+    
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.finished {
+            return Poll::Ready(None);
+        }
+        
+        // Messages without a payload carry only metadata, so keep reading past them
+        loop {
+            let response_bytes = match self.reader.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(bytes))) => bytes,
+                Poll::Ready(Some(Err(e))) => {
+                    self.finished = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+                Poll::Ready(None) => {
+                    // The server closed the stream without sending a trailer
+                    self.finished = true;
+                    return Poll::Ready(Some(Err(Error::ConnectionClosed)));
+                }
+                Poll::Pending => return Poll::Pending,
+            };
+            
+            // Deserialize response
+            let response: Response = match crate::deserialize(&response_bytes, self.format) {
+                Ok(response) => response,
+                Err(e) => {
+                    self.reset();
+                    return Poll::Ready(Some(Err(e)));
+                }
+            };
+            
+            self.headers.merge(response.headers);
+            self.trailers.merge(response.trailers);
+            if response.end_of_stream {
+                // The call is over, dropping the writing half finishes it so the server stops reading
+                self.finished = true;
+                self.writer.take();
+            }
+            
+            // Error trailer
+            if let Some(status) = response.error {
+                self.finished = true;
+                return Poll::Ready(Some(Err(Error::from(status))));
+            }
+            
+            match response.payload {
+                Some(payload) => return Poll::Ready(Some(crate::deserialize(&payload, self.format))),
+                None if self.finished => return Poll::Ready(None),
+                None => continue,
+            }
+        }
+    }
+}
+
+impl<R> Drop for CallStream<R> {
+    fn drop(&mut self) {
+        self.reset();
+    }
+}
+
+/// Typed sink for the messages a client sends on a streaming call
+///
+/// Closing the sink sends an end-of-stream marker and half-closes the client's
+/// side of the call, while replies keep arriving on the matching [`CallStream`].
+/// Dropping the sink without closing it resets the stream, cancelling the call.
+pub struct CallSink<T> {
+    /// Request ID of the call
+    id: u64,
+    /// Writing half of the call's stream
+    writer: MessageWriter,
+    /// Serialization format
+    format: SerializationFormat,
+    /// Whether the end-of-stream marker has been queued
+    end_sent: bool,
+    /// Whether the sink has been closed
+    closed: bool,
+    /// Phantom data to use the type parameter
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T> CallSink<T> {
+    /// Creates a new CallSink over the writing half of a per-call stream
+    pub(crate) fn new(id: u64, writer: MessageWriter, format: SerializationFormat) -> Self {
+        Self {
+            id,
+            writer,
+            format,
+            end_sent: false,
+            closed: false,
+            _marker: PhantomData,
+        }
+    }
+    
+    /// Cancels the call, resetting its stream
+    pub fn cancel(mut self) {
+        self.reset();
+    }
+    
+    /// Serializes a request frame for this call
+    fn frame(&self, payload: bytes::Bytes, end_of_stream: bool) -> Result<bytes::Bytes, Error> {
+        let request = Request {
+            id: self.id,
+            method: String::new(),
+            payload,
+            kind: RequestKind::BidiStreaming,
+            end_of_stream,
+            timeout_ms: None,
+            metadata: Metadata::new(),
+        };
+        crate::serialize(&request, self.format)
+    }
+    
+    /// Resets the stream if the sink has not been closed
+    fn reset(&mut self) {
+        if self.closed {
+            return;
+        }
+        self.closed = true;
+        
+        debug!("Cancelling streaming call {}", self.id);
+        self.writer.reset(CALL_CANCELLED_CODE);
+    }
+}
+
+impl<T> Sink<T> for CallSink<T>
+where
+    T: Serialize + prost::Message,
+{
+    type Error = Error;
+    
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.end_sent {
+            return Poll::Ready(Err(Error::ConnectionClosed));
+        }
+        self.writer.poll_ready_unpin(cx)
+    }
+    
+    fn start_send(mut self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        let payload = crate::serialize(&item, self.format)?;
+        let frame = self.frame(payload, false)?;
+        self.writer.start_send_unpin(frame)
+    }
+    
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.writer.poll_flush_unpin(cx)
+    }
+    
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Queue the end-of-stream marker before finishing the stream
+        if !self.end_sent {
+            match self.writer.poll_ready_unpin(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+            let frame = self.frame(bytes::Bytes::new(), true)?;
+            self.writer.start_send_unpin(frame)?;
+            self.end_sent = true;
+        }
+        
+        let result = self.writer.poll_close_unpin(cx);
+        if result.is_ready() {
+            self.closed = true;
+        }
+        result
+    }
+}
+
+impl<T> Drop for CallSink<T> {
+    fn drop(&mut self) {
+        self.reset();
+    }
+}
+
+/// Serializes each message of a typed stream, for returning it from a [`Service`](crate::Service)
+pub fn encode_stream<T>(messages: TypedStream<T>, format: SerializationFormat) -> ResponseStream
+where
+    T: Serialize + prost::Message + 'static,
+{
+    Box::pin(messages.map(move |message| crate::serialize(&message?, format)))
+}
+
+/// Serializes a single message as a one-message response stream
+pub fn encode_single<T>(message: &T, format: SerializationFormat) -> Result<ResponseStream, Error>
+where
+    T: Serialize + prost::Message,
+{
+    let message = crate::serialize(message, format)?;
+    Ok(Box::pin(futures_util::stream::once(async move { Ok(message) })))
+}
+
+/// Deserializes each message of a request stream received by a [`Service`](crate::Service)
+pub fn decode_stream<T>(messages: RequestStream, format: SerializationFormat) -> TypedStream<T>
+where
+    T: DeserializeOwned + prost::Message + Default + 'static,
+{
+    Box::pin(messages.map(move |message| crate::deserialize(&message?, format)))
+}
@@ -0,0 +1,244 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use tower::util::ServiceExt;
+use tower::BoxError;
+
+use crate::client::{CallOptions, CallResponse, Client};
+use crate::context::CallContext;
+use crate::error::Error;
+use crate::metadata::Metadata;
+use crate::server::Server;
+use crate::Service;
+
+/// Unary call as seen by `tower` services
+///
+/// The same type is used on both sides, so a [`Client`] can itself be registered on a
+/// [`Server`] through [`TowerService`], for example to build a proxy.
+#[derive(Debug, Clone)]
+pub struct RpcRequest {
+    /// Full `service.method` name of the call
+    pub method: String,
+    /// Serialized payload
+    pub payload: Bytes,
+    /// Call metadata
+    pub metadata: Metadata,
+    /// Time left to complete the call, if bounded
+    pub timeout: Option<Duration>,
+}
+
+impl RpcRequest {
+    /// Creates a new RpcRequest
+    pub fn new(method: impl Into<String>, payload: impl Into<Bytes>) -> Self {
+        Self {
+            method: method.into(),
+            payload: payload.into(),
+            metadata: Metadata::new(),
+            timeout: None,
+        }
+    }
+    
+    /// Adds a metadata entry to the request
+    ///
+    /// Fails if the value isn't valid UTF-8 and the key doesn't end in `-bin`.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<Bytes>) -> Result<Self, Error> {
+        self.metadata.insert(key, value)?;
+        Ok(self)
+    }
+    
+    /// Sets the timeout of the request
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl tower::Service<RpcRequest> for Client {
+    type Response = CallResponse<Bytes>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Calls are multiplexed over the connection, so the client is always ready
+        Poll::Ready(Ok(()))
+    }
+    
+    fn call(&mut self, request: RpcRequest) -> Self::Future {
+        let client = self.clone();
+        let options = CallOptions {
+            timeout: request.timeout,
+            metadata: request.metadata,
+        };
+        Box::pin(async move { client.call_raw(&request.method, request.payload, options).await })
+    }
+}
+
+/// Adapter registering a `tower::Service` as a QuicServe service
+///
+/// Only unary calls are supported. Each call is made on a clone of the inner service,
+/// as is customary for `tower`. Headers and trailers of the response are sent back
+/// to the client.
+///
+/// The inner service is kept behind a mutex so it doesn't have to be `Sync`, which
+/// boxed services such as `BoxCloneService` aren't.
+pub struct TowerService<S> {
+    /// Inner tower service, only locked to clone it
+    inner: Mutex<S>,
+    /// Names of the methods served
+    methods: Vec<String>,
+}
+
+impl<S> TowerService<S> {
+    /// Creates a new TowerService serving the given methods
+    pub fn new<I, M>(inner: S, methods: I) -> Self
+    where
+        I: IntoIterator<Item = M>,
+        M: Into<String>,
+    {
+        Self {
+            inner: Mutex::new(inner),
+            methods: methods.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl<S: Clone> Clone for TowerService<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Mutex::new(self.inner.lock().unwrap().clone()),
+            methods: self.methods.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> Service for TowerService<S>
+where
+    S: tower::Service<RpcRequest> + Clone + Send + 'static,
+    S::Response: Into<CallResponse<Bytes>>,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+{
+    async fn call(&self, method: &str, payload: Bytes) -> Result<Bytes, Error> {
+        // Rebuild the request from the current call
+        let context = CallContext::current();
+        let request = RpcRequest {
+            method: context.as_ref().map_or_else(|| method.to_string(), |context| context.method().to_string()),
+            payload,
+            metadata: context.as_ref().map(|context| context.metadata().clone()).unwrap_or_default(),
+            timeout: context.as_ref().and_then(|context| context.remaining()),
+        };
+        
+        // Wait for the service to be ready, then call it
+        let inner = self.inner.lock().unwrap().clone();
+        let response: CallResponse<Bytes> = inner.oneshot(request).await
+            .map_err(|e| tower_error(e.into()))?
+            .into();
+        
+        // Pass the response metadata on to the client
+        if let Some(context) = context {
+            for (key, value) in &response.headers {
+                context.set_header(key.clone(), value.clone())?;
+            }
+            for (key, value) in &response.trailers {
+                context.set_trailer(key.clone(), value.clone())?;
+            }
+        }
+        
+        Ok(response.message)
+    }
+    
+    fn methods(&self) -> Vec<String> {
+        self.methods.clone()
+    }
+}
+
+/// Converts an error returned by a tower service or layer into an [`Error`]
+fn tower_error(err: BoxError) -> Error {
+    let err = match err.downcast::<Error>() {
+        Ok(err) => return *err,
+        Err(err) => err,
+    };
+    if err.is::<tower::timeout::error::Elapsed>() {
+        return Error::Timeout;
+    }
+    if err.is::<tower::load_shed::error::Overloaded>() {
+        return Error::ResourceExhausted(err.to_string());
+    }
+    Error::Internal(err.to_string())
+}
+
+impl Server {
+    /// Registers a `tower::Service` serving the given methods
+    pub async fn register_tower_service<S, I, M>(&self, name: &str, service: S, methods: I) -> Result<(), Error>
+    where
+        S: tower::Service<RpcRequest> + Clone + Send + 'static,
+        S::Response: Into<CallResponse<Bytes>>,
+        S::Error: Into<BoxError>,
+        S::Future: Send,
+        I: IntoIterator<Item = M>,
+        M: Into<String>,
+    {
+        self.register_service(name, TowerService::new(service, methods)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn calls_outside_a_context_use_the_method_name() {
+        let inner = tower::service_fn(|request: RpcRequest| async move {
+            assert_eq!(request.method, "forward");
+            assert!(request.metadata.is_empty());
+            assert!(request.timeout.is_none());
+            Ok::<_, Error>(CallResponse::from(request.payload))
+        });
+        let service = TowerService::new(inner, ["forward"]);
+        assert_eq!(service.call("forward", Bytes::from("ping")).await.unwrap(), Bytes::from("ping"));
+    }
+    
+    #[tokio::test]
+    async fn layer_timeouts_fail_with_timeout() {
+        let inner = tower::service_fn(|_request: RpcRequest| std::future::pending::<Result<CallResponse<Bytes>, Error>>());
+        let service = TowerService::new(tower::timeout::Timeout::new(inner, Duration::from_millis(10)), ["forward"]);
+        assert!(matches!(service.call("forward", Bytes::new()).await, Err(Error::Timeout)));
+    }
+    
+    #[tokio::test]
+    async fn boxed_services_can_be_served() {
+        let inner = tower::service_fn(|request: RpcRequest| async move {
+            Ok::<_, Error>(CallResponse::from(request.payload))
+        });
+        let service = TowerService::new(tower::util::BoxCloneService::new(inner), ["forward"]);
+        assert_eq!(service.call("forward", Bytes::from("ping")).await.unwrap(), Bytes::from("ping"));
+    }
+    
+    #[test]
+    fn tower_errors_map_to_rpc_errors() {
+        assert!(matches!(tower_error(Box::new(tower::timeout::error::Elapsed::new())), Error::Timeout));
+        assert!(matches!(tower_error(Box::new(tower::load_shed::error::Overloaded::new())), Error::ResourceExhausted(_)));
+        assert!(matches!(tower_error(Box::new(Error::NotFound("key".to_string()))), Error::NotFound(key) if key == "key"));
+        assert!(matches!(tower_error("broken".into()), Error::Internal(message) if message == "broken"));
+    }
+    
+    #[test]
+    fn request_builders_set_metadata_and_timeout() {
+        let request = RpcRequest::new("service.method", "payload")
+            .with_metadata("trace-id", "abc")
+            .unwrap()
+            .with_timeout(Duration::from_secs(1));
+        assert_eq!(request.payload, Bytes::from("payload"));
+        assert_eq!(request.metadata.get_str("trace-id"), Some("abc"));
+        assert_eq!(request.timeout, Some(Duration::from_secs(1)));
+        
+        let invalid = RpcRequest::new("service.method", Bytes::new()).with_metadata("trace-id", vec![0xff]);
+        assert!(invalid.is_err());
+    }
+}
@@ -1,11 +1,28 @@
 use bytes::{Buf, BufMut, Bytes, BytesMut};
-use futures_util::{SinkExt, StreamExt};
-use h3_webtransport::session::SendStream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use h3::quic;
+use h3_webtransport::session::{RecvStream, SendStream};
 use tokio::io::{AsyncRead, AsyncWrite};
-use tokio_util::codec::{Decoder, Encoder, Framed, LengthDelimitedCodec};
+use tokio_util::codec::{Decoder, Encoder, Framed, FramedRead, FramedWrite, LengthDelimitedCodec};
 
 use crate::error::Error;
 
+/// Application error code used when a stream is reset because its call was cancelled
+pub const CALL_CANCELLED_CODE: u64 = 0x5143_0001;
+
+/// Builds the length-delimited codec used for message framing
+fn message_codec() -> LengthDelimitedCodec {
+    LengthDelimitedCodec::builder()
+        .length_field_offset(0)
+        .length_field_length(4)
+        .length_adjustment(0)
+        .max_frame_length(16 * 1024 * 1024) // 16MB max message size
+        .new_codec()
+}
+
 /// Message-oriented stream for bidirectional communication
 pub struct MessageStream {
     /// Framed stream for reading and writing length-delimited messages
@@ -15,16 +32,8 @@ pub struct MessageStream {
 impl MessageStream {
     /// Creates a new MessageStream from a WebTransport bidirectional stream
     pub fn new(stream: h3_webtransport::session::BidiStream) -> Self {
-        // Configure length-delimited codec for message framing
-        let codec = LengthDelimitedCodec::builder()
-            .length_field_offset(0)
-            .length_field_length(4)
-            .length_adjustment(0)
-            .max_frame_length(16 * 1024 * 1024) // 16MB max message size
-            .new_codec();
-        
         Self {
-            framed: Framed::new(stream, codec),
+            framed: Framed::new(stream, message_codec()),
         }
     }
     
@@ -42,6 +51,113 @@ impl MessageStream {
             None => Ok(None),
         }
     }
+    
+    /// Finishes the sending side of the stream, signalling that no more messages follow
+    pub async fn finish(&mut self) -> Result<(), Error> {
+        self.framed.close().await
+            .map_err(|e| Error::WebTransport(format!("Failed to finish stream: {}", e)))
+    }
+    
+    /// Abruptly terminates the sending side of the stream, discarding any unsent messages
+    pub fn reset(&mut self, code: u64) {
+        quic::SendStream::reset(self.framed.get_mut(), code);
+    }
+    
+    /// Asks the peer to stop sending on the receiving side of the stream
+    pub fn stop(&mut self, code: u64) {
+        quic::RecvStream::stop_sending(self.framed.get_mut(), code);
+    }
+    
+    /// Splits the stream into independent reading and writing halves
+    ///
+    /// Must be called before any message is received, as buffered data is not carried over.
+    pub fn split(self) -> (MessageReader, MessageWriter) {
+        let (send, recv) = quic::BidiStream::split(self.framed.into_inner());
+        (
+            MessageReader { framed: FramedRead::new(recv, message_codec()) },
+            MessageWriter { framed: FramedWrite::new(send, message_codec()) },
+        )
+    }
+}
+
+/// Reading half of a [`MessageStream`]
+pub struct MessageReader {
+    /// Framed stream for reading length-delimited messages
+    framed: FramedRead<RecvStream, LengthDelimitedCodec>,
+}
+
+impl MessageReader {
+    /// Receives a message from the stream
+    pub async fn receive(&mut self) -> Result<Option<Bytes>, Error> {
+        self.next().await.transpose()
+    }
+    
+    /// Asks the peer to stop sending on this stream
+    pub fn stop(&mut self, code: u64) {
+        quic::RecvStream::stop_sending(self.framed.get_mut(), code);
+    }
+}
+
+impl Stream for MessageReader {
+    type Item = Result<Bytes, Error>;
+    
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.framed.poll_next_unpin(cx) {
+            Poll::Ready(Some(Ok(bytes))) => Poll::Ready(Some(Ok(bytes.freeze()))),
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(Error::WebTransport(format!("Failed to receive message: {}", e))))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Writing half of a [`MessageStream`]
+pub struct MessageWriter {
+    /// Framed stream for writing length-delimited messages
+    framed: FramedWrite<SendStream, LengthDelimitedCodec>,
+}
+
+impl MessageWriter {
+    /// Sends a message over the stream
+    pub async fn send(&mut self, bytes: Bytes) -> Result<(), Error> {
+        self.framed.send(bytes).await
+            .map_err(|e| Error::WebTransport(format!("Failed to send message: {}", e)))
+    }
+    
+    /// Finishes the stream, signalling that no more messages follow
+    pub async fn finish(&mut self) -> Result<(), Error> {
+        self.framed.close().await
+            .map_err(|e| Error::WebTransport(format!("Failed to finish stream: {}", e)))
+    }
+    
+    /// Abruptly terminates the stream, discarding any unsent messages
+    pub fn reset(&mut self, code: u64) {
+        quic::SendStream::reset(self.framed.get_mut(), code);
+    }
+}
+
+impl Sink<Bytes> for MessageWriter {
+    type Error = Error;
+    
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.framed.poll_ready_unpin(cx)
+            .map_err(|e| Error::WebTransport(format!("Failed to send message: {}", e)))
+    }
+    
+    fn start_send(mut self: Pin<&mut Self>, item: Bytes) -> Result<(), Self::Error> {
+        self.framed.start_send_unpin(item)
+            .map_err(|e| Error::WebTransport(format!("Failed to send message: {}", e)))
+    }
+    
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.framed.poll_flush_unpin(cx)
+            .map_err(|e| Error::WebTransport(format!("Failed to send message: {}", e)))
+    }
+    
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.framed.poll_close_unpin(cx)
+            .map_err(|e| Error::WebTransport(format!("Failed to finish stream: {}", e)))
+    }
 }
 
 /// Codec for Protocol Buffers messages
@@ -7,11 +7,11 @@ use anyhow::Result;
 use futures_util::future::Future;
 use libc::rand;
 use log::{debug, error, info, warn};
-use rustls::Certificate;
+use rustls::pki_types::CertificateDer;
 use tokio::time;
 
 use crate::error::Error;
-use crate::SerializationFormat;
+use crate::{ClientAuthMode, SerializationFormat, StreamMode};
 
 /// Reads a PEM certificate file and returns the certificate data
 pub fn read_certificate_file(path: &Path) -> Result<Vec<u8>, Error> {
@@ -26,10 +26,10 @@ pub fn read_certificate_file(path: &Path) -> Result<Vec<u8>, Error> {
 }
 
 /// Parses a PEM certificate into rustls certificates
-pub fn parse_certificates(cert_data: &[u8]) -> Result<Vec<Certificate>, Error> {
+pub fn parse_certificates(cert_data: &[u8]) -> Result<Vec<CertificateDer<'static>>, Error> {
     rustls_pemfile::certs(&mut cert_data.as_ref())
+        .collect::<Result<Vec<_>, _>>()
         .map_err(|e| Error::CertificateError(format!("Failed to parse certificate: {}", e)))
-        .map(|certs| certs.into_iter().map(Certificate).collect())
 }
 
 /// Parses a serialization format from a string
@@ -41,6 +41,25 @@ pub fn parse_format(format_str: &str) -> Result<SerializationFormat, Error> {
     }
 }
 
+/// Parses a stream mode from a string
+pub fn parse_stream_mode(mode_str: &str) -> Result<StreamMode, Error> {
+    match mode_str.to_lowercase().as_str() {
+        "shared" => Ok(StreamMode::Shared),
+        "per-call" | "percall" => Ok(StreamMode::PerCall),
+        _ => Err(Error::InvalidConfig(format!("Unknown stream mode: {}", mode_str))),
+    }
+}
+
+/// Parses a client authentication mode from a string
+pub fn parse_client_auth(mode_str: &str) -> Result<ClientAuthMode, Error> {
+    match mode_str.to_lowercase().as_str() {
+        "none" => Ok(ClientAuthMode::None),
+        "optional" => Ok(ClientAuthMode::Optional),
+        "required" => Ok(ClientAuthMode::Required),
+        _ => Err(Error::InvalidConfig(format!("Unknown client auth mode: {}", mode_str))),
+    }
+}
+
 /// Executes a future with retry logic
 pub async fn retry_with_backoff<F, Fut, T>(
     f: F,
@@ -99,6 +118,36 @@ pub fn format_method_name(service: &str, method: &str) -> String {
     format!("{}.{}", service, method)
 }
 
+/// Matches text against a glob pattern
+///
+/// `*` matches any run of characters, including none, and `?` matches any single character.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            // Let the star match nothing for now, retrying with one more character on mismatch
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    
+    // Trailing stars match the empty rest of the text
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 /// Checks if a bidirectional QUIC stream is still alive
 pub async fn check_stream_alive(stream: &mut h3_webtransport::session::BidiStream) -> bool {
     // This is a minimal ping implementation - just write and read a small message
@@ -185,4 +234,42 @@ pub fn parse_socket_addr(addr: &str, default_port: u16) -> Result<std::net::Sock
             .parse()
             .map_err(|e| Error::InvalidConfig(format!("Invalid socket address: {}", e)))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn glob_matches_literals() {
+        assert!(glob_match("echo.echo", "echo.echo"));
+        assert!(!glob_match("echo.echo", "echo.echoes"));
+        assert!(!glob_match("echo.echo", "echo"));
+    }
+    
+    #[test]
+    fn glob_star_matches_any_run() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("echo.*", "echo."));
+        assert!(glob_match("echo.*", "echo.stream"));
+        assert!(glob_match("*.reset", "admin.reset"));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(glob_match("a*b", "abab"));
+        assert!(!glob_match("echo.*", "admin.echo"));
+        assert!(!glob_match("a*b*c", "aXXbYY"));
+    }
+    
+    #[test]
+    fn glob_question_mark_matches_one_character() {
+        assert!(glob_match("v?", "v1"));
+        assert!(!glob_match("v?", "v"));
+        assert!(!glob_match("v?", "v10"));
+        assert!(glob_match("??*", "ab"));
+    }
+    
+    #[test]
+    fn glob_matches_characters_not_bytes() {
+        assert!(glob_match("caf?", "café"));
+    }
+}
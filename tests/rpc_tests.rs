@@ -0,0 +1,1395 @@
+//! End-to-end tests running a server and its clients over the loopback interface
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use quicserve::apikey::{generate_api_key, ApiKeyEntry, ApiKeyFile};
+use quicserve::auth::StaticTokenAuthenticator;
+use quicserve::config::Config;
+use quicserve::health::{HealthCheckRequest, HealthCheckResponse};
+use quicserve::reflection::{
+    DescribeServiceRequest, DescribeServiceResponse, ListServicesRequest, ListServicesResponse, FILE_DESCRIPTOR_SET,
+};
+use quicserve::transport::MessageStream;
+use quicserve::{
+    ApiKeyAuthenticator, Authenticator, CallContext, CallOptions, CallStream, Client, ClientAuthMode, ConnectInfo, ContextService,
+    DatagramHandler, Error, GroupRegistry, Identity, InterceptedRequest, Interceptor, PubSubConfig, Request, RequestKind, Response,
+    ResponseStream, SerializationFormat, Server, ServerHandle, Service, ServingStatus, StreamMode, TypedStream,
+};
+use futures_util::StreamExt;
+use tokio::sync::mpsc;
+
+/// Longest a test waits for something it expects to happen
+const WAIT: Duration = Duration::from_secs(5);
+
+/// Serialization format of the default configuration, used by the services below
+const FORMAT: SerializationFormat = SerializationFormat::Protobuf;
+
+/// Returns the path of a file in `tests/resources`
+fn resource(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/resources").join(name)
+}
+
+/// Returns a server configuration listening on a free loopback port
+fn server_config() -> Config {
+    Config {
+        addr: "127.0.0.1:0".parse().unwrap(),
+        cert_path: Some(resource("cert.pem")),
+        key_path: Some(resource("key.pem")),
+        ..Default::default()
+    }
+}
+
+/// Starts a server, returning its handle and the address it listens on
+async fn start(server: Server) -> (ServerHandle, SocketAddr) {
+    let addr = server.local_addr().unwrap();
+    (server.serve().await.unwrap(), addr)
+}
+
+/// Returns the configuration of a client using the given stream mode and trusting the test certificate authority
+fn client_config(addr: SocketAddr, stream_mode: StreamMode) -> Config {
+    Config {
+        addr,
+        ca_path: Some(resource("ca.pem")),
+        server_name: Some("localhost".to_string()),
+        stream_mode,
+        ..Default::default()
+    }
+}
+
+/// Connects a client with the given configuration to a server
+async fn connect_with(config: Config) -> Client {
+    let client = Client::new(config).await.unwrap();
+    client.connect().await.unwrap();
+    client
+}
+
+/// Connects a client using the given stream mode to a server
+async fn connect(addr: SocketAddr, stream_mode: StreamMode) -> Client {
+    connect_with(client_config(addr, stream_mode)).await
+}
+
+/// Calls a method taking and returning a string
+async fn call(client: &Client, method: &str, message: &str) -> Result<String, Error> {
+    client.call(method, &message.to_string()).await
+}
+
+/// Waits for the next event a service reports, failing the test if none comes
+async fn next_event<T>(events: &mut mpsc::UnboundedReceiver<T>) -> T {
+    tokio::time::timeout(WAIT, events.recv())
+        .await
+        .expect("timed out waiting for event")
+        .expect("service dropped its events")
+}
+
+/// Service whose `echo` method returns the request payload
+struct Echo;
+
+#[async_trait]
+impl Service for Echo {
+    async fn call(&self, method: &str, payload: Bytes) -> Result<Bytes, Error> {
+        match method {
+            "echo" => Ok(payload),
+            _ => Err(Error::MethodNotFound(method.to_string())),
+        }
+    }
+    
+    fn methods(&self) -> Vec<String> {
+        vec!["echo".to_string()]
+    }
+}
+
+/// Service whose `record` method reports when it starts and finishes handling each message
+struct Recorder {
+    /// Receives `start <message>` and `end <message>` for every call
+    events: mpsc::UnboundedSender<String>,
+}
+
+#[async_trait]
+impl Service for Recorder {
+    async fn call(&self, method: &str, payload: Bytes) -> Result<Bytes, Error> {
+        if method != "record" {
+            return Err(Error::MethodNotFound(method.to_string()));
+        }
+        
+        let message: String = quicserve::deserialize(&payload, FORMAT)?;
+        let _ = self.events.send(format!("start {}", message));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let _ = self.events.send(format!("end {}", message));
+        quicserve::serialize(&message, FORMAT)
+    }
+    
+    fn methods(&self) -> Vec<String> {
+        vec!["record".to_string()]
+    }
+}
+
+/// Service whose `count` stream yields the numbers below the requested one with `x-count`/`x-done` metadata, and whose `fail` stream fails after them
+struct Counter;
+
+#[async_trait]
+impl Service for Counter {
+    async fn call(&self, method: &str, _payload: Bytes) -> Result<Bytes, Error> {
+        Err(Error::MethodNotFound(method.to_string()))
+    }
+    
+    async fn call_stream(&self, method: &str, payload: Bytes) -> Result<ResponseStream, Error> {
+        let count: u32 = quicserve::deserialize(&payload, FORMAT)?;
+        if let Some(context) = CallContext::current() {
+            context.set_header("x-count", count.to_string())?;
+            context.set_trailer("x-done", "yes")?;
+        }
+        let messages = futures_util::stream::iter(0..count).map(|n| quicserve::serialize(&n, FORMAT));
+        
+        match method {
+            "count" => Ok(Box::pin(messages)),
+            "fail" => {
+                let failure = futures_util::stream::once(async { Err(Error::NotFound("more".to_string())) });
+                Ok(Box::pin(messages.chain(failure)))
+            }
+            _ => Err(Error::MethodNotFound(method.to_string())),
+        }
+    }
+    
+    fn methods(&self) -> Vec<String> {
+        vec!["count".to_string(), "fail".to_string()]
+    }
+}
+
+/// Service whose `remaining` method returns the milliseconds left before the call's deadline
+struct Deadline;
+
+#[async_trait]
+impl Service for Deadline {
+    async fn call(&self, method: &str, _payload: Bytes) -> Result<Bytes, Error> {
+        match method {
+            "remaining" => {
+                let context = CallContext::current().ok_or_else(|| Error::Internal("No call context".to_string()))?;
+                let remaining = context.remaining().ok_or_else(|| Error::Internal("No deadline".to_string()))?;
+                quicserve::serialize(&(remaining.as_millis() as u64), FORMAT)
+            }
+            _ => Err(Error::MethodNotFound(method.to_string())),
+        }
+    }
+    
+    fn methods(&self) -> Vec<String> {
+        vec!["remaining".to_string()]
+    }
+}
+
+/// Returns the milliseconds the `remaining` method of [`Deadline`] reports
+async fn remaining_ms(client: &Client) -> u64 {
+    client.call::<String, u64>("deadline.remaining", &String::new()).await.unwrap()
+}
+
+/// Service whose `block` methods never return, reporting when they start and when they are cancelled
+struct Blocking {
+    /// Receives the request ID of every call that starts
+    started: mpsc::UnboundedSender<u64>,
+    /// Receives the request ID of every call that is cancelled
+    cancelled: mpsc::UnboundedSender<u64>,
+}
+
+impl Blocking {
+    /// Reports that a stream started, and later whether it is cancelled
+    fn watch(&self, context: &CallContext) {
+        // The stream outlives the handler that opened it, so watch for cancellation from another task
+        let watched = context.clone();
+        let cancelled = self.cancelled.clone();
+        tokio::spawn(async move {
+            watched.cancelled().await;
+            let _ = cancelled.send(watched.request_id());
+        });
+        
+        let _ = self.started.send(context.request_id());
+    }
+}
+
+#[async_trait]
+impl ContextService for Blocking {
+    async fn call(&self, context: &CallContext, method: &str, _payload: Bytes) -> Result<Bytes, Error> {
+        if method != "block" {
+            return Err(Error::MethodNotFound(method.to_string()));
+        }
+        
+        // The handler sees the cancellation itself and cleans up before returning
+        let _ = self.started.send(context.request_id());
+        context.cancelled().await;
+        let _ = self.cancelled.send(context.request_id());
+        Err(Error::Cancelled)
+    }
+    
+    async fn call_stream(&self, context: &CallContext, method: &str, _payload: Bytes) -> Result<ResponseStream, Error> {
+        if method != "block" {
+            return Err(Error::MethodNotFound(method.to_string()));
+        }
+        
+        self.watch(context);
+        Ok(Box::pin(futures_util::stream::pending()))
+    }
+    
+    fn methods(&self) -> Vec<String> {
+        vec!["block".to_string()]
+    }
+}
+
+#[tokio::test]
+async fn dropping_a_per_call_call_cancels_its_handler() {
+    let (started_tx, mut started) = mpsc::unbounded_channel();
+    let (cancelled_tx, mut cancelled) = mpsc::unbounded_channel();
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_context_service("blocking", Blocking { started: started_tx, cancelled: cancelled_tx }).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::PerCall).await;
+    
+    let call = tokio::spawn({
+        let client = client.clone();
+        async move { client.call_raw("blocking.block", Bytes::new(), CallOptions::new()).await }
+    });
+    let id = next_event(&mut started).await;
+    
+    // Dropping the call resets its stream while the handler is still running, which
+    // sees the cancellation and cleans up rather than being aborted
+    call.abort();
+    assert_eq!(next_event(&mut cancelled).await, id);
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn queued_calls_dont_stop_the_shared_stream_reader() {
+    let (started_tx, mut started) = mpsc::unbounded_channel();
+    let (cancelled_tx, mut cancelled) = mpsc::unbounded_channel();
+    let config = Config { ordered_dispatch: true, ..server_config() };
+    let server = Server::new(config).await.unwrap();
+    server.register_context_service("blocking", Blocking { started: started_tx, cancelled: cancelled_tx }).await.unwrap();
+    server.register_service("echo", Echo).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::Shared).await;
+    
+    // The blocked call holds the session's only dispatch slot
+    let blocked = tokio::spawn({
+        let client = client.clone();
+        async move { client.call_raw("blocking.block", Bytes::new(), CallOptions::new()).await }
+    });
+    let id = next_event(&mut started).await;
+    
+    // This call queues for the slot on the same stream
+    let queued = tokio::spawn({
+        let client = client.clone();
+        async move { client.call_raw("echo.echo", Bytes::from("ping"), CallOptions::new()).await }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    
+    // The cancel frame is still read, which frees the slot for the queued call
+    blocked.abort();
+    assert_eq!(next_event(&mut cancelled).await, id);
+    let response = tokio::time::timeout(WAIT, queued).await.unwrap().unwrap().unwrap();
+    assert_eq!(response.message, Bytes::from("ping"));
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn queued_calls_time_out_at_their_deadline() {
+    let (started_tx, mut started) = mpsc::unbounded_channel();
+    let (cancelled_tx, mut cancelled) = mpsc::unbounded_channel();
+    let config = Config { max_in_flight_requests: 1, timeout_ms: 200, ..server_config() };
+    let server = Server::new(config).await.unwrap();
+    server.register_context_service("blocking", Blocking { started: started_tx, cancelled: cancelled_tx }).await.unwrap();
+    server.register_service("echo", Echo).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::Shared).await;
+    
+    // Streams without a deadline aren't bound by the server's timeout, so this one keeps the slot
+    let _blocked = client.call_stream::<(), ()>("blocking.block", &()).await.unwrap();
+    let id = next_event(&mut started).await;
+    
+    // The server fails the queued call once its deadline passes, well before the client gives up
+    let options = CallOptions::new().with_timeout(Duration::from_secs(30));
+    let result = tokio::time::timeout(WAIT, client.call_raw("echo.echo", Bytes::from("ping"), options)).await.unwrap();
+    assert!(matches!(result, Err(Error::Timeout)), "unexpected result {:?}", result.map(|response| response.message));
+    assert!(cancelled.try_recv().is_err(), "call {} was cancelled", id);
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+/// Checks that a slow call doesn't hold up a fast one made on the same session
+async fn slow_calls_dont_delay_fast_ones(stream_mode: StreamMode) {
+    let (started_tx, mut started) = mpsc::unbounded_channel();
+    let (cancelled_tx, _cancelled) = mpsc::unbounded_channel();
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_context_service("blocking", Blocking { started: started_tx, cancelled: cancelled_tx }).await.unwrap();
+    server.register_service("echo", Echo).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, stream_mode).await;
+    
+    let slow = tokio::spawn({
+        let client = client.clone();
+        async move { call(&client, "blocking.block", "").await }
+    });
+    next_event(&mut started).await;
+    
+    let fast = tokio::time::timeout(WAIT, call(&client, "echo.echo", "ping")).await;
+    assert_eq!(fast.unwrap().unwrap(), "ping");
+    assert!(!slow.is_finished());
+    
+    slow.abort();
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn slow_calls_dont_delay_fast_ones_on_a_shared_stream() {
+    slow_calls_dont_delay_fast_ones(StreamMode::Shared).await;
+}
+
+#[tokio::test]
+async fn slow_calls_dont_delay_fast_ones_on_per_call_streams() {
+    slow_calls_dont_delay_fast_ones(StreamMode::PerCall).await;
+}
+
+#[tokio::test]
+async fn ordered_dispatch_runs_calls_one_at_a_time_in_arrival_order() {
+    let (events_tx, mut events) = mpsc::unbounded_channel();
+    let config = Config { ordered_dispatch: true, ..server_config() };
+    let server = Server::new(config).await.unwrap();
+    server.register_service("recorder", Recorder { events: events_tx }).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::Shared).await;
+    
+    // Space the calls out so they reach the server in order, while each still queues behind the previous one
+    let mut calls = Vec::new();
+    for i in 0..4 {
+        let client = client.clone();
+        calls.push(tokio::spawn(async move { call(&client, "recorder.record", &i.to_string()).await }));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    for (i, call) in calls.into_iter().enumerate() {
+        assert_eq!(call.await.unwrap().unwrap(), i.to_string());
+    }
+    
+    let mut recorded = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        recorded.push(event);
+    }
+    let expected: Vec<String> = (0..4)
+        .flat_map(|i| [format!("start {}", i), format!("end {}", i)])
+        .collect();
+    assert_eq!(recorded, expected);
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn subscriptions_dont_hold_up_ordered_dispatch() {
+    let config = Config { ordered_dispatch: true, ..server_config() };
+    let server = Server::new(config).await.unwrap();
+    server.register_service("echo", Echo).await.unwrap();
+    let pubsub = server.enable_pubsub(PubSubConfig::default()).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::Shared).await;
+    
+    // Calls made while subscriptions are open are still dispatched
+    let mut news = client.subscribe::<String>("news", 0).await.unwrap();
+    let mut sports = client.subscribe::<String>("sports", 0).await.unwrap();
+    assert_eq!(tokio::time::timeout(WAIT, call(&client, "echo.echo", "hi")).await.unwrap().unwrap(), "hi");
+    
+    // And the subscriptions still receive their events
+    for (topic, subscription) in [("news", &mut news), ("sports", &mut sports)] {
+        eventually(|| {
+            let pubsub = pubsub.clone();
+            async move { pubsub.publish(topic, &topic.to_string()).unwrap() == 1 }
+        })
+        .await;
+        let event = tokio::time::timeout(WAIT, subscription.next()).await.unwrap().unwrap().unwrap();
+        assert_eq!(event.message, topic);
+    }
+    
+    drop(news);
+    drop(sports);
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+/// Checks that concurrent calls each receive their own response
+async fn concurrent_calls_get_their_own_responses(stream_mode: StreamMode) {
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_service("echo", Echo).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, stream_mode).await;
+    
+    let calls: Vec<_> = (0..20)
+        .map(|i| {
+            let client = client.clone();
+            tokio::spawn(async move {
+                let message = format!("message {}", i);
+                assert_eq!(call(&client, "echo.echo", &message).await.unwrap(), message);
+            })
+        })
+        .collect();
+    for call in calls {
+        tokio::time::timeout(WAIT, call).await.unwrap().unwrap();
+    }
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn concurrent_calls_get_their_own_responses_on_a_shared_stream() {
+    concurrent_calls_get_their_own_responses(StreamMode::Shared).await;
+}
+
+#[tokio::test]
+async fn concurrent_calls_get_their_own_responses_on_per_call_streams() {
+    concurrent_calls_get_their_own_responses(StreamMode::PerCall).await;
+}
+
+/// Checks that errors of a call reach its caller and leave the session usable
+async fn failed_calls_leave_the_session_usable(stream_mode: StreamMode) {
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_service("echo", Echo).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, stream_mode).await;
+    
+    assert!(matches!(call(&client, "echo.missing", "").await, Err(Error::MethodNotFound(_))));
+    assert!(matches!(call(&client, "missing.echo", "").await, Err(Error::MethodNotFound(_))));
+    assert_eq!(call(&client, "echo.echo", "ping").await.unwrap(), "ping");
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn failed_calls_leave_a_shared_stream_usable() {
+    failed_calls_leave_the_session_usable(StreamMode::Shared).await;
+}
+
+#[tokio::test]
+async fn failed_calls_leave_per_call_sessions_usable() {
+    failed_calls_leave_the_session_usable(StreamMode::PerCall).await;
+}
+
+/// Opens a session and a stream to a server without a [`Client`], to send it raw frames
+///
+/// The session is returned alongside the stream so it stays open.
+async fn raw_stream(addr: SocketAddr) -> (h3_webtransport::client::Session, MessageStream) {
+    let mut endpoint = quinn::Endpoint::client("127.0.0.1:0".parse().unwrap()).unwrap();
+    endpoint.set_default_client_config(client_config(addr, StreamMode::Shared).build_client_config().unwrap());
+    let connection = endpoint.connect(addr, "localhost").unwrap().await.unwrap();
+    
+    let h3_conn = h3::client::Connection::new(h3::quic::Connection::new(connection)).await.unwrap();
+    let session = h3_webtransport::client::Builder::new()
+        .enable_webtransport(true)
+        .enable_datagram(true)
+        .build(h3_conn)
+        .await
+        .unwrap();
+    let request = http::Request::builder()
+        .method(http::Method::CONNECT)
+        .uri("https://localhost/rpc")
+        .body(())
+        .unwrap();
+    let session = session.connect_request(request).await.unwrap();
+    
+    let stream = MessageStream::new(session.open_bi().await.unwrap());
+    (session, stream)
+}
+
+#[tokio::test]
+async fn malformed_frames_leave_the_shared_stream_usable() {
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_service("echo", Echo).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let (_session, mut stream) = raw_stream(addr).await;
+    
+    // A frame that isn't a request is dropped
+    stream.send(Bytes::from_static(&[0xff; 11])).await.unwrap();
+    
+    // And the next call on the same stream is still served
+    let request = Request {
+        id: 1,
+        method: "echo.echo".to_string(),
+        payload: Bytes::from("ping"),
+        kind: RequestKind::Unary,
+        end_of_stream: false,
+        timeout_ms: None,
+        metadata: Default::default(),
+    };
+    stream.send(quicserve::serialize(&request, FORMAT).unwrap()).await.unwrap();
+    let response_bytes = tokio::time::timeout(WAIT, stream.receive()).await.unwrap().unwrap().unwrap();
+    let response: Response = quicserve::deserialize(&response_bytes, FORMAT).unwrap();
+    assert_eq!(response.id, 1);
+    assert_eq!(response.payload, Some(Bytes::from("ping")));
+    
+    stream.finish().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn server_streams_end_with_their_trailer() {
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_service("counter", Counter).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::PerCall).await;
+    
+    let mut stream = client.call_stream::<u32, u32>("counter.count", &3).await.unwrap();
+    let mut received = Vec::new();
+    while let Some(message) = tokio::time::timeout(WAIT, stream.next()).await.unwrap() {
+        received.push(message.unwrap());
+    }
+    assert_eq!(received, vec![0, 1, 2]);
+    assert_eq!(stream.headers().get_str("x-count"), Some("3"));
+    assert_eq!(stream.trailers().get_str("x-done"), Some("yes"));
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn server_stream_errors_follow_the_messages_sent_before_them() {
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_service("counter", Counter).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::PerCall).await;
+    
+    let mut stream = client.call_stream::<u32, u32>("counter.fail", &2).await.unwrap();
+    assert_eq!(stream.next().await.unwrap().unwrap(), 0);
+    assert_eq!(stream.next().await.unwrap().unwrap(), 1);
+    assert!(matches!(stream.next().await, Some(Err(Error::NotFound(message))) if message == "more"));
+    assert!(stream.next().await.is_none());
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn unknown_stream_methods_fail_with_a_trailer() {
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_service("counter", Counter).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::PerCall).await;
+    
+    let mut stream = client.call_stream::<u32, u32>("counter.missing", &1).await.unwrap();
+    assert!(matches!(stream.next().await, Some(Err(Error::MethodNotFound(_)))));
+    assert!(stream.next().await.is_none());
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn client_deadlines_reach_the_handler() {
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_service("deadline", Deadline).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect_with(Config { timeout_ms: 2000, ..client_config(addr, StreamMode::PerCall) }).await;
+    
+    let remaining = remaining_ms(&client).await;
+    assert!(remaining <= 2000 && remaining > 1000, "remaining {}ms", remaining);
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn server_timeout_bounds_client_deadlines() {
+    let config = Config { timeout_ms: 500, ..server_config() };
+    let server = Server::new(config).await.unwrap();
+    server.register_service("deadline", Deadline).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect_with(Config { timeout_ms: 10_000, ..client_config(addr, StreamMode::Shared) }).await;
+    
+    assert!(remaining_ms(&client).await <= 500);
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn shutdown_lets_in_flight_calls_finish_and_refuses_new_work() {
+    let (events_tx, mut events) = mpsc::unbounded_channel();
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_service("recorder", Recorder { events: events_tx }).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::PerCall).await;
+    
+    let in_flight = tokio::spawn({
+        let client = client.clone();
+        async move { call(&client, "recorder.record", "a").await }
+    });
+    assert_eq!(next_event(&mut events).await, "start a");
+    
+    let shutdown = tokio::spawn({
+        let handle = handle.clone();
+        async move { handle.shutdown().await }
+    });
+    tokio::time::timeout(WAIT, async {
+        while !handle.is_shutting_down() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    
+    // The call that was running when shutdown began still completes
+    tokio::time::timeout(WAIT, in_flight).await.unwrap().unwrap().unwrap();
+    assert_eq!(next_event(&mut events).await, "end a");
+    tokio::time::timeout(WAIT, shutdown).await.unwrap().unwrap();
+    
+    // Calls on the old session and new connections are refused
+    let result = tokio::time::timeout(WAIT, call(&client, "recorder.record", "b")).await.unwrap();
+    assert!(result.is_err());
+    let late = Client::new(client_config(addr, StreamMode::PerCall)).await.unwrap();
+    assert!(tokio::time::timeout(WAIT, late.connect()).await.unwrap().is_err());
+    assert!(events.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn shutdown_deadline_cuts_off_calls_still_running() {
+    let (started_tx, mut started) = mpsc::unbounded_channel();
+    let (cancelled_tx, _cancelled) = mpsc::unbounded_channel();
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_context_service("blocking", Blocking { started: started_tx, cancelled: cancelled_tx }).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::PerCall).await;
+    
+    let pending = tokio::spawn({
+        let client = client.clone();
+        async move { call(&client, "blocking.block", "").await }
+    });
+    next_event(&mut started).await;
+    
+    // The server stops once the grace period is over, even though the call never returns
+    tokio::time::timeout(WAIT, handle.shutdown_with_deadline(Duration::from_millis(200))).await.unwrap();
+    tokio::time::timeout(WAIT, handle.wait()).await.unwrap();
+    let result = tokio::time::timeout(WAIT, pending).await.unwrap().unwrap();
+    assert!(result.is_err());
+}
+
+/// Interceptor appending its tag to every request and response message
+struct Tag(&'static str);
+
+impl Tag {
+    /// Returns the string message encoded in a payload with the tag appended
+    fn append(&self, payload: &Bytes) -> Result<Bytes, Error> {
+        let message: String = quicserve::deserialize(payload, FORMAT)?;
+        quicserve::serialize(&(message + self.0), FORMAT)
+    }
+}
+
+#[async_trait]
+impl Interceptor for Tag {
+    async fn on_request(&self, request: &mut InterceptedRequest) -> Result<(), Error> {
+        request.payload = self.append(&request.payload)?;
+        Ok(())
+    }
+    
+    async fn on_response(&self, _request: &InterceptedRequest, response: &mut Result<Bytes, Error>) {
+        if let Ok(message) = response {
+            *response = self.append(message);
+        }
+    }
+}
+
+#[tokio::test]
+async fn global_interceptors_wrap_service_interceptors() {
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_service("echo", Echo).await.unwrap();
+    server.register_service("other", Echo).await.unwrap();
+    server.add_interceptor(Tag("g1")).await.unwrap();
+    server.add_service_interceptor("echo", Tag("s1")).await.unwrap();
+    server.add_service_interceptor("echo", Tag("s2")).await.unwrap();
+    server.add_interceptor(Tag("g2")).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::PerCall).await;
+    
+    // Request hooks run global first in registration order, response hooks in reverse
+    assert_eq!(call(&client, "echo.echo", "-").await.unwrap(), "-g1g2s1s2s2s1g2g1");
+    
+    // Per-service interceptors only apply to their service
+    assert_eq!(call(&client, "other.echo", "-").await.unwrap(), "-g1g2g2g1");
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[cfg(feature = "tower")]
+#[tokio::test]
+async fn tower_services_see_the_call_and_pass_on_its_metadata() {
+    use quicserve::{CallResponse, RpcRequest};
+    
+    let inner = tower::service_fn(|request: RpcRequest| async move {
+        assert_eq!(request.method, "proxy.forward");
+        assert_eq!(request.metadata.get_str("trace-id"), Some("abc"));
+        assert!(request.timeout.is_some_and(|timeout| timeout <= Duration::from_secs(10)));
+        
+        let mut response = CallResponse::from(request.payload);
+        response.headers.insert_str("x-header", "1");
+        response.trailers.insert_str("x-trailer", "2");
+        Ok::<_, Error>(response)
+    });
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_tower_service("proxy", inner, ["forward"]).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::PerCall).await;
+    
+    let options = CallOptions::new().with_metadata("trace-id", "abc").unwrap().with_timeout(Duration::from_secs(10));
+    let response = client.call_raw("proxy.forward", Bytes::from("ping"), options).await.unwrap();
+    assert_eq!(response.message, Bytes::from("ping"));
+    
+    // Response metadata is passed on to the client
+    assert_eq!(response.headers.get_str("x-header"), Some("1"));
+    assert_eq!(response.trailers.get_str("x-trailer"), Some("2"));
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[cfg(feature = "tower")]
+#[tokio::test]
+async fn boxed_tower_services_can_be_registered() {
+    use quicserve::{CallResponse, RpcRequest};
+    use tower::util::BoxCloneService;
+    
+    // Boxed services are `Send` but not `Sync`
+    let inner = tower::service_fn(|request: RpcRequest| async move {
+        Ok::<_, Error>(CallResponse::from(request.payload))
+    });
+    let service: BoxCloneService<RpcRequest, CallResponse<Bytes>, Error> = BoxCloneService::new(inner);
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_tower_service("boxed", service, ["echo"]).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::PerCall).await;
+    
+    let response = client.call_raw("boxed.echo", Bytes::from("ping"), CallOptions::new()).await.unwrap();
+    assert_eq!(response.message, Bytes::from("ping"));
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+/// Service defined through the `service` macro, registered under its trait name in snake_case
+#[quicserve::service]
+pub trait CountingGreeter {
+    /// Greets the given name
+    async fn greet(&self, request: String) -> Result<String, Error>;
+    
+    /// Streams the numbers below the requested one
+    async fn count(&self, context: &CallContext, request: u32) -> Result<TypedStream<u32>, Error>;
+}
+
+/// Implementation of [`CountingGreeter`]
+struct Greetings;
+
+#[async_trait]
+impl CountingGreeter for Greetings {
+    async fn greet(&self, request: String) -> Result<String, Error> {
+        Ok(format!("Hello, {}", request))
+    }
+    
+    async fn count(&self, context: &CallContext, request: u32) -> Result<TypedStream<u32>, Error> {
+        assert_eq!(context.method(), "counting_greeter.count");
+        Ok(Box::pin(futures_util::stream::iter(0..request).map(Ok)))
+    }
+}
+
+/// Interceptor reporting the full name of every call it sees
+struct Methods(mpsc::UnboundedSender<String>);
+
+#[async_trait]
+impl Interceptor for Methods {
+    async fn on_request(&self, request: &mut InterceptedRequest) -> Result<(), Error> {
+        let _ = self.0.send(request.method.clone());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn generated_servers_and_clients_call_each_other() {
+    let (methods_tx, mut methods) = mpsc::unbounded_channel();
+    let server = Server::new(server_config()).await.unwrap();
+    let service = CountingGreeterServer::new(Greetings);
+    assert_eq!(service.methods(), vec!["greet".to_string(), "count".to_string()]);
+    server.register_service(COUNTING_GREETER_NAME, service).await.unwrap();
+    server.add_interceptor(Methods(methods_tx)).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = CountingGreeterClient::new(connect(addr, StreamMode::PerCall).await);
+    
+    // Unary methods are dispatched to their handler
+    assert_eq!(client.greet(&"world".to_string()).await.unwrap(), "Hello, world");
+    assert_eq!(next_event(&mut methods).await, "counting_greeter.greet");
+    
+    // So are server-streaming methods, whose messages are encoded one by one
+    let mut stream = client.count(&3).await.unwrap();
+    let mut received = Vec::new();
+    while let Some(message) = tokio::time::timeout(WAIT, stream.next()).await.unwrap() {
+        received.push(message.unwrap());
+    }
+    assert_eq!(received, vec![0, 1, 2]);
+    assert_eq!(next_event(&mut methods).await, "counting_greeter.count");
+    
+    // Unknown methods aren't served
+    let result = client.inner().call::<String, String>("counting_greeter.missing", &String::new()).await;
+    assert!(matches!(result, Err(Error::MethodNotFound(_))));
+    
+    client.inner().close().await.unwrap();
+    handle.shutdown().await;
+}
+
+/// Service whose `whoami` method describes the session the call was made on
+struct Whoami;
+
+#[async_trait]
+impl ContextService for Whoami {
+    async fn call(&self, context: &CallContext, method: &str, _payload: Bytes) -> Result<Bytes, Error> {
+        if method != "whoami" {
+            return Err(Error::MethodNotFound(method.to_string()));
+        }
+        
+        let session_id = context.session_id().ok_or_else(|| Error::Internal("No session".to_string()))?;
+        let session = context.session().ok_or_else(|| Error::Internal("No session handle".to_string()))?;
+        assert_eq!(session.id(), session_id);
+        assert_eq!(session.peer_addr(), context.peer_addr());
+        
+        let description = format!(
+            "{} {} {} {}",
+            session_id,
+            context.peer_addr().ip(),
+            context.peer_certificates().len(),
+            context.identity().map_or("-", |identity| identity.subject.as_str()),
+        );
+        Ok(Bytes::from(description))
+    }
+    
+    fn methods(&self) -> Vec<String> {
+        vec!["whoami".to_string()]
+    }
+}
+
+/// Calls `whoami.whoami`, returning the words of its description
+async fn whoami(client: &Client) -> Vec<String> {
+    let response = client.call_raw("whoami.whoami", Bytes::new(), CallOptions::new()).await.unwrap();
+    std::str::from_utf8(&response.message).unwrap().split(' ').map(str::to_string).collect()
+}
+
+#[tokio::test]
+async fn calls_see_the_session_they_were_made_on() {
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_context_service("whoami", Whoami).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::PerCall).await;
+    
+    // Calls of a client share its session, which the server handle knows about
+    let first = whoami(&client).await;
+    let second = whoami(&client).await;
+    assert_eq!(first, second);
+    assert_eq!(first[1..], ["127.0.0.1", "0", "-"]);
+    let sessions: Vec<String> = handle.sessions().await.iter().map(|session| session.id().to_string()).collect();
+    assert_eq!(sessions, [first[0].clone()]);
+    
+    // Another client gets a session of its own
+    let other = connect(addr, StreamMode::Shared).await;
+    assert_ne!(whoami(&other).await[0], first[0]);
+    assert_eq!(handle.sessions().await.len(), 2);
+    
+    other.close().await.unwrap();
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn calls_see_the_identity_of_their_session() {
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_context_service("whoami", Whoami).await.unwrap();
+    let authenticator = StaticTokenAuthenticator::new().with_token("secret", Identity::new("alice"));
+    server.set_authenticator(authenticator).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect_with(Config { bearer_token: Some("secret".to_string()), ..client_config(addr, StreamMode::PerCall) }).await;
+    
+    let description = whoami(&client).await;
+    assert_eq!(description[3], "alice");
+    let session = handle.session(description[0].parse().unwrap()).await.unwrap();
+    assert_eq!(session.identity().unwrap().subject, "alice");
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+/// Returns the error a client with the given configuration fails to connect with
+async fn connect_error(config: Config) -> Error {
+    let client = Client::new(config).await.unwrap();
+    client.connect().await.expect_err("session was accepted")
+}
+
+#[tokio::test]
+async fn sessions_without_valid_credentials_are_refused() {
+    // Bearer tokens
+    let server = Server::new(server_config()).await.unwrap();
+    server.set_authenticator(StaticTokenAuthenticator::new().with_token("secret", Identity::new("alice"))).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let config = client_config(addr, StreamMode::PerCall);
+    
+    assert!(matches!(connect_error(config.clone()).await, Error::AuthenticationFailed(_)));
+    let wrong = Config { bearer_token: Some("guess".to_string()), ..config.clone() };
+    assert!(matches!(connect_error(wrong).await, Error::AuthenticationFailed(_)));
+    connect_with(Config { bearer_token: Some("secret".to_string()), ..config }).await.close().await.unwrap();
+    handle.shutdown().await;
+    
+    // API keys
+    let path = std::env::temp_dir().join(format!("quicserve-apikeys-{}.json", uuid::Uuid::new_v4()));
+    let key = generate_api_key();
+    let file = ApiKeyFile { keys: vec![ApiKeyEntry::new("bob", &key)] };
+    std::fs::write(&path, serde_json::to_vec(&file).unwrap()).unwrap();
+    let server = Server::new(server_config()).await.unwrap();
+    server.set_authenticator(ApiKeyAuthenticator::from_file(&path).await.unwrap()).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let config = client_config(addr, StreamMode::PerCall);
+    
+    assert!(matches!(connect_error(config.clone()).await, Error::AuthenticationFailed(_)));
+    let wrong = Config { api_key: Some(generate_api_key()), ..config.clone() };
+    assert!(matches!(connect_error(wrong).await, Error::AuthenticationFailed(_)));
+    connect_with(Config { api_key: Some(key), ..config }).await.close().await.unwrap();
+    handle.shutdown().await;
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// Authenticator refusing every session with the error named by its `x-refuse` header
+struct Refuse;
+
+#[async_trait]
+impl Authenticator for Refuse {
+    async fn authenticate(&self, info: &ConnectInfo) -> Result<Identity, Error> {
+        match info.header("x-refuse") {
+            Some("forbidden") => Err(Error::PermissionDenied("banned".to_string())),
+            Some("busy") => Err(Error::Unavailable("directory down".to_string())),
+            _ => Err(Error::AuthenticationFailed("no credentials".to_string())),
+        }
+    }
+}
+
+#[tokio::test]
+async fn refused_sessions_fail_with_the_authenticators_error() {
+    let server = Server::new(server_config()).await.unwrap();
+    server.set_authenticator(Refuse).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let refused = |reason: &str| {
+        let mut config = client_config(addr, StreamMode::PerCall);
+        config.connect_headers.insert("x-refuse".to_string(), reason.to_string());
+        connect_error(config)
+    };
+    
+    assert!(matches!(refused("forbidden").await, Error::PermissionDenied(_)));
+    assert!(matches!(refused("busy").await, Error::Unavailable(_)));
+    assert!(matches!(refused("").await, Error::AuthenticationFailed(_)));
+    
+    handle.shutdown().await;
+}
+
+/// Service whose `peer` method describes the certificate the client presented
+struct Peer;
+
+#[async_trait]
+impl ContextService for Peer {
+    async fn call(&self, context: &CallContext, _method: &str, _payload: Bytes) -> Result<Bytes, Error> {
+        let description = format!(
+            "{} {} {}",
+            context.peer_certificates().len(),
+            context.peer_subject().unwrap_or("-"),
+            context.peer_sans().join(","),
+        );
+        Ok(Bytes::from(description))
+    }
+    
+    fn methods(&self) -> Vec<String> {
+        vec!["peer".to_string()]
+    }
+}
+
+/// Starts a server verifying client certificates against the test certificate authority
+async fn start_mtls(client_auth: ClientAuthMode) -> (ServerHandle, SocketAddr) {
+    let server = Server::new(Config {
+        ca_path: Some(resource("ca.pem")),
+        client_auth,
+        ..server_config()
+    }).await.unwrap();
+    server.register_context_service("peer", Peer).await.unwrap();
+    start(server).await
+}
+
+/// Returns the configuration of a client presenting the certificate in `tests/resources/<name>.pem`
+fn mtls_client_config(addr: SocketAddr, name: &str) -> Config {
+    Config {
+        client_cert_path: Some(resource(&format!("{}.pem", name))),
+        client_key_path: Some(resource(&format!("{}-key.pem", name))),
+        ..client_config(addr, StreamMode::PerCall)
+    }
+}
+
+/// Calls `peer.peer`, returning its description of the client certificate
+async fn peer(client: &Client) -> String {
+    let response = client.call_raw("peer.peer", Bytes::new(), CallOptions::new()).await.unwrap();
+    String::from_utf8(response.message.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn required_client_certificates_are_verified_and_exposed() {
+    let (handle, addr) = start_mtls(ClientAuthMode::Required).await;
+    
+    let client = connect_with(mtls_client_config(addr, "client")).await;
+    assert_eq!(peer(&client).await, "1 CN=quicserve-test-client client.quicserve.test");
+    
+    // Clients without a certificate are turned away
+    let anonymous = Client::new(client_config(addr, StreamMode::PerCall)).await.unwrap();
+    assert!(anonymous.connect().await.is_err());
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn optional_client_certificates_are_exposed_when_presented() {
+    let (handle, addr) = start_mtls(ClientAuthMode::Optional).await;
+    
+    let client = connect_with(mtls_client_config(addr, "client")).await;
+    assert_eq!(peer(&client).await, "1 CN=quicserve-test-client client.quicserve.test");
+    
+    // Clients without a certificate are let in anonymously
+    let anonymous = connect(addr, StreamMode::PerCall).await;
+    assert_eq!(peer(&anonymous).await, "0 - ");
+    
+    anonymous.close().await.unwrap();
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn client_certificates_from_unknown_authorities_are_rejected() {
+    for client_auth in [ClientAuthMode::Required, ClientAuthMode::Optional] {
+        let (handle, addr) = start_mtls(client_auth).await;
+        
+        let rogue = Client::new(mtls_client_config(addr, "rogue")).await.unwrap();
+        assert!(rogue.connect().await.is_err(), "{} client auth accepted a self-signed certificate", client_auth);
+        assert!(handle.sessions().await.is_empty());
+        
+        handle.shutdown().await;
+    }
+}
+
+#[tokio::test]
+async fn reflection_describes_the_registered_services() {
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_service("echo", Echo).await.unwrap();
+    server.enable_reflection().await.unwrap();
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::PerCall).await;
+    
+    let response: ListServicesResponse = client.call("reflection.list_services", &ListServicesRequest::default()).await.unwrap();
+    let names: Vec<&str> = response.services.iter().map(|service| service.name.as_str()).collect();
+    assert_eq!(names, ["echo", "reflection"]);
+    
+    let request = DescribeServiceRequest { name: "reflection".to_string() };
+    let response: DescribeServiceResponse = client.call("reflection.describe_service", &request).await.unwrap();
+    assert_eq!(response.service.unwrap().methods, ["list_services", "describe_service"]);
+    assert_eq!(response.file_descriptor_set, Bytes::from_static(FILE_DESCRIPTOR_SET));
+    
+    let request = DescribeServiceRequest { name: "missing".to_string() };
+    let result = client.call::<_, DescribeServiceResponse>("reflection.describe_service", &request).await;
+    assert!(matches!(result, Err(Error::NotFound(_))));
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+/// Returns the status of the next response of a health watch
+async fn next_status(statuses: &mut CallStream<HealthCheckResponse>) -> Option<ServingStatus> {
+    let response = tokio::time::timeout(WAIT, statuses.next()).await.expect("timed out waiting for status");
+    response.map(|response| response.unwrap().status())
+}
+
+#[tokio::test]
+async fn health_checks_and_watches_follow_the_reporter() {
+    let server = Server::new(server_config()).await.unwrap();
+    server.enable_health().await.unwrap();
+    let reporter = server.health_reporter();
+    reporter.set_not_serving("db");
+    let (handle, addr) = start(server).await;
+    let client = connect(addr, StreamMode::PerCall).await;
+    
+    // The server as a whole is the empty name
+    let request = HealthCheckRequest { service: String::new() };
+    let response: HealthCheckResponse = client.call("health.check", &request).await.unwrap();
+    assert_eq!(response.status(), ServingStatus::Serving);
+    let request = HealthCheckRequest { service: "cache".to_string() };
+    let result = client.call::<_, HealthCheckResponse>("health.check", &request).await;
+    assert!(matches!(result, Err(Error::NotFound(_))));
+    
+    let request = HealthCheckRequest { service: "db".to_string() };
+    let mut statuses = client.call_stream::<_, HealthCheckResponse>("health.watch", &request).await.unwrap();
+    assert_eq!(next_status(&mut statuses).await, Some(ServingStatus::NotServing));
+    reporter.set_serving("db");
+    assert_eq!(next_status(&mut statuses).await, Some(ServingStatus::Serving));
+    
+    // Watches end once the server starts shutting down
+    let shutdown = tokio::spawn(async move { handle.shutdown().await });
+    assert_eq!(next_status(&mut statuses).await, Some(ServingStatus::NotServing));
+    assert_eq!(next_status(&mut statuses).await, None);
+    tokio::time::timeout(WAIT, shutdown).await.unwrap().unwrap();
+}
+
+/// Service whose `join` method adds the caller's session to the group named by the payload
+struct Rooms {
+    /// Groups of the server
+    groups: GroupRegistry,
+}
+
+#[async_trait]
+impl ContextService for Rooms {
+    async fn call(&self, context: &CallContext, method: &str, payload: Bytes) -> Result<Bytes, Error> {
+        if method != "join" {
+            return Err(Error::MethodNotFound(method.to_string()));
+        }
+        
+        let session = context.session().ok_or_else(|| Error::Internal("No session".to_string()))?;
+        let joined = self.groups.join(std::str::from_utf8(&payload).unwrap(), session).await;
+        Ok(Bytes::from(joined.to_string()))
+    }
+    
+    fn methods(&self) -> Vec<String> {
+        vec!["join".to_string()]
+    }
+}
+
+/// Makes a client join a group through [`Rooms`], returning whether it wasn't a member yet
+async fn join(client: &Client, group: &str) -> bool {
+    let response = client.call_raw("rooms.join", Bytes::from(group.to_string()), CallOptions::new()).await.unwrap();
+    response.message == Bytes::from("true")
+}
+
+/// Waits until a condition on the server holds, failing the test if it never does
+async fn eventually<F, Fut>(mut condition: F)
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    tokio::time::timeout(WAIT, async {
+        while !condition().await {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("timed out waiting for condition");
+}
+
+#[tokio::test]
+async fn groups_track_their_members_and_broadcast_to_them() {
+    let server = Server::new(server_config()).await.unwrap();
+    let groups = server.groups();
+    server.register_context_service("rooms", Rooms { groups: groups.clone() }).await.unwrap();
+    let (handle, addr) = start(server).await;
+    
+    let (events_tx, mut events) = mpsc::unbounded_channel();
+    let alice = connect(addr, StreamMode::PerCall).await;
+    alice.register_service("recorder", Recorder { events: events_tx.clone() }).await.unwrap();
+    let bob = connect(addr, StreamMode::Shared).await;
+    bob.register_service("recorder", Recorder { events: events_tx }).await.unwrap();
+    
+    assert!(join(&alice, "lobby").await);
+    assert!(!join(&alice, "lobby").await);
+    assert!(join(&alice, "games").await);
+    assert!(join(&bob, "lobby").await);
+    
+    let mut names = groups.groups().await;
+    names.sort();
+    assert_eq!(names, ["games", "lobby"]);
+    assert_eq!(groups.members("lobby").await.len(), 2);
+    
+    // Every member receives broadcasts
+    assert_eq!(groups.broadcast("lobby", "recorder.record", &"hi".to_string()).await.unwrap(), 2);
+    let mut received = Vec::new();
+    for _ in 0..4 {
+        received.push(next_event(&mut events).await);
+    }
+    received.sort();
+    assert_eq!(received, ["end hi", "end hi", "start hi", "start hi"]);
+    
+    // Leaving the last member's group removes it
+    let alice_id = groups.members("games").await[0].id();
+    assert!(groups.leave("games", alice_id).await);
+    assert!(!groups.leave("games", alice_id).await);
+    assert_eq!(groups.groups_of(alice_id).await, ["lobby"]);
+    assert_eq!(groups.groups().await, ["lobby"]);
+    
+    alice.close().await.unwrap();
+    bob.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn closed_sessions_leave_their_groups() {
+    let server = Server::new(server_config()).await.unwrap();
+    server.register_context_service("rooms", Rooms { groups: server.groups() }).await.unwrap();
+    let (handle, addr) = start(server).await;
+    let groups = handle.groups();
+    
+    let client = connect(addr, StreamMode::PerCall).await;
+    assert!(join(&client, "lobby").await);
+    let session = groups.members("lobby").await.remove(0);
+    
+    client.close().await.unwrap();
+    eventually(|| {
+        let groups = groups.clone();
+        async move { groups.groups().await.is_empty() }
+    })
+    .await;
+    assert!(session.is_closed());
+    assert!(groups.groups_of(session.id()).await.is_empty());
+    assert!(handle.session(session.id()).await.is_none());
+    
+    // A handle kept from before the session closed can't join again
+    assert!(!groups.join("lobby", session.clone()).await);
+    assert!(groups.members("lobby").await.is_empty());
+    assert_eq!(groups.broadcast_raw("lobby", "recorder.record", Bytes::new(), Default::default()).await, 0);
+    
+    handle.shutdown().await;
+}
+
+/// Datagram handler reporting the payloads it receives
+struct Beacon {
+    /// Receives the payload of every datagram
+    events: mpsc::UnboundedSender<String>,
+}
+
+#[async_trait]
+impl DatagramHandler for Beacon {
+    async fn handle(&self, _context: &CallContext, payload: Bytes) {
+        let _ = self.events.send(String::from_utf8(payload.to_vec()).unwrap());
+    }
+}
+
+#[tokio::test]
+async fn datagrams_reach_both_sides_and_must_fit_in_a_packet() {
+    let server = Server::new(server_config()).await.unwrap();
+    let groups = server.groups();
+    let (pings_tx, mut pings) = mpsc::unbounded_channel();
+    server.register_datagram_handler("beacon.ping", Beacon { events: pings_tx }).await.unwrap();
+    server.register_context_service("rooms", Rooms { groups: groups.clone() }).await.unwrap();
+    let (handle, addr) = start(server).await;
+    
+    let (events_tx, mut events) = mpsc::unbounded_channel();
+    let client = connect(addr, StreamMode::PerCall).await;
+    client.register_service("recorder", Recorder { events: events_tx }).await.unwrap();
+    
+    // Client to server
+    client.send_datagram_raw("beacon.ping", Bytes::from("ping")).await.unwrap();
+    assert_eq!(next_event(&mut pings).await, "ping");
+    
+    // Server to client, directly and through a group
+    assert!(join(&client, "lobby").await);
+    let session = groups.members("lobby").await.remove(0);
+    session.send_datagram("recorder.record", &"pong".to_string()).unwrap();
+    assert_eq!(next_event(&mut events).await, "start pong");
+    assert_eq!(next_event(&mut events).await, "end pong");
+    assert_eq!(groups.broadcast_datagram("lobby", "recorder.record", &"all".to_string()).await.unwrap(), 1);
+    assert_eq!(next_event(&mut events).await, "start all");
+    assert_eq!(next_event(&mut events).await, "end all");
+    
+    // Datagrams too large for a packet are refused by either side before anything is sent
+    let oversized = Bytes::from(vec![b'x'; client.max_datagram_size().await.unwrap()]);
+    let result = client.send_datagram_raw("beacon.ping", oversized.clone()).await;
+    assert!(matches!(result, Err(Error::InvalidArgument(_))));
+    let oversized = Bytes::from(vec![b'x'; session.max_datagram_size().unwrap()]);
+    assert!(matches!(session.send_datagram_raw("recorder.record", oversized.clone()), Err(Error::InvalidArgument(_))));
+    let result = groups.broadcast_datagram_raw("lobby", "recorder.record", oversized).await;
+    assert!(matches!(result, Err(Error::InvalidArgument(_))));
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}
+
+/// Client service answering calls made by the server
+struct Callee {
+    /// Receives `started` when a `stall` call starts and `dropped` when it is cancelled
+    events: mpsc::UnboundedSender<String>,
+}
+
+/// Reports `dropped` when the handler holding it is cancelled
+struct DropReport(mpsc::UnboundedSender<String>);
+
+impl Drop for DropReport {
+    fn drop(&mut self) {
+        let _ = self.0.send("dropped".to_string());
+    }
+}
+
+#[async_trait]
+impl Service for Callee {
+    async fn call(&self, method: &str, _payload: Bytes) -> Result<Bytes, Error> {
+        let context = CallContext::current().unwrap();
+        match method {
+            "id" => Ok(Bytes::from(context.request_id().to_string())),
+            "stall" => {
+                let _report = DropReport(self.events.clone());
+                let _ = self.events.send("started".to_string());
+                std::future::pending().await
+            }
+            _ => Err(Error::MethodNotFound(method.to_string())),
+        }
+    }
+    
+    fn methods(&self) -> Vec<String> {
+        vec!["id".to_string(), "stall".to_string()]
+    }
+}
+
+#[tokio::test]
+async fn servers_push_to_and_call_their_clients() {
+    let server = Server::new(Config { timeout_ms: 200, ..server_config() }).await.unwrap();
+    let groups = server.groups();
+    server.register_context_service("rooms", Rooms { groups: groups.clone() }).await.unwrap();
+    let (handle, addr) = start(server).await;
+    
+    let (events_tx, mut events) = mpsc::unbounded_channel();
+    let client = connect(addr, StreamMode::Shared).await;
+    client.register_service("recorder", Recorder { events: events_tx.clone() }).await.unwrap();
+    client.register_service("callee", Callee { events: events_tx }).await.unwrap();
+    assert!(join(&client, "lobby").await);
+    let session = groups.members("lobby").await.remove(0);
+    
+    // Pushes reach the client's service
+    session.push("recorder.record", &"pushed".to_string()).await.unwrap();
+    assert_eq!(next_event(&mut events).await, "start pushed");
+    assert_eq!(next_event(&mut events).await, "end pushed");
+    
+    // Calls get their responses, each with its own request ID
+    let first = session.call_raw("callee.id", Bytes::new(), CallOptions::new()).await.unwrap().message;
+    let second = session.call_raw("callee.id", Bytes::new(), CallOptions::new()).await.unwrap().message;
+    assert_ne!(first, second);
+    
+    // Calls without a timeout of their own time out after the server's, cancelling the client's handler
+    let started = tokio::time::Instant::now();
+    let result = session.call_raw("callee.stall", Bytes::new(), CallOptions::new()).await;
+    assert!(matches!(result, Err(Error::Timeout)));
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(next_event(&mut events).await, "started");
+    assert_eq!(next_event(&mut events).await, "dropped");
+    
+    // Dropped calls are cancelled too
+    let call = tokio::spawn(async move {
+        session.call_raw("callee.stall", Bytes::new(), CallOptions::new().with_timeout(WAIT * 2)).await
+    });
+    assert_eq!(next_event(&mut events).await, "started");
+    call.abort();
+    assert_eq!(next_event(&mut events).await, "dropped");
+    
+    client.close().await.unwrap();
+    handle.shutdown().await;
+}